@@ -0,0 +1,91 @@
+-- Row-level security for per-company data isolation.
+-- The API connects as the table owner (which bypasses RLS); request handlers switch to
+-- e_signature_app with SET LOCAL ROLE and describe the caller through app.* settings.
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_roles WHERE rolname = 'e_signature_app') THEN
+        CREATE ROLE e_signature_app NOLOGIN;
+    END IF;
+END
+$$;
+
+GRANT e_signature_app TO CURRENT_USER;
+GRANT SELECT, INSERT, UPDATE, DELETE ON ALL TABLES IN SCHEMA public TO e_signature_app;
+GRANT USAGE, SELECT ON ALL SEQUENCES IN SCHEMA public TO e_signature_app;
+ALTER DEFAULT PRIVILEGES IN SCHEMA public GRANT SELECT, INSERT, UPDATE, DELETE ON TABLES TO e_signature_app;
+ALTER DEFAULT PRIVILEGES IN SCHEMA public GRANT USAGE, SELECT ON SEQUENCES TO e_signature_app;
+
+CREATE OR REPLACE FUNCTION app_scope() RETURNS TEXT
+    LANGUAGE sql STABLE
+    AS $$ SELECT COALESCE(current_setting('app.scope', true), '') $$;
+
+CREATE OR REPLACE FUNCTION app_company_id() RETURNS BIGINT
+    LANGUAGE sql STABLE
+    AS $$ SELECT NULLIF(current_setting('app.company_id', true), '')::BIGINT $$;
+
+CREATE OR REPLACE FUNCTION app_signer_id() RETURNS BIGINT
+    LANGUAGE sql STABLE
+    AS $$ SELECT NULLIF(current_setting('app.signer_id', true), '')::BIGINT $$;
+
+-- SECURITY DEFINER lookups keep the policies below from recursing into each other.
+CREATE OR REPLACE FUNCTION company_document_ids(p_company_id BIGINT) RETURNS SETOF BIGINT
+    LANGUAGE sql STABLE SECURITY DEFINER
+    AS $$ SELECT document_id FROM document WHERE company_id = p_company_id $$;
+
+CREATE OR REPLACE FUNCTION company_signer_ids(p_company_id BIGINT) RETURNS SETOF BIGINT
+    LANGUAGE sql STABLE SECURITY DEFINER
+    AS $$
+        SELECT ds.signer_id
+        FROM document_signer ds
+        INNER JOIN document d ON d.document_id = ds.document_id
+        WHERE d.company_id = p_company_id
+    $$;
+
+CREATE OR REPLACE FUNCTION signer_document_ids(p_signer_id BIGINT) RETURNS SETOF BIGINT
+    LANGUAGE sql STABLE SECURITY DEFINER
+    AS $$ SELECT document_id FROM document_signer WHERE signer_id = p_signer_id $$;
+
+ALTER TABLE document ENABLE ROW LEVEL SECURITY;
+
+CREATE POLICY document_tenant_read ON document FOR SELECT
+    USING (
+        app_scope() = 'all'
+        OR (app_scope() = 'company' AND company_id = app_company_id())
+        OR (app_scope() = 'signer' AND document_id IN (SELECT signer_document_ids(app_signer_id())))
+    );
+
+CREATE POLICY document_tenant_write ON document FOR ALL
+    USING (app_scope() = 'all' OR (app_scope() = 'company' AND company_id = app_company_id()))
+    WITH CHECK (app_scope() = 'all' OR (app_scope() = 'company' AND company_id = app_company_id()));
+
+ALTER TABLE document_signer ENABLE ROW LEVEL SECURITY;
+
+CREATE POLICY document_signer_tenant_read ON document_signer FOR SELECT
+    USING (
+        app_scope() = 'all'
+        OR (app_scope() = 'company' AND document_id IN (SELECT company_document_ids(app_company_id())))
+        OR (app_scope() = 'signer' AND signer_id = app_signer_id())
+    );
+
+CREATE POLICY document_signer_tenant_write ON document_signer FOR ALL
+    USING (
+        app_scope() = 'all'
+        OR (app_scope() = 'company' AND document_id IN (SELECT company_document_ids(app_company_id())))
+    )
+    WITH CHECK (
+        app_scope() = 'all'
+        OR (app_scope() = 'company' AND document_id IN (SELECT company_document_ids(app_company_id())))
+    );
+
+ALTER TABLE signer ENABLE ROW LEVEL SECURITY;
+
+CREATE POLICY signer_tenant_read ON signer FOR SELECT
+    USING (
+        app_scope() = 'all'
+        OR (app_scope() = 'company' AND signer_id IN (SELECT company_signer_ids(app_company_id())))
+        OR (app_scope() = 'signer' AND signer_id = app_signer_id())
+    );
+
+CREATE POLICY signer_tenant_write ON signer FOR ALL
+    USING (app_scope() = 'all')
+    WITH CHECK (app_scope() = 'all');
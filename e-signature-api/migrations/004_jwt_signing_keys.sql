@@ -0,0 +1,11 @@
+CREATE TABLE jwt_signing_key (
+    kid VARCHAR(36) PRIMARY KEY,
+    algorithm VARCHAR(16) NOT NULL,
+    private_key BYTEA NOT NULL,
+    public_key BYTEA NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    activates_at TIMESTAMPTZ NOT NULL,
+    retires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX jwt_signing_key_retires_at_idx ON jwt_signing_key (retires_at);
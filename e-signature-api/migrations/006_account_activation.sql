@@ -0,0 +1,14 @@
+-- Contas criadas antes da ativação obrigatória continuam podendo entrar.
+UPDATE user_account SET is_active = TRUE WHERE deleted_at IS NULL;
+
+CREATE TABLE account_activation (
+    account_activation_id BIGSERIAL PRIMARY KEY,
+    user_id BIGINT NOT NULL REFERENCES user_account(user_id),
+    code_hash CHAR(64) NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    attempts INT NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    used_at TIMESTAMPTZ NULL
+);
+
+CREATE INDEX account_activation_user_idx ON account_activation (user_id) WHERE used_at IS NULL;
@@ -0,0 +1,5 @@
+ALTER TABLE user_account
+    ADD COLUMN failed_login_attempts INT NOT NULL DEFAULT 0,
+    ADD COLUMN locked_until TIMESTAMPTZ NULL;
+
+ALTER TABLE otp_codes ADD COLUMN attempts INT NOT NULL DEFAULT 0;
@@ -0,0 +1,14 @@
+ALTER TABLE user_account
+    ADD COLUMN totp_secret BYTEA NULL,
+    ADD COLUMN totp_enabled BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN totp_last_step BIGINT NULL;
+
+CREATE TABLE totp_recovery_code (
+    totp_recovery_code_id BIGSERIAL PRIMARY KEY,
+    user_id BIGINT NOT NULL REFERENCES user_account(user_id),
+    code_hash CHAR(64) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    used_at TIMESTAMPTZ NULL
+);
+
+CREATE INDEX totp_recovery_code_user_idx ON totp_recovery_code (user_id) WHERE used_at IS NULL;
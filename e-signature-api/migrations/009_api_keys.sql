@@ -0,0 +1,14 @@
+CREATE TABLE api_key (
+    api_key_id BIGSERIAL PRIMARY KEY,
+    company_id BIGINT NOT NULL REFERENCES company(company_id),
+    name VARCHAR(100) NOT NULL,
+    key_prefix VARCHAR(16) NOT NULL,
+    key_hash CHAR(64) NOT NULL UNIQUE,
+    scopes TEXT[] NOT NULL DEFAULT '{}',
+    created_by BIGINT NOT NULL REFERENCES user_account(user_id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_used_at TIMESTAMPTZ NULL,
+    revoked_at TIMESTAMPTZ NULL
+);
+
+CREATE INDEX api_key_company_idx ON api_key (company_id) WHERE revoked_at IS NULL;
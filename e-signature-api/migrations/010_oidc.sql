@@ -0,0 +1,22 @@
+CREATE TABLE company_oidc_provider (
+    company_id BIGINT PRIMARY KEY REFERENCES company(company_id),
+    issuer VARCHAR(255) NOT NULL,
+    client_id VARCHAR(255) NOT NULL,
+    client_secret VARCHAR(255) NOT NULL,
+    auto_provision BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMPTZ NULL
+);
+
+CREATE TABLE oidc_login_state (
+    state CHAR(64) PRIMARY KEY,
+    company_id BIGINT NOT NULL REFERENCES company(company_id),
+    code_verifier VARCHAR(128) NOT NULL,
+    nonce VARCHAR(64) NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    used_at TIMESTAMPTZ NULL
+);
+
+-- Usuários da empresa além do dono (ex.: criados no primeiro login via SSO).
+ALTER TABLE user_account ADD COLUMN company_id BIGINT NULL REFERENCES company(company_id);
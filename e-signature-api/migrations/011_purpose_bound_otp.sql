@@ -0,0 +1,26 @@
+-- Os códigos antigos estão em texto puro e expiram em minutos; basta descartá-los.
+DELETE FROM otp_codes;
+
+ALTER TABLE otp_codes DROP CONSTRAINT IF EXISTS otp_codes_pkey;
+
+ALTER TABLE otp_codes
+    DROP COLUMN code,
+    ADD COLUMN otp_code_id BIGSERIAL PRIMARY KEY,
+    ADD COLUMN purpose VARCHAR(32) NOT NULL,
+    ADD COLUMN document_id BIGINT NULL REFERENCES document(document_id),
+    ADD COLUMN code_salt CHAR(32) NOT NULL,
+    ADD COLUMN code_hash CHAR(64) NOT NULL,
+    ADD COLUMN created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP;
+
+CREATE INDEX otp_codes_lookup_idx ON otp_codes (email, purpose, document_id) WHERE used = FALSE;
+
+CREATE TABLE otp_proof (
+    otp_proof_id BIGSERIAL PRIMARY KEY,
+    token_hash CHAR(64) NOT NULL UNIQUE,
+    email VARCHAR(255) NOT NULL,
+    purpose VARCHAR(32) NOT NULL,
+    document_id BIGINT NULL REFERENCES document(document_id),
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    consumed_at TIMESTAMPTZ NULL
+);
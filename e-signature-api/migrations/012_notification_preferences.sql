@@ -0,0 +1,2 @@
+-- Ordem de preferência dos canais (ex.: {telegram,whatsapp,email}); NULL usa a ordem padrão do servidor.
+ALTER TABLE user_account ADD COLUMN notification_channels TEXT[] NULL;
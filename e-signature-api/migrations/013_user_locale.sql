@@ -0,0 +1,2 @@
+-- Idioma preferido (pt-BR, en, es); NULL segue o Accept-Language da requisição.
+ALTER TABLE user_account ADD COLUMN locale VARCHAR(10) NULL;
@@ -0,0 +1,30 @@
+-- Toda mensagem enviada (OTP, convites, lembretes...) com o id do provedor e o status de entrega.
+CREATE TABLE outbound_message (
+    message_id BIGSERIAL PRIMARY KEY,
+    public_id VARCHAR(36) NOT NULL UNIQUE,
+    kind VARCHAR(32) NOT NULL,
+    otp_code_id BIGINT NULL REFERENCES otp_codes(otp_code_id) ON DELETE SET NULL,
+    document_id BIGINT NULL REFERENCES document(document_id),
+    channel VARCHAR(20) NULL,
+    recipient VARCHAR(255) NULL,
+    provider_sid VARCHAR(64) NULL UNIQUE,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending',
+    error_code VARCHAR(20) NULL,
+    error_message TEXT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX outbound_message_otp_idx ON outbound_message (otp_code_id) WHERE otp_code_id IS NOT NULL;
+CREATE INDEX outbound_message_document_idx ON outbound_message (document_id) WHERE document_id IS NOT NULL;
+
+-- Histórico das mudanças de status (envio e callbacks do provedor).
+CREATE TABLE outbound_message_event (
+    event_id BIGSERIAL PRIMARY KEY,
+    message_id BIGINT NOT NULL REFERENCES outbound_message(message_id) ON DELETE CASCADE,
+    status VARCHAR(20) NOT NULL,
+    error_code VARCHAR(20) NULL,
+    occurred_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX outbound_message_event_message_idx ON outbound_message_event (message_id);
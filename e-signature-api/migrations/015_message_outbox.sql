@@ -0,0 +1,10 @@
+-- outbound_message passa a ser a caixa de saída: a mensagem é gravada na mesma transação
+-- do OTP/lembrete e um worker faz o envio, com novas tentativas e dead-letter.
+ALTER TABLE outbound_message
+    ADD COLUMN payload JSONB NULL,
+    ADD COLUMN attempts INT NOT NULL DEFAULT 0,
+    ADD COLUMN next_attempt_at TIMESTAMPTZ NULL,
+    ADD COLUMN dead_lettered_at TIMESTAMPTZ NULL;
+
+CREATE INDEX outbound_message_outbox_idx ON outbound_message (next_attempt_at)
+    WHERE status = 'pending' AND payload IS NOT NULL;
@@ -0,0 +1,5 @@
+-- O embedding só é comparável com outro do mesmo modelo; ao trocar o modelo, o
+-- cadastro antigo é refeito a partir da foto do documento ou descartado.
+ALTER TABLE user_account
+    ADD COLUMN face_embedding_model VARCHAR(64) NULL,
+    ADD COLUMN face_enrolled_at TIMESTAMPTZ NULL;
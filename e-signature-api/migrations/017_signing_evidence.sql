@@ -0,0 +1,12 @@
+-- Provas coletadas de cada signatário durante a assinatura (ex.: verificação facial
+-- com prova de vida), para compor a trilha de evidências do documento.
+CREATE TABLE signing_evidence (
+    evidence_id BIGSERIAL PRIMARY KEY,
+    document_id BIGINT NOT NULL REFERENCES document(document_id),
+    signer_id BIGINT NOT NULL REFERENCES signer(signer_id),
+    kind VARCHAR(32) NOT NULL,
+    details JSONB NOT NULL,
+    recorded_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX signing_evidence_document_idx ON signing_evidence (document_id, signer_id);
@@ -0,0 +1,23 @@
+-- Histórico de todas as tentativas de verificação facial. O quadro ao vivo é dado
+-- biométrico (LGPD): guardamos o hash e, se houver chave configurada, uma cópia
+-- cifrada, apagada pelo job de retenção depois do prazo.
+CREATE TABLE face_verification_attempt (
+    attempt_id BIGSERIAL PRIMARY KEY,
+    signer_id BIGINT NOT NULL REFERENCES signer(signer_id),
+    document_id BIGINT NULL REFERENCES document(document_id),
+    outcome VARCHAR(32) NOT NULL,
+    similarity REAL NULL,
+    liveness_score REAL NULL,
+    model_version VARCHAR(64) NULL,
+    details JSONB NULL,
+    frame_sha256 CHAR(64) NULL,
+    frame_ciphertext BYTEA NULL,
+    frame_nonce BYTEA NULL,
+    frame_purged_at TIMESTAMPTZ NULL,
+    attempted_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX face_verification_attempt_signer_idx
+    ON face_verification_attempt (signer_id, document_id, attempted_at);
+CREATE INDEX face_verification_attempt_frame_idx
+    ON face_verification_attempt (attempted_at) WHERE frame_ciphertext IS NOT NULL;
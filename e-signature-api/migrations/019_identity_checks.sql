@@ -0,0 +1,12 @@
+-- Conferências do documento de identidade (RG/CNH) do signatário: o que o OCR leu,
+-- a comparação com o cadastro e a confiança de cada campo.
+CREATE TABLE identity_check (
+    check_id BIGSERIAL PRIMARY KEY,
+    signer_id BIGINT NOT NULL REFERENCES signer(signer_id),
+    document_type VARCHAR(16) NOT NULL,
+    status VARCHAR(16) NOT NULL,
+    details JSONB NOT NULL,
+    checked_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX identity_check_signer_idx ON identity_check (signer_id, checked_at);
@@ -0,0 +1,59 @@
+-- CPF e CNPJ passam a ser gravados sem pontuação: CPF com 11 dígitos e CNPJ com 14
+-- caracteres (dígitos ou, no CNPJ alfanumérico, letras maiúsculas).
+UPDATE signer
+SET national_id = regexp_replace(national_id, '[^0-9]', '', 'g')
+WHERE national_id ~ '[^0-9]';
+
+UPDATE company
+SET tax_id = upper(regexp_replace(tax_id, '[^0-9A-Za-z]', '', 'g'))
+WHERE tax_id ~ '[^0-9A-Z]';
+
+-- Signatários que só diferiam na pontuação viram um só. Fica o que tem usuário vinculado
+-- ou, entre iguais, o mais antigo; os demais são apagados e o histórico vai para ele.
+CREATE TEMP TABLE signer_merge AS
+SELECT signer_id, keep_id
+FROM (
+    SELECT signer_id,
+           first_value(signer_id) OVER (
+               PARTITION BY national_id ORDER BY user_id IS NULL, signer_id
+           ) AS keep_id
+    FROM signer
+    WHERE deleted_at IS NULL
+) duplicates
+WHERE signer_id <> keep_id;
+
+-- No mesmo documento, a assinatura feita por qualquer um dos duplicados vale para o que fica.
+UPDATE document_signer k
+SET signed_at = d.signed_at, status_id = d.status_id
+FROM signer_merge m, document_signer d
+WHERE d.signer_id = m.signer_id AND k.signer_id = m.keep_id AND k.document_id = d.document_id
+  AND k.signed_at IS NULL AND d.signed_at IS NOT NULL;
+
+DELETE FROM document_signer d
+USING signer_merge m
+WHERE d.signer_id = m.signer_id
+  AND EXISTS (
+      SELECT 1 FROM document_signer k
+      WHERE k.signer_id = m.keep_id AND k.document_id = d.document_id
+  );
+
+UPDATE document_signer t SET signer_id = m.keep_id FROM signer_merge m WHERE t.signer_id = m.signer_id;
+UPDATE signing_evidence t SET signer_id = m.keep_id FROM signer_merge m WHERE t.signer_id = m.signer_id;
+UPDATE face_verification_attempt t SET signer_id = m.keep_id FROM signer_merge m WHERE t.signer_id = m.signer_id;
+UPDATE identity_check t SET signer_id = m.keep_id FROM signer_merge m WHERE t.signer_id = m.signer_id;
+
+UPDATE signer s
+SET deleted_at = CURRENT_TIMESTAMP, updated_at = CURRENT_TIMESTAMP
+FROM signer_merge m
+WHERE s.signer_id = m.signer_id;
+
+DROP TABLE signer_merge;
+
+CREATE UNIQUE INDEX signer_national_id_key ON signer (national_id) WHERE deleted_at IS NULL;
+
+-- Registros antigos com dígitos verificadores errados continuam como estão; o formato
+-- vale para o que for gravado daqui em diante.
+ALTER TABLE signer
+    ADD CONSTRAINT signer_national_id_canonical CHECK (national_id ~ '^[0-9]{11}$') NOT VALID;
+ALTER TABLE company
+    ADD CONSTRAINT company_tax_id_canonical CHECK (tax_id ~ '^[0-9A-Z]{12}[0-9]{2}$') NOT VALID;
@@ -0,0 +1,16 @@
+-- Telefones de signatários em E.164. Os que vieram sem código de país são brasileiros
+-- (DDD + número); números que não se encaixam ficam como estão e são normalizados no
+-- envio. Códigos OTP expiram em minutos e não precisam ser convertidos.
+UPDATE signer
+SET phone_number = '+' || regexp_replace(phone_number, '[^0-9]', '', 'g')
+WHERE phone_number ~ '^\+' AND phone_number ~ '[^+0-9]';
+
+UPDATE signer
+SET phone_number = '+55' || regexp_replace(phone_number, '[^0-9]', '', 'g')
+WHERE phone_number !~ '^\+'
+  AND length(regexp_replace(phone_number, '[^0-9]', '', 'g')) IN (10, 11);
+
+UPDATE signer
+SET phone_number = '+' || regexp_replace(phone_number, '[^0-9]', '', 'g')
+WHERE phone_number !~ '^\+'
+  AND regexp_replace(phone_number, '[^0-9]', '', 'g') ~ '^55[0-9]{10,11}$';
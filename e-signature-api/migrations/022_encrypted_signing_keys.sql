@@ -0,0 +1,3 @@
+-- A chave privada passa a ser gravada cifrada (AES-256-GCM com JWT_KEY_ENCRYPTION_KEY).
+-- Linhas antigas ficam com nonce nulo até a API cifrá-las na inicialização.
+ALTER TABLE jwt_signing_key ADD COLUMN private_key_nonce BYTEA NULL;
@@ -0,0 +1,2 @@
+-- O código vai para os contatos gravados do e-mail, e nem todo contato tem telefone.
+ALTER TABLE otp_codes ALTER COLUMN phone_number DROP NOT NULL;
@@ -1,5 +1,7 @@
+use crate::services::i18n::{translate, translate_with, Locale};
 use dotenv::dotenv;
 use dptree;
+use reqwest::header::ACCEPT_LANGUAGE;
 use reqwest::Client;
 use serde_json::json;
 use std::env;
@@ -37,6 +39,15 @@ pub async fn run_bot() {
         .await;
 }
 
+/// Mensagem de uma resposta de erro da API: o `detail` do problem+json, ou o corpo cru.
+async fn error_detail(response: reqwest::Response) -> String {
+    let body = response.text().await.unwrap_or_default();
+    serde_json::from_str::<serde_json::Value>(&body)
+        .ok()
+        .and_then(|problem| problem["detail"].as_str().map(str::to_string))
+        .unwrap_or(body)
+}
+
 async fn handle_command(
     bot: Bot,
     msg: Message,
@@ -46,14 +57,16 @@ async fn handle_command(
     match cmd {
         Command::Start(link_token) => {
             let chat_id = msg.chat.id;
+            let locale = msg
+                .from()
+                .and_then(|user| user.language_code.as_deref())
+                .and_then(Locale::parse)
+                .unwrap_or_default();
 
             if link_token.is_empty() {
-                bot.send_message(
-                    chat_id,
-                    "Olá! Para vincular sua conta, acesse o painel e clique em *Conectar Telegram*.",
-                )
-                .parse_mode(teloxide::types::ParseMode::MarkdownV2)
-                .await?;
+                bot.send_message(chat_id, translate(locale, "bot.start_instructions"))
+                    .parse_mode(teloxide::types::ParseMode::MarkdownV2)
+                    .await?;
                 return Ok(());
             }
 
@@ -62,6 +75,7 @@ async fn handle_command(
             let confirm_url = format!("{}/telegram/confirm", api_url);
             let resp = client
                 .post(&confirm_url)
+                .header(ACCEPT_LANGUAGE, locale.tag())
                 .json(&json!({ "token": link_token, "chat_id": chat_id }))
                 .send()
                 .await;
@@ -69,14 +83,24 @@ async fn handle_command(
             let resp_json = match resp {
                 Ok(r) if r.status().is_success() => r.json::<serde_json::Value>().await.ok(),
                 Ok(r) => {
-                    let body = r.text().await.unwrap_or_default();
-                    bot.send_message(chat_id, format!("❌ Erro ao confirmar link: {}", body))
-                        .await?;
+                    let body = error_detail(r).await;
+                    bot.send_message(
+                        chat_id,
+                        translate_with(locale, "bot.confirm_failed", &[("error", &body)]),
+                    )
+                    .await?;
                     return Ok(());
                 }
                 Err(e) => {
-                    bot.send_message(chat_id, format!("Erro de comunicação: {}", e))
-                        .await?;
+                    bot.send_message(
+                        chat_id,
+                        translate_with(
+                            locale,
+                            "bot.communication_error",
+                            &[("error", &e.to_string())],
+                        ),
+                    )
+                    .await?;
                     return Ok(());
                 }
             };
@@ -87,18 +111,15 @@ async fn handle_command(
             {
                 Some(jwt) => jwt.to_string(),
                 None => {
-                    bot.send_message(
-                        chat_id,
-                        "⚠️ JWT do usuário não disponível. OTP não pode ser gerado.",
-                    )
-                    .await?;
+                    bot.send_message(chat_id, translate(locale, "bot.jwt_unavailable"))
+                        .await?;
                     return Ok(());
                 }
             };
 
-            bot.send_message(chat_id, "✅ Seu Telegram foi vinculado com sucesso!")
+            bot.send_message(chat_id, translate(locale, "bot.linked"))
                 .await?;
-            get_user_and_generate_otp(&client, &api_url, &user_jwt, chat_id, &bot).await?;
+            get_user_and_generate_otp(&client, &api_url, &user_jwt, chat_id, &bot, locale).await?;
         }
     }
 
@@ -111,21 +132,37 @@ async fn get_user_and_generate_otp(
     user_jwt: &str,
     chat_id: teloxide::types::ChatId,
     bot: &Bot,
+    locale: Locale,
 ) -> ResponseResult<()> {
     let me_url = format!("{}/api/users/me", api_url);
-    let user_resp = client.get(&me_url).bearer_auth(user_jwt).send().await;
+    let user_resp = client
+        .get(&me_url)
+        .bearer_auth(user_jwt)
+        .header(ACCEPT_LANGUAGE, locale.tag())
+        .send()
+        .await;
 
     let user_json = match user_resp {
         Ok(r) if r.status().is_success() => r.json::<serde_json::Value>().await.ok(),
         Ok(r) => {
-            let body = r.text().await.unwrap_or_default();
-            bot.send_message(chat_id, format!("❌ Erro ao recuperar usuário: {}", body))
-                .await?;
+            let body = error_detail(r).await;
+            bot.send_message(
+                chat_id,
+                translate_with(locale, "bot.user_fetch_failed", &[("error", &body)]),
+            )
+            .await?;
             return Ok(());
         }
         Err(e) => {
-            bot.send_message(chat_id, format!("Erro de comunicação: {}", e))
-                .await?;
+            bot.send_message(
+                chat_id,
+                translate_with(
+                    locale,
+                    "bot.communication_error",
+                    &[("error", &e.to_string())],
+                ),
+            )
+            .await?;
             return Ok(());
         }
     };
@@ -133,11 +170,12 @@ async fn get_user_and_generate_otp(
     if let Some(user) = user_json {
         if let Some(email) = user["email"].as_str() {
             let otp_url = format!("{}/otp/generate", api_url);
-            bot.send_message(chat_id, "🔐 Gerando seu código OTP...")
+            bot.send_message(chat_id, translate(locale, "bot.generating_otp"))
                 .await?;
 
             match client
                 .post(&otp_url)
+                .header(ACCEPT_LANGUAGE, locale.tag())
                 .json(&json!({ "email": email }))
                 .send()
                 .await
@@ -145,21 +183,24 @@ async fn get_user_and_generate_otp(
                 Ok(resp) => match resp.json::<serde_json::Value>().await {
                     Ok(json) => {
                         if let Some(code) = json["otp"].as_str() {
-                            bot.send_message(chat_id, format!("🔑 Seu código OTP é: {}", code))
-                                .parse_mode(teloxide::types::ParseMode::MarkdownV2)
-                                .await?;
+                            bot.send_message(
+                                chat_id,
+                                translate_with(locale, "bot.otp_code", &[("code", code)]),
+                            )
+                            .parse_mode(teloxide::types::ParseMode::MarkdownV2)
+                            .await?;
                         } else {
-                            bot.send_message(chat_id, "Erro ao interpretar o código OTP.")
+                            bot.send_message(chat_id, translate(locale, "bot.otp_parse_failed"))
                                 .await?;
                         }
                     }
                     Err(_) => {
-                        bot.send_message(chat_id, "Erro ao processar resposta da API.")
+                        bot.send_message(chat_id, translate(locale, "bot.api_response_failed"))
                             .await?;
                     }
                 },
                 Err(_) => {
-                    bot.send_message(chat_id, "Erro de comunicação com a API.")
+                    bot.send_message(chat_id, translate(locale, "bot.api_unreachable"))
                         .await?;
                 }
             }
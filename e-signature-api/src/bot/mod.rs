@@ -1,3 +1,4 @@
+#[allow(clippy::module_inception)]
 pub mod bot;
 
 pub use bot::run_bot;
@@ -0,0 +1,96 @@
+use crate::services::api_keys::{self, ApiKeyScope};
+use crate::services::auth::AuthenticatedUser;
+use crate::services::errors::AppError;
+use crate::services::policy;
+use crate::AppState;
+use actix_web::{delete, get, post, web, HttpResponse};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct CreateApiKeyPayload {
+    name: String,
+    scopes: Vec<ApiKeyScope>,
+    company_id: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct CompanyQuery {
+    company_id: Option<i64>,
+}
+
+fn company_or_error(user: &AuthenticatedUser, requested: Option<i64>) -> Result<i64, AppError> {
+    match policy::company_for_api_keys(user, requested) {
+        Some(company_id) => Ok(company_id),
+        None if policy::is_admin(user) && user.api_key_id.is_none() => {
+            Err(AppError::bad_request("company.id_required"))
+        }
+        None => Err(AppError::forbidden()),
+    }
+}
+
+#[post("/api-keys")]
+pub async fn create_api_key_handler(
+    state: web::Data<AppState>,
+    user: AuthenticatedUser,
+    body: web::Json<CreateApiKeyPayload>,
+) -> Result<HttpResponse, AppError> {
+    let company_id = company_or_error(&user, body.company_id)?;
+
+    let name = body.name.trim();
+    if name.is_empty() || name.chars().count() > 100 {
+        return Err(AppError::bad_request("api_key.invalid_name"));
+    }
+    if body.scopes.is_empty() {
+        return Err(AppError::bad_request("api_key.scope_required"));
+    }
+
+    let created = api_keys::create_api_key(
+        &state.postgres_client,
+        company_id,
+        user.user_id,
+        name,
+        &body.scopes,
+    )
+    .await
+    .map_err(|e| AppError::internal("api_key.create_failed", e))?;
+    // A chave em texto puro só aparece nesta resposta.
+    Ok(HttpResponse::Created().json(created))
+}
+
+#[get("/api-keys")]
+pub async fn list_api_keys_handler(
+    state: web::Data<AppState>,
+    user: AuthenticatedUser,
+    query: web::Query<CompanyQuery>,
+) -> Result<HttpResponse, AppError> {
+    let company_id = company_or_error(&user, query.company_id)?;
+
+    let keys = api_keys::list_api_keys(&state.postgres_client, company_id)
+        .await
+        .map_err(|e| AppError::internal("api_key.list_failed", e))?;
+    Ok(HttpResponse::Ok().json(keys))
+}
+
+#[delete("/api-keys/{id}")]
+pub async fn revoke_api_key_handler(
+    state: web::Data<AppState>,
+    user: AuthenticatedUser,
+    path: web::Path<i64>,
+    query: web::Query<CompanyQuery>,
+) -> Result<HttpResponse, AppError> {
+    let company_id = company_or_error(&user, query.company_id)?;
+
+    let revoked = api_keys::revoke_api_key(&state.postgres_client, company_id, path.into_inner())
+        .await
+        .map_err(|e| AppError::internal("api_key.revoke_failed", e))?;
+    if !revoked {
+        return Err(AppError::not_found("api_key.not_found"));
+    }
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(create_api_key_handler)
+        .service(list_api_keys_handler)
+        .service(revoke_api_key_handler);
+}
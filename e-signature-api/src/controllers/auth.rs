@@ -0,0 +1,347 @@
+use crate::services::activation::{self, ActivationOutcome};
+use crate::services::auth::AuthenticatedUser;
+use crate::services::errors::AppError;
+use crate::services::i18n;
+use crate::services::lockout;
+use crate::services::passwords;
+use crate::services::rate_limit;
+use crate::services::sessions;
+use crate::services::signing_keys;
+use crate::services::totp;
+use crate::services::users::models::User;
+use crate::AppState;
+use actix_web::{get, post, web, HttpResponse, Responder};
+use serde::Deserialize;
+use serde_json;
+
+#[derive(Deserialize)]
+pub struct LoginPayload {
+    email: String,
+    password: String,
+    totp_code: Option<String>,
+    recovery_code: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct RefreshPayload {
+    refresh_token: String,
+}
+
+#[derive(Deserialize)]
+pub struct ActivationPayload {
+    email: String,
+    code: String,
+}
+
+#[derive(Deserialize)]
+pub struct ResendActivationPayload {
+    email: String,
+}
+
+#[derive(Deserialize)]
+pub struct ForgotPasswordPayload {
+    email: String,
+}
+
+#[derive(Deserialize)]
+pub struct ResetPasswordPayload {
+    token: String,
+    new_password: String,
+}
+
+#[derive(Deserialize)]
+pub struct TotpConfirmPayload {
+    code: String,
+}
+
+#[derive(Deserialize)]
+pub struct ChangePasswordPayload {
+    current_password: String,
+    new_password: String,
+}
+
+#[get("/.well-known/jwks.json")]
+pub async fn jwks_handler() -> impl Responder {
+    HttpResponse::Ok()
+        .insert_header(("Cache-Control", "public, max-age=300"))
+        .json(signing_keys::jwks())
+}
+
+#[post("/auth/login")]
+pub async fn login_handler(
+    state: web::Data<AppState>,
+    body: web::Json<LoginPayload>,
+) -> Result<HttpResponse, AppError> {
+    let account_key = format!("login:{}", body.email.to_lowercase());
+    state
+        .rate_limiter
+        .check(&account_key, rate_limit::ACCOUNT_LIMIT)
+        .map_err(AppError::TooManyRequests)?;
+
+    let user = sqlx::query_as::<_, User>("SELECT user_id, email, password_hash, role, created_at, updated_at, deleted_at, is_active FROM user_account WHERE email = $1 AND deleted_at IS NULL")
+        .bind(&body.email)
+        .fetch_optional(&state.postgres_client)
+        .await?
+        .ok_or_else(|| AppError::unauthorized("auth.invalid_credentials"))?;
+
+    if let Some(locked_until) = lockout::locked_until(&state.postgres_client, user.user_id).await? {
+        return Err(AppError::TooManyRequests(lockout::retry_after(
+            locked_until,
+        )));
+    }
+
+    let valid_password = bcrypt::verify(&body.password, &user.password_hash).unwrap_or(false);
+
+    if !valid_password {
+        if let Ok(Some(locked_until)) =
+            lockout::record_failure(&state.postgres_client, user.user_id).await
+        {
+            return Err(AppError::TooManyRequests(lockout::retry_after(
+                locked_until,
+            )));
+        }
+        return Err(AppError::unauthorized("auth.invalid_credentials"));
+    }
+
+    if totp::is_enabled(&state.postgres_client, user.user_id).await? {
+        let second_factor = match (&body.totp_code, &body.recovery_code) {
+            (Some(code), _) => totp::verify_code(&state.postgres_client, user.user_id, code).await,
+            (None, Some(code)) => {
+                totp::use_recovery_code(&state.postgres_client, user.user_id, code).await
+            }
+            (None, None) => {
+                return Err(AppError::unauthorized("auth.totp_required")
+                    .with_extension("totp_required", true))
+            }
+        };
+
+        if !second_factor.map_err(|e| AppError::internal("auth.token_create_failed", e))? {
+            if let Ok(Some(locked_until)) =
+                lockout::record_failure(&state.postgres_client, user.user_id).await
+            {
+                return Err(AppError::TooManyRequests(lockout::retry_after(
+                    locked_until,
+                )));
+            }
+            return Err(
+                AppError::unauthorized("auth.totp_invalid").with_extension("totp_required", true)
+            );
+        }
+    }
+
+    if let Err(e) = lockout::record_success(&state.postgres_client, user.user_id).await {
+        eprintln!("Falha ao zerar tentativas de login: {:?}", e);
+    }
+
+    if !user.is_active {
+        return Err(AppError::forbidden_for("auth.account_not_activated")
+            .with_extension("message", i18n::t("auth.confirm_activation")));
+    }
+
+    let tokens = sessions::start_session(&state.postgres_client, user.user_id)
+        .await
+        .map_err(|e| AppError::internal("auth.token_create_failed", e))?;
+    Ok(HttpResponse::Ok().json(tokens))
+}
+
+#[post("/auth/refresh")]
+pub async fn refresh_handler(
+    state: web::Data<AppState>,
+    body: web::Json<RefreshPayload>,
+) -> Result<HttpResponse, AppError> {
+    let tokens = sessions::rotate(&state.postgres_client, &body.refresh_token)
+        .await
+        .map_err(|e| AppError::internal("auth.refresh_failed", e))?
+        .ok_or_else(|| AppError::unauthorized("auth.refresh_invalid"))?;
+    Ok(HttpResponse::Ok().json(tokens))
+}
+
+#[post("/auth/logout")]
+pub async fn logout_handler(
+    state: web::Data<AppState>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    if let Some(claims) = &user.claims {
+        sessions::logout(&state.postgres_client, claims)
+            .await
+            .map_err(|e| AppError::internal("auth.logout_failed", e))?;
+    }
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[post("/auth/logout-all")]
+pub async fn logout_all_handler(
+    state: web::Data<AppState>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    sessions::revoke_all_sessions(&state.postgres_client, user.user_id)
+        .await
+        .map_err(|e| AppError::internal("session.revoke_failed", e))?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[post("/auth/activate")]
+pub async fn activate_handler(
+    state: web::Data<AppState>,
+    body: web::Json<ActivationPayload>,
+) -> Result<HttpResponse, AppError> {
+    match activation::activate(&state.postgres_client, &body.email, &body.code)
+        .await
+        .map_err(|e| AppError::internal("activation.failed", e))?
+    {
+        ActivationOutcome::Activated => Ok(HttpResponse::Ok()
+            .json(serde_json::json!({"message": i18n::t("activation.activated")}))),
+        ActivationOutcome::AlreadyActive => Err(AppError::conflict("activation.already_active")),
+        ActivationOutcome::InvalidCode => Err(AppError::bad_request("activation.invalid_code")),
+    }
+}
+
+#[post("/auth/activate/resend")]
+pub async fn resend_activation_handler(
+    state: web::Data<AppState>,
+    body: web::Json<ResendActivationPayload>,
+) -> HttpResponse {
+    if let Err(e) = activation::send_activation_code(&state.postgres_client, &body.email).await {
+        eprintln!("Falha ao reenviar código de ativação: {:?}", e);
+    }
+
+    HttpResponse::Accepted().json(serde_json::json!({
+        "message": i18n::t("activation.resent")
+    }))
+}
+
+#[post("/auth/password/forgot")]
+pub async fn forgot_password_handler(
+    state: web::Data<AppState>,
+    body: web::Json<ForgotPasswordPayload>,
+) -> HttpResponse {
+    if let Err(e) = passwords::request_reset(&state.postgres_client, &body.email).await {
+        eprintln!("Falha ao gerar token de redefinição de senha: {:?}", e);
+    }
+
+    // Mesma resposta exista ou não a conta, para não revelar e-mails cadastrados.
+    HttpResponse::Accepted().json(serde_json::json!({
+        "message": i18n::t("password.reset_requested")
+    }))
+}
+
+#[post("/auth/password/reset")]
+pub async fn reset_password_handler(
+    state: web::Data<AppState>,
+    body: web::Json<ResetPasswordPayload>,
+) -> Result<HttpResponse, AppError> {
+    check_password_strength(&body.new_password)?;
+
+    let reset = passwords::reset_password(&state.postgres_client, &body.token, &body.new_password)
+        .await
+        .map_err(|e| AppError::internal("password.reset_failed", e))?;
+    if !reset {
+        return Err(AppError::bad_request("password.reset_invalid"));
+    }
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[post("/auth/password/change")]
+pub async fn change_password_handler(
+    state: web::Data<AppState>,
+    user: AuthenticatedUser,
+    body: web::Json<ChangePasswordPayload>,
+) -> Result<HttpResponse, AppError> {
+    check_password_strength(&body.new_password)?;
+
+    // A senha atual é conferida aqui também: mesmos limites e bloqueio do login, para
+    // que uma sessão roubada não sirva para testar senhas à vontade.
+    let account_key = format!("password_change:{}", user.user_id);
+    state
+        .rate_limiter
+        .check(&account_key, rate_limit::ACCOUNT_LIMIT)
+        .map_err(AppError::TooManyRequests)?;
+    if let Some(locked_until) = lockout::locked_until(&state.postgres_client, user.user_id).await? {
+        return Err(AppError::TooManyRequests(lockout::retry_after(
+            locked_until,
+        )));
+    }
+
+    let session_id = user.claims.as_ref().and_then(|c| c.sid.as_deref());
+    let changed = passwords::change_password(
+        &state.postgres_client,
+        user.user_id,
+        session_id,
+        &body.current_password,
+        &body.new_password,
+    )
+    .await
+    .map_err(|e| AppError::internal("password.change_failed", e))?;
+
+    if !changed {
+        if let Ok(Some(locked_until)) =
+            lockout::record_failure(&state.postgres_client, user.user_id).await
+        {
+            return Err(AppError::TooManyRequests(lockout::retry_after(
+                locked_until,
+            )));
+        }
+        return Err(AppError::unauthorized("password.current_incorrect"));
+    }
+
+    if let Err(e) = lockout::record_success(&state.postgres_client, user.user_id).await {
+        eprintln!("Falha ao zerar tentativas de login: {:?}", e);
+    }
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[post("/auth/totp/enroll")]
+pub async fn totp_enroll_handler(
+    state: web::Data<AppState>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let (email,): (String,) = sqlx::query_as("SELECT email FROM user_account WHERE user_id = $1")
+        .bind(user.user_id)
+        .fetch_optional(&state.postgres_client)
+        .await
+        .map_err(|e| AppError::internal("totp.enroll_failed", e))?
+        .ok_or_else(|| AppError::not_found("user.not_found"))?;
+
+    let enrollment = totp::begin_enrollment(&state.postgres_client, user.user_id, &email)
+        .await
+        .map_err(|e| AppError::internal("totp.enroll_failed", e))?
+        .ok_or_else(|| AppError::conflict("totp.already_enabled"))?;
+    Ok(HttpResponse::Ok().json(enrollment))
+}
+
+#[post("/auth/totp/confirm")]
+pub async fn totp_confirm_handler(
+    state: web::Data<AppState>,
+    user: AuthenticatedUser,
+    body: web::Json<TotpConfirmPayload>,
+) -> Result<HttpResponse, AppError> {
+    let recovery_codes = totp::confirm_enrollment(&state.postgres_client, user.user_id, &body.code)
+        .await
+        .map_err(|e| AppError::internal("totp.confirm_failed", e))?
+        .ok_or_else(|| AppError::bad_request("totp.confirm_invalid"))?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": i18n::t("totp.enabled"),
+        "recovery_codes": recovery_codes
+    })))
+}
+
+fn check_password_strength(password: &str) -> Result<(), AppError> {
+    if passwords::is_acceptable_password(password) {
+        return Ok(());
+    }
+    Err(AppError::bad_request("password.too_short").with_var("min", passwords::MIN_PASSWORD_LENGTH))
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(login_handler)
+        .service(refresh_handler)
+        .service(logout_handler)
+        .service(logout_all_handler)
+        .service(activate_handler)
+        .service(resend_activation_handler)
+        .service(forgot_password_handler)
+        .service(reset_password_handler)
+        .service(change_password_handler)
+        .service(totp_enroll_handler)
+        .service(totp_confirm_handler);
+}
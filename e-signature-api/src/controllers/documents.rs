@@ -1,8 +1,19 @@
-use crate::services::documents::models::{CreateDocument, Document, UpdateDocument};
+use crate::services::auth::AuthenticatedUser;
+use crate::services::deliveries;
+use crate::services::documents::evidence;
+use crate::services::documents::models::{
+    CreateDocument, Document, UpdateDocument, DOCUMENT_STATUS_PENDING, DOCUMENT_STATUS_SIGNED,
+};
+use crate::services::documents::notifications as document_notifications;
 use crate::services::documents::services as document_service;
+use crate::services::errors::AppError;
+use crate::services::i18n;
+use crate::services::phones;
+use crate::services::policy;
+use crate::services::tax_ids;
 use crate::AppState;
-use actix_multipart::Multipart;
-use actix_web::{delete, get, post, put, web, Error, HttpResponse, Responder};
+use actix_multipart::{Multipart, MultipartError};
+use actix_web::{delete, get, post, put, web, HttpResponse};
 use futures_util::TryStreamExt;
 use sanitize_filename::sanitize;
 use sha2::{Digest, Sha256};
@@ -10,19 +21,29 @@ use std::fs::{create_dir_all, File};
 use std::io::Write;
 use uuid::Uuid;
 
+fn invalid_upload(error: MultipartError) -> AppError {
+    AppError::bad_request("document.invalid_upload").with_var("error", error)
+}
+
+fn document_not_found(doc_id: i64) -> AppError {
+    AppError::not_found("document.not_found_id").with_var("id", doc_id)
+}
+
 #[post("/documents")]
 async fn create_document_handler(
     state: web::Data<AppState>,
+    user: AuthenticatedUser,
     mut payload: Multipart,
-) -> Result<HttpResponse, Error> {
+) -> Result<HttpResponse, AppError> {
     let mut create_request = CreateDocument::default();
 
     let mut document_file_data: Vec<u8> = Vec::new();
     let mut document_filename = String::new();
     let mut photo_id_file_data: Vec<u8> = Vec::new();
     let mut photo_id_filename = String::new();
+    let mut requested_company_id: Option<i64> = None;
 
-    while let Some(mut field) = payload.try_next().await? {
+    while let Some(mut field) = payload.try_next().await.map_err(invalid_upload)? {
         let field_name_opt = field
             .content_disposition()
             .and_then(|d| d.get_name())
@@ -34,7 +55,7 @@ async fn create_document_handler(
 
         if let Some(fname) = filename_opt {
             let mut file_bytes = Vec::new();
-            while let Some(chunk) = field.try_next().await? {
+            while let Some(chunk) = field.try_next().await.map_err(invalid_upload)? {
                 file_bytes.extend_from_slice(&chunk);
             }
 
@@ -53,13 +74,13 @@ async fn create_document_handler(
             }
         } else if let Some(name) = field_name_opt {
             let mut field_data_bytes = Vec::new();
-            while let Some(chunk) = field.try_next().await? {
+            while let Some(chunk) = field.try_next().await.map_err(invalid_upload)? {
                 field_data_bytes.extend_from_slice(&chunk);
             }
             let value = String::from_utf8(field_data_bytes).unwrap_or_default();
 
             match name.as_str() {
-                "company_id" => create_request.company_id = value.parse().unwrap_or(0),
+                "company_id" => requested_company_id = value.parse().ok(),
                 "status_id" => create_request.status_id = value.parse().unwrap_or(1),
                 "signer_full_name" => create_request.signer_full_name = Some(value),
                 "signer_phone_number" => create_request.signer_phone_number = Some(value),
@@ -70,27 +91,53 @@ async fn create_document_handler(
         }
     }
 
+    create_request.company_id = match policy::company_for_new_document(&user, requested_company_id)
+    {
+        Some(company_id) => company_id,
+        None if policy::is_admin(&user) => {
+            return Err(AppError::bad_request("company.id_required"))
+        }
+        None => return Err(AppError::forbidden()),
+    };
+
+    // Validado antes de gravar os arquivos, para não deixar uploads órfãos.
+    create_request.signer_national_id = Some(
+        create_request
+            .signer_national_id
+            .as_deref()
+            .and_then(tax_ids::normalize_cpf)
+            .ok_or_else(|| AppError::unprocessable("signer.invalid_national_id"))?,
+    );
+    create_request.signer_phone_number = Some(
+        create_request
+            .signer_phone_number
+            .as_deref()
+            .and_then(phones::normalize)
+            .ok_or_else(|| AppError::unprocessable("phone.invalid"))?,
+    );
+
     if document_file_data.is_empty() {
-        return Ok(HttpResponse::BadRequest().json("Arquivo PDF do documento é obrigatório."));
+        return Err(AppError::bad_request("document.file_required"));
     }
     if photo_id_file_data.is_empty() {
-        return Ok(
-            HttpResponse::BadRequest().json("Arquivo da foto de identificação é obrigatório.")
-        );
+        return Err(AppError::bad_request("document.photo_required"));
     }
 
+    let store_failed = |e: std::io::Error| AppError::internal("document.create_failed", e);
     let upload_dir = "./uploads";
-    create_dir_all(upload_dir)?;
+    create_dir_all(upload_dir).map_err(store_failed)?;
 
     let doc_unique_filename = format!("{}-{}", Uuid::new_v4(), document_filename);
     let doc_file_path = format!("{}/{}", upload_dir, doc_unique_filename);
-    let mut doc_file = File::create(&doc_file_path)?;
-    doc_file.write_all(&document_file_data)?;
+    File::create(&doc_file_path)
+        .and_then(|mut file| file.write_all(&document_file_data))
+        .map_err(store_failed)?;
 
     let photo_unique_filename = format!("{}-{}", Uuid::new_v4(), photo_id_filename);
     let photo_file_path = format!("{}/{}", upload_dir, photo_unique_filename);
-    let mut photo_file = File::create(&photo_file_path)?;
-    photo_file.write_all(&photo_id_file_data)?;
+    File::create(&photo_file_path)
+        .and_then(|mut file| file.write_all(&photo_id_file_data))
+        .map_err(store_failed)?;
 
     let mut hasher = Sha256::new();
     hasher.update(&document_file_data);
@@ -101,78 +148,168 @@ async fn create_document_handler(
     create_request.hash_sha256 = Some(hash_hex);
     create_request.photo_id_url = Some(photo_file_path);
 
-    match document_service::create_document_and_signer(&state.postgres_client, create_request).await
+    let scope = policy::document_scope(&user);
+    let document =
+        document_service::create_document_and_signer(&state.postgres_client, scope, create_request)
+            .await
+            .map_err(|e| match e {
+                AppError::Database(e) => AppError::internal("document.create_failed", e),
+                e => e,
+            })?;
+    if let Err(e) =
+        document_notifications::send_invitations(&state.postgres_client, scope, &document).await
     {
-        Ok(document) => Ok(HttpResponse::Created().json(document)),
-        Err(e) => Ok(
-            HttpResponse::InternalServerError().json(format!("Falha ao criar documento: {}", e))
-        ),
+        eprintln!("Falha ao enviar convites de assinatura: {:?}", e);
     }
+    Ok(HttpResponse::Created().json(document))
 }
 
 #[get("/documents")]
-async fn get_documents_handler(state: web::Data<AppState>) -> impl Responder {
-    match document_service::get_all_documents(&state.postgres_client).await {
-        Ok(documents) => HttpResponse::Ok().json(documents),
-        Err(_) => HttpResponse::InternalServerError()
-            .json(serde_json::json!("Failed to retrieve documents.")),
-    }
+async fn get_documents_handler(
+    state: web::Data<AppState>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let scope = policy::document_scope(&user);
+    let documents = document_service::get_all_documents(&state.postgres_client, scope)
+        .await
+        .map_err(|e| AppError::internal("document.list_failed", e))?;
+    Ok(HttpResponse::Ok().json(documents))
 }
 
 #[get("/documents/{id}")]
 async fn get_document_by_id_handler(
     state: web::Data<AppState>,
+    user: AuthenticatedUser,
     path: web::Path<i64>,
-) -> impl Responder {
+) -> Result<HttpResponse, AppError> {
     let doc_id = path.into_inner();
-    match document_service::get_document_by_id(&state.postgres_client, doc_id).await {
-        Ok(Some(document)) => HttpResponse::Ok().json(document),
-        Ok(None) => HttpResponse::NotFound().json(serde_json::json!(format!(
-            "Document with ID {} not found.",
-            doc_id
-        ))),
-        Err(_) => HttpResponse::InternalServerError()
-            .json(serde_json::json!("Failed to retrieve document.")),
-    }
+    let scope = policy::document_scope(&user);
+    let document = document_service::get_document_by_id(&state.postgres_client, scope, doc_id)
+        .await
+        .map_err(|e| AppError::internal("document.fetch_failed", e))?
+        .ok_or_else(|| document_not_found(doc_id))?;
+    Ok(HttpResponse::Ok().json(document))
 }
 
 #[put("/documents/{id}")]
 async fn update_document_handler(
     state: web::Data<AppState>,
+    user: AuthenticatedUser,
     path: web::Path<i64>,
     body: web::Json<UpdateDocument>,
-) -> impl Responder {
+) -> Result<HttpResponse, AppError> {
     let doc_id = path.into_inner();
-    match document_service::update_document(&state.postgres_client, doc_id, body.into_inner()).await
+    let previous = ensure_can_manage(&state, &user, doc_id).await?;
+    let scope = policy::document_scope(&user);
+
+    let document =
+        document_service::update_document(&state.postgres_client, scope, doc_id, body.into_inner())
+            .await
+            .map_err(|e| AppError::internal("document.update_failed", e))?
+            .ok_or_else(|| document_not_found(doc_id))?;
+    if document.status_id == DOCUMENT_STATUS_SIGNED && previous.status_id != DOCUMENT_STATUS_SIGNED
     {
-        Ok(Some(document)) => HttpResponse::Ok().json(document),
-        Ok(None) => HttpResponse::NotFound().json(serde_json::json!(format!(
-            "Document with ID {} not found.",
-            doc_id
-        ))),
-        Err(_) => HttpResponse::InternalServerError()
-            .json(serde_json::json!("Failed to update document.")),
+        if let Err(e) = document_notifications::send_completion_notices(
+            &state.postgres_client,
+            scope,
+            &document,
+        )
+        .await
+        {
+            eprintln!("Falha ao enviar avisos de conclusão: {:?}", e);
+        }
     }
+    Ok(HttpResponse::Ok().json(document))
+}
+
+#[post("/documents/{id}/reminders")]
+async fn send_reminders_handler(
+    state: web::Data<AppState>,
+    user: AuthenticatedUser,
+    path: web::Path<i64>,
+) -> Result<HttpResponse, AppError> {
+    let doc_id = path.into_inner();
+    let document = ensure_can_manage(&state, &user, doc_id).await?;
+    if document.status_id != DOCUMENT_STATUS_PENDING {
+        return Err(AppError::conflict("document.reminders_not_pending"));
+    }
+
+    let scope = policy::document_scope(&user);
+    let reminded = document_notifications::send_reminders(&state.postgres_client, scope, &document)
+        .await
+        .map_err(|e| AppError::internal("document.reminders_failed", e))?;
+    Ok(HttpResponse::Accepted().json(serde_json::json!({ "reminded": reminded })))
+}
+
+/// Status de entrega de convites, lembretes e avisos de conclusão do documento.
+#[get("/documents/{id}/deliveries")]
+async fn get_document_deliveries_handler(
+    state: web::Data<AppState>,
+    user: AuthenticatedUser,
+    path: web::Path<i64>,
+) -> Result<HttpResponse, AppError> {
+    let doc_id = path.into_inner();
+    ensure_can_manage(&state, &user, doc_id).await?;
+    let messages = deliveries::list_for_document(&state.postgres_client, doc_id)
+        .await
+        .map_err(|e| AppError::internal("delivery.list_failed", e))?;
+    Ok(HttpResponse::Ok().json(messages))
+}
+
+/// Evidências coletadas dos signatários, como a verificação facial com prova de vida.
+#[get("/documents/{id}/evidence")]
+async fn get_document_evidence_handler(
+    state: web::Data<AppState>,
+    user: AuthenticatedUser,
+    path: web::Path<i64>,
+) -> Result<HttpResponse, AppError> {
+    let doc_id = path.into_inner();
+    ensure_can_manage(&state, &user, doc_id).await?;
+    let records = evidence::list_for_document(&state.postgres_client, doc_id)
+        .await
+        .map_err(|e| AppError::internal("document.evidence_failed", e))?;
+    Ok(HttpResponse::Ok().json(records))
 }
 
 #[delete("/documents/{id}")]
 async fn delete_document_handler(
     state: web::Data<AppState>,
+    user: AuthenticatedUser,
     path: web::Path<i64>,
-) -> impl Responder {
+) -> Result<HttpResponse, AppError> {
     let doc_id = path.into_inner();
-    match document_service::delete_document(&state.postgres_client, doc_id).await {
-        Ok(0) => HttpResponse::NotFound().json(serde_json::json!(format!(
-            "Document with ID {} not found.",
-            doc_id
-        ))),
-        Ok(_) => HttpResponse::Ok().json(serde_json::json!(format!(
-            "Document with ID {} successfully deleted.",
-            doc_id
-        ))),
-        Err(_) => HttpResponse::InternalServerError()
-            .json(serde_json::json!("Failed to delete document.")),
+    ensure_can_manage(&state, &user, doc_id).await?;
+    match document_service::delete_document(
+        &state.postgres_client,
+        policy::document_scope(&user),
+        doc_id,
+    )
+    .await
+    .map_err(|e| AppError::internal("document.delete_failed", e))?
+    {
+        0 => Err(document_not_found(doc_id)),
+        _ => Ok(HttpResponse::Ok().json(serde_json::json!(i18n::t_with(
+            "document.deleted",
+            &[("id", &doc_id.to_string())]
+        )))),
+    }
+}
+
+/// O documento, se está no escopo do usuário e ele pode gerenciá-lo.
+async fn ensure_can_manage(
+    state: &AppState,
+    user: &AuthenticatedUser,
+    doc_id: i64,
+) -> Result<Document, AppError> {
+    let scope = policy::document_scope(user);
+    let document = document_service::get_document_by_id(&state.postgres_client, scope, doc_id)
+        .await
+        .map_err(|e| AppError::internal("document.fetch_failed", e))?
+        .ok_or_else(|| document_not_found(doc_id))?;
+    if !policy::can_manage_document(user, &document) {
+        return Err(AppError::forbidden());
     }
+    Ok(document)
 }
 
 /*#[post("/signers")]
@@ -183,7 +320,7 @@ async fn add_signer_handler(
     match document_service::add_signer_to_document(&state.postgres_client, body.into_inner()).await {
         Ok(signer) => HttpResponse::Created().json(signer),
         Err(e) => HttpResponse::InternalServerError()
-            .json(serde_json::json!(format!("Failed to add signer: {}", e))),
+            .json(serde_json::json!(i18n::t_with("signer.add_failed", &[("error", &e.to_string())]))),
     }
 }*/
 
@@ -196,7 +333,7 @@ async fn get_signers_handler(
     match document_service::get_signers_for_document(&state.postgres_client, doc_id).await {
         Ok(signers) => HttpResponse::Ok().json(signers),
         Err(_) => {
-            HttpResponse::InternalServerError().json(serde_json::json!("Failed to retrieve signers."))
+            HttpResponse::InternalServerError().json(serde_json::json!(i18n::t("signer.list_failed")))
         }
     }
 }*/
@@ -208,6 +345,9 @@ pub fn config(cfg: &mut web::ServiceConfig) {
             .service(get_documents_handler)
             .service(get_document_by_id_handler)
             .service(update_document_handler)
+            .service(send_reminders_handler)
+            .service(get_document_deliveries_handler)
+            .service(get_document_evidence_handler)
             .service(delete_document_handler)
             //.service(add_signer_handler)
            //.service(get_signers_handler)
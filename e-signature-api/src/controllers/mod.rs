@@ -1,4 +1,8 @@
+pub mod api_keys;
+pub mod auth;
 pub mod documents;
+pub mod oidc;
 pub mod otp;
 pub mod telegram;
 pub mod users;
+pub mod webhooks;
@@ -0,0 +1,123 @@
+use crate::services::auth::AuthenticatedUser;
+use crate::services::errors::AppError;
+use crate::services::oidc::{self, OidcError, UpsertOidcProvider};
+use crate::services::policy;
+use crate::AppState;
+use actix_web::{delete, get, put, web, HttpResponse};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct CallbackQuery {
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+}
+
+impl From<OidcError> for AppError {
+    fn from(error: OidcError) -> Self {
+        match error {
+            OidcError::NotConfigured => AppError::not_found("oidc.not_configured"),
+            OidcError::InvalidState => AppError::bad_request("oidc.invalid_state"),
+            OidcError::Provider(e) => AppError::bad_gateway("oidc.provider_failed", e),
+            OidcError::UnverifiedEmail => AppError::forbidden_for("oidc.unverified_email"),
+            OidcError::UnknownUser | OidcError::NotCompanyMember => {
+                AppError::forbidden_for("oidc.unknown_user")
+            }
+            OidcError::Database(e) => AppError::internal("oidc.login_failed", e),
+            OidcError::Service(e) => AppError::internal("oidc.login_failed", e),
+        }
+    }
+}
+
+#[get("/auth/oidc/{company_id}/authorize")]
+pub async fn oidc_authorize_handler(
+    state: web::Data<AppState>,
+    path: web::Path<i64>,
+) -> Result<HttpResponse, AppError> {
+    let url = oidc::authorization_url(&state.postgres_client, path.into_inner()).await?;
+    Ok(HttpResponse::Found()
+        .insert_header(("Location", url))
+        .finish())
+}
+
+#[get("/auth/oidc/callback")]
+pub async fn oidc_callback_handler(
+    state: web::Data<AppState>,
+    query: web::Query<CallbackQuery>,
+) -> Result<HttpResponse, AppError> {
+    if let Some(error) = &query.error {
+        return Err(
+            AppError::unauthorized("oidc.denied").with_extension("provider_error", error.as_str())
+        );
+    }
+    let (Some(code), Some(login_state)) = (&query.code, &query.state) else {
+        return Err(AppError::bad_request("oidc.missing_code"));
+    };
+
+    let tokens = oidc::complete_login(&state.postgres_client, login_state, code).await?;
+    Ok(HttpResponse::Ok().json(tokens))
+}
+
+#[get("/companies/{id}/oidc")]
+pub async fn get_oidc_provider_handler(
+    state: web::Data<AppState>,
+    user: AuthenticatedUser,
+    path: web::Path<i64>,
+) -> Result<HttpResponse, AppError> {
+    let company_id = path.into_inner();
+    if !policy::can_manage_company(&user, company_id) {
+        return Err(AppError::forbidden());
+    }
+    let provider = oidc::get_provider(&state.postgres_client, company_id)
+        .await
+        .map_err(|e| AppError::internal("oidc.load_failed", e))?
+        .ok_or_else(|| AppError::not_found("oidc.not_configured"))?;
+    Ok(HttpResponse::Ok().json(provider))
+}
+
+#[put("/companies/{id}/oidc")]
+pub async fn upsert_oidc_provider_handler(
+    state: web::Data<AppState>,
+    user: AuthenticatedUser,
+    path: web::Path<i64>,
+    body: web::Json<UpsertOidcProvider>,
+) -> Result<HttpResponse, AppError> {
+    let company_id = path.into_inner();
+    if !policy::can_manage_company(&user, company_id) {
+        return Err(AppError::forbidden());
+    }
+    if reqwest::Url::parse(&body.issuer).is_err() || body.client_id.trim().is_empty() {
+        return Err(AppError::bad_request("oidc.invalid_config"));
+    }
+    let provider = oidc::upsert_provider(&state.postgres_client, company_id, &body)
+        .await
+        .map_err(|e| AppError::internal("oidc.save_failed", e))?;
+    Ok(HttpResponse::Ok().json(provider))
+}
+
+#[delete("/companies/{id}/oidc")]
+pub async fn delete_oidc_provider_handler(
+    state: web::Data<AppState>,
+    user: AuthenticatedUser,
+    path: web::Path<i64>,
+) -> Result<HttpResponse, AppError> {
+    let company_id = path.into_inner();
+    if !policy::can_manage_company(&user, company_id) {
+        return Err(AppError::forbidden());
+    }
+    let deleted = oidc::delete_provider(&state.postgres_client, company_id)
+        .await
+        .map_err(|e| AppError::internal("oidc.delete_failed", e))?;
+    if deleted == 0 {
+        return Err(AppError::not_found("oidc.not_configured"));
+    }
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(oidc_authorize_handler)
+        .service(oidc_callback_handler)
+        .service(get_oidc_provider_handler)
+        .service(upsert_oidc_provider_handler)
+        .service(delete_oidc_provider_handler);
+}
@@ -1,104 +1,161 @@
-use crate::services::whatsapp::whatsapp;
-use actix_web::{post, web, HttpResponse, Responder};
-use chrono::{Duration, Utc};
-use rand::Rng;
+use crate::services::deliveries::{self, MessageContext, MessageKind};
+use crate::services::email::templates::{self, EmailTemplate};
+use crate::services::errors::AppError;
+use crate::services::i18n;
+use crate::services::notifications;
+use crate::services::otp_codes::{self, OtpPurpose, OtpVerification};
+use crate::services::outbox;
+use actix_web::{get, post, web, HttpResponse};
 use serde::{Deserialize, Serialize};
 
+use crate::services::rate_limit;
 use crate::AppState;
 
 #[derive(Deserialize)]
 pub struct OtpRequest {
     pub email: String,
-    pub phone_number: String,
+    #[serde(default)]
+    pub purpose: OtpPurpose,
+    pub document_id: Option<i64>,
 }
 
 #[derive(Serialize)]
 pub struct OtpResponse {
     pub message: String,
     pub expires_at: String,
+    pub delivery_id: String,
+    pub delivery_status: String,
 }
+
+fn check_purpose(purpose: OtpPurpose, document_id: Option<i64>) -> Result<(), AppError> {
+    match (purpose, document_id) {
+        (OtpPurpose::SignDocument, None) => Err(AppError::bad_request("otp.document_required")),
+        (OtpPurpose::Login | OtpPurpose::PasswordReset, Some(_)) => {
+            Err(AppError::bad_request("otp.document_not_allowed"))
+        }
+        _ => Ok(()),
+    }
+}
+
 #[post("/otp/generate")]
-pub async fn generate_otp(data: web::Data<AppState>, req: web::Json<OtpRequest>) -> impl Responder {
-    let otp_code: String = rand::thread_rng().gen_range(100_000..999_999).to_string();
-    let expires_at = Utc::now() + Duration::minutes(5);
+pub async fn generate_otp(
+    data: web::Data<AppState>,
+    req: web::Json<OtpRequest>,
+) -> Result<HttpResponse, AppError> {
+    check_purpose(req.purpose, req.document_id)?;
 
-    let query = r#"
-        INSERT INTO otp_codes (email, phone_number, code, expires_at, used)
-        VALUES ($1, $2, $3, $4, FALSE)
-        ON CONFLICT (email) DO UPDATE
-        SET code = EXCLUDED.code, 
-            expires_at = EXCLUDED.expires_at, 
-            phone_number = EXCLUDED.phone_number, 
-            used = FALSE
-    "#;
+    // O código só vai para contatos já gravados; um telefone vindo do cliente permitiria
+    // pedir o código do e-mail de outra pessoa e recebê-lo no próprio aparelho.
+    // O código de assinatura só vai para o signatário daquele documento.
+    let recipient = match (req.purpose, req.document_id) {
+        (OtpPurpose::SignDocument, Some(document_id)) => {
+            notifications::recipient_for_document_signer(
+                &data.postgres_client,
+                &req.email,
+                document_id,
+            )
+            .await?
+            .ok_or_else(|| AppError::forbidden_for("otp.not_document_signer"))?
+        }
+        _ => notifications::recipient_for_email(&data.postgres_client, &req.email)
+            .await?
+            .ok_or_else(|| AppError::not_found("otp.no_contact"))?,
+    };
+    // A prova sai para o e-mail gravado, o mesmo que a etapa facial confere.
+    let otp_email = recipient.email.clone().unwrap_or_else(|| req.email.clone());
 
-    if let Err(err) = sqlx::query(query)
-        .bind(&req.email)
-        .bind(&req.phone_number)
-        .bind(&otp_code)
-        .bind(expires_at)
-        .execute(&data.postgres_client)
-        .await
-    {
-        return HttpResponse::InternalServerError()
-            .body(format!("Erro ao salvar no banco: {}", err));
+    // Código e mensagem na mesma transação: ou os dois existem, ou nenhum.
+    let (issued, message) = async {
+        let mut tx = data.postgres_client.begin().await?;
+        let issued = otp_codes::issue_code(
+            &mut tx,
+            &otp_email,
+            recipient.phone_number.as_deref(),
+            req.purpose,
+            req.document_id,
+        )
+        .await?;
+        let email = templates::render(
+            EmailTemplate::OtpCode,
+            recipient.locale(),
+            &[
+                ("code", &issued.code),
+                ("minutes", &otp_codes::OTP_TTL_MINUTES.to_string()),
+            ],
+        );
+        let message = outbox::enqueue(
+            &mut tx,
+            recipient,
+            email.into(),
+            MessageContext::for_otp(issued.otp_code_id, req.document_id),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok::<_, AppError>((issued, message))
     }
+    .await?;
 
-    let phone_clone = req.phone_number.clone();
-    let code_clone = otp_code.clone();
+    Ok(HttpResponse::Ok().json(OtpResponse {
+        message: i18n::t("otp.sent"),
+        expires_at: issued.expires_at.to_rfc3339(),
+        delivery_id: message.public_id,
+        delivery_status: message.status,
+    }))
+}
 
-    tokio::spawn(async move {
-        match whatsapp::send_otp_via_whatsapp(&phone_clone, &code_clone).await {
-            Ok(_) => println!("OTP enviado para WhatsApp: {}", phone_clone),
-            Err(e) => eprintln!("Falha ao enviar OTP para WhatsApp: {:?}", e),
+/// Status de entrega do código, pelo `delivery_id` devolvido em /otp/generate.
+#[get("/otp/deliveries/{delivery_id}")]
+pub async fn get_otp_delivery(
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    match deliveries::find_by_public_id(&data.postgres_client, &path.into_inner()).await? {
+        Some(message) if message.kind == MessageKind::Otp.as_str() => {
+            Ok(HttpResponse::Ok().json(serde_json::json!({
+                "delivery_id": message.public_id,
+                "status": message.status,
+                "channel": message.channel,
+                "error_code": message.error_code,
+                "updated_at": message.updated_at,
+            })))
         }
-    });
-
-    HttpResponse::Ok().json(OtpResponse {
-        message: "Código OTP enviado para o seu WhatsApp.".to_string(),
-        expires_at: expires_at.to_rfc3339(),
-    })
+        _ => Err(AppError::not_found("delivery.not_found")),
+    }
 }
 
 #[derive(Deserialize)]
 pub struct VerifyRequest {
     pub email: String,
     pub code: String,
+    #[serde(default)]
+    pub purpose: OtpPurpose,
+    pub document_id: Option<i64>,
 }
 
 #[post("/otp/verify")]
 pub async fn verify_otp(
     data: web::Data<AppState>,
     req: web::Json<VerifyRequest>,
-) -> impl Responder {
-    let record = sqlx::query!(
-        r#"
-        SELECT code, expires_at, used FROM otp_codes
-        WHERE email = $1
-        "#,
-        req.email
-    )
-    .fetch_optional(&data.postgres_client)
-    .await;
+) -> Result<HttpResponse, AppError> {
+    let account_key = format!("otp:{}", req.email.to_lowercase());
+    data.rate_limiter
+        .check(&account_key, rate_limit::ACCOUNT_LIMIT)
+        .map_err(AppError::TooManyRequests)?;
+    check_purpose(req.purpose, req.document_id)?;
 
-    if let Ok(Some(rec)) = record {
-        if rec.used {
-            return HttpResponse::BadRequest().body("Código já utilizado");
-        }
-
-        if Utc::now() > rec.expires_at {
-            return HttpResponse::BadRequest().body("Código expirado");
-        }
-        if rec.code == req.code {
-            let _ = sqlx::query!(
-                "UPDATE otp_codes SET used = TRUE WHERE email = $1",
-                req.email
-            )
-            .execute(&data.postgres_client)
-            .await;
-            return HttpResponse::Ok().body("Validação bem-sucedida!");
-        }
+    match otp_codes::verify_code(
+        &data.postgres_client,
+        &req.email,
+        req.purpose,
+        req.document_id,
+        &req.code,
+    )
+    .await?
+    {
+        OtpVerification::Verified(proof) => Ok(HttpResponse::Ok().json(proof)),
+        OtpVerification::Used => Err(AppError::bad_request("otp.used")),
+        OtpVerification::Expired => Err(AppError::bad_request("otp.expired")),
+        OtpVerification::TooManyAttempts => Err(AppError::bad_request("otp.too_many_attempts")),
+        OtpVerification::Invalid => Err(AppError::bad_request("otp.invalid")),
     }
-
-    HttpResponse::BadRequest().body("Código inválido")
 }
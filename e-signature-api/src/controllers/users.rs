@@ -1,228 +1,427 @@
-use crate::services::auth;
+use crate::services::activation;
+use crate::services::auth::AuthenticatedUser;
+use crate::services::errors::AppError;
+use crate::services::face::FaceError;
+use crate::services::i18n;
+use crate::services::i18n::Locale;
+use crate::services::identity::{self, IdentityError};
+use crate::services::lockout;
+use crate::services::notifications::ChannelKind;
+use crate::services::otp_codes::{self, OtpPurpose};
+use crate::services::policy;
+use crate::services::rate_limit;
+use crate::services::sessions;
+use crate::services::tax_ids;
+use crate::services::totp;
 use crate::services::users as user_service;
-use crate::services::users::models::{CreateUser, UpdateUser, User};
+use crate::services::users::models::{CreateUser, UpdateUser};
 use crate::AppState;
-use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse, Responder};
+use actix_web::{delete, get, post, put, web, HttpResponse};
 use serde::Deserialize;
 use serde_json;
-//use crate::services::users::services::{FaceEnrollmentRequest, FaceVerificationRequest};
-
-#[derive(Deserialize)]
-pub struct LoginPayload {
-    email: String,
-    password: String,
-}
 
 #[derive(Deserialize)]
 pub struct FaceVerificationPayload {
     live_image_base64: String,
+    document_id: i64,
+    otp_proof: String,
 }
 
-#[post("/auth/login")]
-pub async fn login_handler(
-    state: web::Data<AppState>,
-    body: web::Json<LoginPayload>,
-) -> impl Responder {
-    let user = match sqlx::query_as::<_, User>("SELECT user_id, email, password_hash, role, created_at, updated_at, deleted_at FROM user_account WHERE email = $1 AND deleted_at IS NULL")
-        .bind(&body.email)
-        .fetch_one(&state.postgres_client)
-        .await
-    {
-        Ok(user) => user,
-        Err(_) => return HttpResponse::Unauthorized().json(serde_json::json!({"error": "Invalid credentials"})),
-    };
-
-    let valid_password = bcrypt::verify(&body.password, &user.password_hash).unwrap_or(false);
-
-    if !valid_password {
-        return HttpResponse::Unauthorized()
-            .json(serde_json::json!({"error": "Invalid credentials"}));
-    }
+#[derive(Deserialize)]
+pub struct FaceEnrollmentPayload {
+    image_base64: Option<String>,
+}
 
-    match auth::create_jwt(&user.user_id.to_string()) {
-        Ok(token) => HttpResponse::Ok().json(serde_json::json!({ "token": token })),
-        Err(_) => HttpResponse::InternalServerError()
-            .json(serde_json::json!({"error": "Failed to create token"})),
-    }
+#[derive(Deserialize)]
+pub struct IdentityCheckPayload {
+    document_image_base64: Option<String>,
 }
 
 #[post("/users")]
 async fn create_user_handler(
     state: web::Data<AppState>,
+    user: Option<AuthenticatedUser>,
     body: web::Json<CreateUser>,
-) -> impl Responder {
-    match user_service::create_user(&state.postgres_client, body.into_inner()).await {
-        Ok(user) => HttpResponse::Created().json(user),
-        Err(e) => {
-            if let Some(db_err) = e.as_database_error() {
-                if db_err.is_unique_violation() {
-                    return HttpResponse::Conflict().json(serde_json::json!(
-                        "Erro: E-mail já está em uso por um usuário ativo."
-                    ));
-                }
-            }
-            HttpResponse::InternalServerError()
-                .json(serde_json::json!(format!("Failed to create user: {}", e)))
-        }
+) -> Result<HttpResponse, AppError> {
+    if !policy::can_create_user_with_role(user.as_ref(), body.role) {
+        return Err(AppError::forbidden());
+    }
+    let user = user_service::create_user(&state.postgres_client, body.into_inner()).await?;
+    if let Err(e) = activation::send_activation_code(&state.postgres_client, &user.email).await {
+        eprintln!("Falha ao enviar código de ativação: {:?}", e);
     }
+    Ok(HttpResponse::Created().json(user))
+}
+
+fn user_not_found(user_id: i64) -> AppError {
+    AppError::not_found("user.not_found_id").with_var("id", user_id)
 }
 
 #[get("/users")]
-async fn get_users_handler(state: web::Data<AppState>) -> impl Responder {
-    match user_service::get_all_users(&state.postgres_client).await {
-        Ok(users) => HttpResponse::Ok().json(users),
-        Err(_) => {
-            HttpResponse::InternalServerError().json(serde_json::json!("Failed to retrieve users."))
-        }
+async fn get_users_handler(
+    state: web::Data<AppState>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    if !policy::can_list_users(&user) {
+        return Err(AppError::forbidden());
     }
+    let users = user_service::get_all_users(&state.postgres_client)
+        .await
+        .map_err(|e| AppError::internal("user.list_failed", e))?;
+    Ok(HttpResponse::Ok().json(users))
 }
 
 #[get("/users/{id}")]
 async fn get_user_by_id_handler(
     state: web::Data<AppState>,
+    current_user: AuthenticatedUser,
     path: web::Path<i64>,
-) -> impl Responder {
+) -> Result<HttpResponse, AppError> {
     let user_id = path.into_inner();
-    match user_service::get_user_by_id(&state.postgres_client, user_id).await {
-        Ok(Some(user)) => HttpResponse::Ok().json(user),
-        Ok(None) => HttpResponse::NotFound().json(serde_json::json!(format!(
-            "User with ID {} not found.",
-            user_id
-        ))),
-        Err(_) => {
-            HttpResponse::InternalServerError().json(serde_json::json!("Failed to retrieve user."))
-        }
+    if !policy::can_view_user(&current_user, user_id) {
+        return Err(AppError::forbidden());
     }
+    let user = user_service::get_user_by_id(&state.postgres_client, user_id)
+        .await
+        .map_err(|e| AppError::internal("user.fetch_failed", e))?
+        .ok_or_else(|| user_not_found(user_id))?;
+    Ok(HttpResponse::Ok().json(user))
 }
 
 #[get("/signer/{id}")]
 async fn get_signer_by_id_handler(
     state: web::Data<AppState>,
+    current_user: AuthenticatedUser,
     path: web::Path<i64>,
-) -> impl Responder {
+) -> Result<HttpResponse, AppError> {
     let user_id = path.into_inner();
-    match user_service::get_signer_by_id(&state.postgres_client, user_id).await {
-        Ok(Some(user)) => HttpResponse::Ok().json(user),
-        Ok(None) => HttpResponse::NotFound().json(serde_json::json!(format!(
-            "User with ID {} not found.",
-            user_id
-        ))),
-        Err(_) => {
-            HttpResponse::InternalServerError().json(serde_json::json!("Failed to retrieve user."))
+    let scope = policy::document_scope(&current_user);
+    let signer = user_service::get_signer_by_id(&state.postgres_client, scope, user_id)
+        .await
+        .map_err(|e| AppError::internal("user.fetch_failed", e))?
+        .ok_or_else(|| user_not_found(user_id))?;
+    Ok(HttpResponse::Ok().json(signer))
+}
+
+fn face_error(error: FaceError, failure_key: &'static str) -> AppError {
+    match error {
+        FaceError::InvalidImage(_) => AppError::bad_request("face.invalid_image"),
+        FaceError::QualityRejected(issues) => {
+            let reasons: Vec<serde_json::Value> = issues
+                .iter()
+                .map(|issue| {
+                    serde_json::json!({
+                        "code": issue.code,
+                        "image": issue.image,
+                        "value": issue.value,
+                        "limit": issue.limit,
+                        "message": i18n::t(&format!("face.quality.{}", issue.code.as_str())),
+                    })
+                })
+                .collect();
+            AppError::unprocessable("face.quality_rejected").with_extension("reasons", reasons)
+        }
+        FaceError::NoReferencePhoto => AppError::unprocessable("face.no_reference_photo"),
+        FaceError::NotEnrolled => AppError::unprocessable("face.not_enrolled"),
+        FaceError::ReferenceMismatch(face_match) => {
+            AppError::unprocessable("face.reference_mismatch")
+                .with_extension("similarity", face_match.similarity)
+                .with_extension("threshold", face_match.threshold)
         }
+        FaceError::SpoofDetected(liveness) => AppError::unprocessable("face.spoof_detected")
+            .with_extension("liveness", serde_json::json!(liveness)),
+        FaceError::TooManyAttempts(until) => AppError::TooManyRequests(lockout::retry_after(until)),
+        FaceError::NotLoaded => AppError::unavailable("face.unavailable"),
+        FaceError::Inference(_) | FaceError::Database(_) => AppError::internal(failure_key, error),
     }
 }
 
+/// Etapa facial da assinatura de um documento. Exige a prova de um OTP `sign_document`
+/// enviado ao signatário, que é gasta quando o rosto confere; sem ela ninguém consegue
+/// gastar as tentativas do signatário.
 #[post("/signers/{national_id}/facial-verify")]
 pub async fn verify_signer_face_handler(
     state: web::Data<AppState>,
     path: web::Path<String>,
     body: web::Json<FaceVerificationPayload>,
-) -> impl Responder {
+) -> Result<HttpResponse, AppError> {
     let national_id = path.into_inner();
-    match user_service::verify_signer_face(
+    let email = user_service::get_document_signer_email(
         &state.postgres_client,
         &national_id,
+        body.document_id,
+    )
+    .await?
+    .ok_or_else(|| AppError::not_found("signer.not_found"))?;
+    if !otp_codes::proof_is_valid(
+        &state.postgres_client,
+        &body.otp_proof,
+        &email,
+        OtpPurpose::SignDocument,
+        Some(body.document_id),
+    )
+    .await?
+    {
+        return Err(AppError::unauthorized("otp.proof_invalid"));
+    }
+
+    // Só conta depois da prova, para que terceiros não esgotem o limite do signatário.
+    // A mesma pessoa não pode escapar do limite trocando a pontuação do CPF.
+    let signer_key = format!(
+        "face:{}",
+        tax_ids::normalize_cpf(&national_id).unwrap_or_else(|| national_id.clone())
+    );
+    state
+        .rate_limiter
+        .check(&signer_key, rate_limit::ACCOUNT_LIMIT)
+        .map_err(AppError::TooManyRequests)?;
+
+    let result = user_service::verify_signer_face(
+        &state.postgres_client,
+        &national_id,
+        body.document_id,
         &body.live_image_base64,
     )
     .await
+    .map_err(|e| face_error(e, "face.verification_failed"))?
+    .ok_or_else(|| AppError::not_found("signer.not_found"))?;
+
+    if result.matched
+        && !otp_codes::consume_proof(
+            &state.postgres_client,
+            &body.otp_proof,
+            &email,
+            OtpPurpose::SignDocument,
+            Some(body.document_id),
+        )
+        .await?
     {
-        Ok(Some(match_result)) => {
-            HttpResponse::Ok().json(serde_json::json!({ "match": match_result }))
-        }
-        Ok(None) => {
-            HttpResponse::NotFound().json(serde_json::json!({ "error": "Signer not found" }))
+        return Err(AppError::unauthorized("otp.proof_invalid"));
+    }
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[put("/users/{id}/face-enrollment")]
+async fn enroll_face_handler(
+    state: web::Data<AppState>,
+    current_user: AuthenticatedUser,
+    path: web::Path<i64>,
+    body: web::Json<FaceEnrollmentPayload>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = path.into_inner();
+    if !policy::can_manage_face_enrollment(&current_user, user_id) {
+        return Err(AppError::forbidden());
+    }
+    let enrollment = user_service::enroll_face(
+        &state.postgres_client,
+        user_id,
+        body.image_base64.as_deref(),
+        !policy::can_enroll_face_unverified(&current_user),
+    )
+    .await
+    .map_err(|e| face_error(e, "face.enrollment_failed"))?
+    .ok_or_else(|| user_not_found(user_id))?;
+    Ok(HttpResponse::Ok().json(enrollment))
+}
+
+#[delete("/users/{id}/face-enrollment")]
+async fn delete_face_enrollment_handler(
+    state: web::Data<AppState>,
+    current_user: AuthenticatedUser,
+    path: web::Path<i64>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = path.into_inner();
+    if !policy::can_manage_face_enrollment(&current_user, user_id) {
+        return Err(AppError::forbidden());
+    }
+    match user_service::delete_face_enrollment(&state.postgres_client, user_id)
+        .await
+        .map_err(|e| AppError::internal("face.enrollment_delete_failed", e))?
+    {
+        0 => Err(user_not_found(user_id)),
+        _ => Ok(HttpResponse::NoContent().finish()),
+    }
+}
+
+fn identity_error(error: IdentityError) -> AppError {
+    match error {
+        IdentityError::InvalidImage(_) => AppError::bad_request("identity.invalid_image"),
+        IdentityError::NoDocumentImage => AppError::unprocessable("identity.no_document_image"),
+        IdentityError::NotLoaded => AppError::unavailable("identity.unavailable"),
+        IdentityError::Inference(_) | IdentityError::Database(_) => {
+            AppError::internal("identity.check_failed", error)
         }
-        Err(e) => HttpResponse::InternalServerError()
-            .json(serde_json::json!({ "error": format!("Verification failed: {}", e) })),
     }
 }
 
+/// Confere o RG/CNH do signatário com o cadastro. Sem imagem no corpo, usa a foto do
+/// documento já enviada; só nesse caso, e se quem chama pode gerenciar o cadastro facial
+/// do usuário do signatário, o retrato vira o rosto de referência.
+#[post("/signer/{id}/identity-check")]
+async fn check_signer_identity_handler(
+    state: web::Data<AppState>,
+    current_user: AuthenticatedUser,
+    path: web::Path<i64>,
+    body: web::Json<IdentityCheckPayload>,
+) -> Result<HttpResponse, AppError> {
+    let signer_id = path.into_inner();
+    let scope = policy::document_scope(&current_user);
+    let signer = user_service::get_signer_by_id(&state.postgres_client, scope, signer_id)
+        .await
+        .map_err(|e| AppError::internal("user.fetch_failed", e))?
+        .ok_or_else(|| AppError::not_found("signer.not_found"))?;
+
+    let may_enroll = signer
+        .user_id
+        .is_some_and(|user_id| policy::can_manage_face_enrollment(&current_user, user_id));
+    let check = identity::check_signer_document(
+        &state.postgres_client,
+        &signer,
+        body.document_image_base64.as_deref(),
+        may_enroll,
+    )
+    .await
+    .map_err(identity_error)?;
+    Ok(HttpResponse::Ok().json(check))
+}
+
 #[put("/users/{id}")]
 async fn update_user_handler(
     state: web::Data<AppState>,
+    current_user: AuthenticatedUser,
     path: web::Path<i64>,
     body: web::Json<UpdateUser>,
-) -> impl Responder {
+) -> Result<HttpResponse, AppError> {
     let user_id = path.into_inner();
-    match user_service::update_user(&state.postgres_client, user_id, body.into_inner()).await {
-        Ok(Some(user)) => HttpResponse::Ok().json(user),
-        Ok(None) => HttpResponse::NotFound().json(serde_json::json!(format!(
-            "User with ID {} not found.",
-            user_id
-        ))),
-        Err(_) => {
-            HttpResponse::InternalServerError().json(serde_json::json!("Failed to update user."))
-        }
+    if !policy::can_update_user(&current_user, user_id, &body) {
+        return Err(AppError::forbidden());
     }
+    let user = user_service::update_user(&state.postgres_client, user_id, body.into_inner())
+        .await
+        .map_err(|e| AppError::internal("user.update_failed", e))?
+        .ok_or_else(|| user_not_found(user_id))?;
+    Ok(HttpResponse::Ok().json(user))
 }
 
 #[delete("/users/{id}")]
-async fn delete_user_handler(state: web::Data<AppState>, path: web::Path<i64>) -> impl Responder {
+async fn delete_user_handler(
+    state: web::Data<AppState>,
+    current_user: AuthenticatedUser,
+    path: web::Path<i64>,
+) -> Result<HttpResponse, AppError> {
     let user_id = path.into_inner();
-    match user_service::delete_user(&state.postgres_client, user_id).await {
-        Ok(0) => HttpResponse::NotFound().json(serde_json::json!(format!(
-            "User with ID {} not found.",
-            user_id
-        ))),
-        Ok(_) => HttpResponse::Ok().json(serde_json::json!(format!(
-            "User with ID {} successfully deleted.",
-            user_id
-        ))),
-        Err(_) => {
-            HttpResponse::InternalServerError().json(serde_json::json!("Failed to delete user."))
-        }
+    if !policy::can_delete_user(&current_user, user_id) {
+        return Err(AppError::forbidden());
+    }
+    match user_service::delete_user(&state.postgres_client, user_id)
+        .await
+        .map_err(|e| AppError::internal("user.delete_failed", e))?
+    {
+        0 => Err(user_not_found(user_id)),
+        _ => Ok(HttpResponse::Ok().json(serde_json::json!(i18n::t_with(
+            "user.deleted",
+            &[("id", &user_id.to_string())]
+        )))),
+    }
+}
+
+#[delete("/users/{id}/sessions")]
+async fn revoke_user_sessions_handler(
+    state: web::Data<AppState>,
+    current_user: AuthenticatedUser,
+    path: web::Path<i64>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = path.into_inner();
+    if !policy::can_delete_user(&current_user, user_id) {
+        return Err(AppError::forbidden());
     }
+    sessions::revoke_all_sessions(&state.postgres_client, user_id)
+        .await
+        .map_err(|e| AppError::internal("session.revoke_failed", e))?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[delete("/users/{id}/totp")]
+async fn reset_user_totp_handler(
+    state: web::Data<AppState>,
+    current_user: AuthenticatedUser,
+    path: web::Path<i64>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = path.into_inner();
+    if !policy::can_reset_second_factor(&current_user) {
+        return Err(AppError::forbidden());
+    }
+    let reset = totp::reset(&state.postgres_client, user_id)
+        .await
+        .map_err(|e| AppError::internal("totp.reset_failed", e))?;
+    if reset == 0 {
+        return Err(user_not_found(user_id));
+    }
+    if let Err(e) = sessions::revoke_all_sessions(&state.postgres_client, user_id).await {
+        eprintln!("Falha ao revogar sessões após reset de TOTP: {:?}", e);
+    }
+    Ok(HttpResponse::NoContent().finish())
 }
 
 #[get("/users/me")]
-async fn get_current_user_handler(req: HttpRequest, state: web::Data<AppState>) -> impl Responder {
-    let auth_header = match req.headers().get("Authorization") {
-        Some(header) => header,
-        None => {
-            return HttpResponse::Unauthorized().json(serde_json::json!({
-                "message": "Token de autorização não fornecido.",
-                "received_token": null
-            }));
-        }
-    };
-
-    let auth_str = match auth_header.to_str() {
-        Ok(s) => s,
-        Err(_) => {
-            return HttpResponse::BadRequest().json(serde_json::json!({
-                "message": "Valor de cabeçalho inválido.",
-                "received_header": format!("{:?}", auth_header)
-            }));
-        }
-    };
-
-    if !auth_str.starts_with("Bearer ") {
-        return HttpResponse::BadRequest().json(serde_json::json!({
-            "message": "Formato de token inválido. Use: Bearer <token>",
-            "received_header": auth_str
-        }));
-    }
-
-    let token = &auth_str["Bearer ".len()..];
-
-    match auth::validate_jwt(token) {
-        Ok(claims) => {
-            let user_id: i64 = claims.sub.parse().unwrap_or(0);
-            match user_service::get_user_by_id(&state.postgres_client, user_id).await {
-                Ok(Some(user)) => HttpResponse::Ok().json(user),
-                Ok(None) => {
-                    HttpResponse::NotFound().json(serde_json::json!("Usuário não encontrado."))
-                }
-                Err(_) => HttpResponse::InternalServerError()
-                    .json(serde_json::json!("Falha ao buscar usuário.")),
-            }
+async fn get_current_user_handler(
+    state: web::Data<AppState>,
+    current_user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let user = user_service::get_user_by_id(&state.postgres_client, current_user.user_id)
+        .await
+        .map_err(|e| AppError::internal("user.fetch_failed", e))?
+        .ok_or_else(|| AppError::not_found("user.not_found"))?;
+    Ok(HttpResponse::Ok().json(user))
+}
+
+#[derive(Deserialize)]
+pub struct NotificationChannelsPayload {
+    channels: Vec<ChannelKind>,
+}
+
+#[put("/users/me/notification-channels")]
+async fn update_notification_channels_handler(
+    state: web::Data<AppState>,
+    current_user: AuthenticatedUser,
+    body: web::Json<NotificationChannelsPayload>,
+) -> Result<HttpResponse, AppError> {
+    let mut channels: Vec<String> = Vec::new();
+    for channel in &body.channels {
+        if !channels.iter().any(|c| c == channel.as_str()) {
+            channels.push(channel.as_str().to_string());
         }
-        Err(_) => HttpResponse::Unauthorized().json(serde_json::json!({
-            "message": "Token inválido ou expirado.",
-            "received_token": token
-        })),
     }
+
+    user_service::update_notification_channels(
+        &state.postgres_client,
+        current_user.user_id,
+        &channels,
+    )
+    .await
+    .map_err(|e| AppError::internal("user.preferences_failed", e))?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "channels": channels })))
+}
+
+#[derive(Deserialize)]
+pub struct LocalePayload {
+    locale: Option<Locale>,
+}
+
+#[put("/users/me/locale")]
+async fn update_locale_handler(
+    state: web::Data<AppState>,
+    current_user: AuthenticatedUser,
+    body: web::Json<LocalePayload>,
+) -> Result<HttpResponse, AppError> {
+    let locale = body.locale.map(|locale| locale.tag());
+    user_service::update_locale(&state.postgres_client, current_user.user_id, locale)
+        .await
+        .map_err(|e| AppError::internal("user.preferences_failed", e))?;
+    if let Some(locale) = body.locale {
+        i18n::set_current(locale);
+    }
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "locale": locale })))
 }
 
 /* */
@@ -231,18 +430,25 @@ pub fn config(cfg: &mut web::ServiceConfig) {
     println!("Módulo users carregado!");
     cfg.service(
         web::scope("/api")
-            .service(login_handler)
+            .configure(super::auth::config)
+            .configure(super::api_keys::config)
+            .configure(super::oidc::config)
             .service(get_current_user_handler)
+            .service(update_notification_channels_handler)
+            .service(update_locale_handler)
             .service(create_user_handler)
             .service(get_users_handler)
             .service(get_user_by_id_handler)
             .service(update_user_handler)
             .service(delete_user_handler)
+            .service(revoke_user_sessions_handler)
+            .service(reset_user_totp_handler)
             .service(create_user_handler)
             .service(delete_user_handler)
             .service(get_signer_by_id_handler)
-            .service(verify_signer_face_handler),
-        //,(enroll_face_handler)
-        //.service(verify_face_handler),
+            .service(verify_signer_face_handler)
+            .service(enroll_face_handler)
+            .service(delete_face_enrollment_handler)
+            .service(check_signer_identity_handler),
     );
 }
@@ -0,0 +1,48 @@
+use crate::services::deliveries::{self, DeliveryStatus};
+use crate::services::errors::AppError;
+use crate::services::twilio;
+use crate::AppState;
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use std::collections::BTreeMap;
+
+/// URL que a Twilio assinou: a configurada em TWILIO_STATUS_CALLBACK_URL ou, sem ela,
+/// a reconstruída a partir da requisição.
+fn signed_url(req: &HttpRequest) -> String {
+    twilio::status_callback_url().unwrap_or_else(|| {
+        let info = req.connection_info();
+        format!("{}://{}{}", info.scheme(), info.host(), req.uri())
+    })
+}
+
+#[post("/webhooks/twilio/status")]
+pub async fn twilio_status_callback(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    form: web::Form<BTreeMap<String, String>>,
+) -> Result<HttpResponse, AppError> {
+    let params = form.into_inner();
+    let signature = req
+        .headers()
+        .get("X-Twilio-Signature")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+
+    if !twilio::verify_signature(&signed_url(&req), &params, signature) {
+        return Err(AppError::forbidden_for("webhook.invalid_signature"));
+    }
+
+    let (Some(sid), Some(status)) = (
+        params.get("MessageSid"),
+        params
+            .get("MessageStatus")
+            .and_then(|status| DeliveryStatus::from_twilio(status)),
+    ) else {
+        return Ok(HttpResponse::NoContent().finish());
+    };
+
+    let error_code = params.get("ErrorCode").map(String::as_str);
+    deliveries::apply_provider_status(&state.postgres_client, sid, status, error_code)
+        .await
+        .map_err(|e| AppError::internal("webhook.status_failed", e))?;
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -1,11 +1,13 @@
+use crate::services::errors::AppError;
+use crate::services::rate_limit::RateLimiter;
 use crate::services::telegram::models::TelegramLink;
 use actix_cors::Cors;
-use actix_web::middleware::Logger;
+use actix_web::middleware::{from_fn, Logger};
 use actix_web::{get, web, App, HttpResponse, HttpServer, Responder};
 use dotenv::dotenv;
 use sqlx::postgres::PgPoolOptions;
 use std::collections::HashMap;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 
 mod bot;
 mod controllers;
@@ -15,6 +17,7 @@ mod services;
 #[derive(Clone)]
 pub struct AppState {
     postgres_client: sqlx::Pool<sqlx::Postgres>,
+    rate_limiter: Arc<RateLimiter>,
 }
 
 #[get("/")]
@@ -39,17 +42,59 @@ async fn main() -> std::io::Result<()> {
         .await
         .expect("Failed to create pool.");
 
+    services::signing_keys::initialize(&pool)
+        .await
+        .expect("Failed to load JWT signing keys.");
+    services::signing_keys::spawn_rotation(pool.clone());
+    services::outbox::spawn_worker(pool.clone());
+    services::face_attempts::spawn_retention(pool.clone());
+
+    // Sem os modelos o servidor sobe mesmo assim; só a verificação facial fica indisponível.
+    if let Err(e) = services::face::initialize() {
+        eprintln!(
+            "Falha ao carregar os modelos de reconhecimento facial: {}",
+            e
+        );
+    }
+    if let Err(e) = services::identity::initialize() {
+        eprintln!("Falha ao carregar os modelos de OCR de documentos: {}", e);
+    }
+
+    let rate_limiter = Arc::new(RateLimiter::default());
+
     println!("Servidor iniciado em http://127.0.0.1:8080");
 
     HttpServer::new(move || {
         App::new()
+            .wrap(from_fn(services::rate_limit::limit_sensitive_routes))
+            .wrap(from_fn(services::i18n::negotiate_locale))
+            .wrap(from_fn(services::request_id::assign_request_id))
             .wrap(Logger::default())
+            .app_data(web::JsonConfig::default().error_handler(|err, _| {
+                AppError::bad_request("request.invalid_body")
+                    .with_var("error", err)
+                    .into()
+            }))
+            .app_data(web::PathConfig::default().error_handler(|err, _| {
+                AppError::bad_request("request.invalid_path")
+                    .with_var("error", err)
+                    .into()
+            }))
+            .app_data(web::QueryConfig::default().error_handler(|err, _| {
+                AppError::bad_request("request.invalid_query")
+                    .with_var("error", err)
+                    .into()
+            }))
             .app_data(web::Data::new(AppState {
                 postgres_client: pool.clone(),
+                rate_limiter: rate_limiter.clone(),
             }))
             .service(root)
+            .service(controllers::auth::jwks_handler)
             .service(controllers::otp::generate_otp)
             .service(controllers::otp::verify_otp)
+            .service(controllers::otp::get_otp_delivery)
+            .service(controllers::webhooks::twilio_status_callback)
             .configure(controllers::users::config)
             .configure(controllers::telegram::config)
             .configure(controllers::documents::config)
@@ -59,6 +104,7 @@ async fn main() -> std::io::Result<()> {
                     .allow_any_origin()
                     .allow_any_method()
                     .allow_any_header()
+                    .expose_headers([services::request_id::REQUEST_ID_HEADER])
                     .max_age(3600),
             )
     })
@@ -0,0 +1,135 @@
+use crate::services::deliveries::{MessageContext, MessageKind};
+use crate::services::errors::ServiceError;
+use crate::services::i18n;
+use crate::services::notifications::{Notification, Recipient};
+use crate::services::outbox;
+use crate::services::sessions::hash_token;
+use crate::services::users as user_service;
+use chrono::{Duration, Utc};
+use rand::Rng;
+use sqlx::PgPool;
+
+const ACTIVATION_CODE_TTL_HOURS: i64 = 24;
+const MAX_ACTIVATION_ATTEMPTS: i32 = 5;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ActivationOutcome {
+    Activated,
+    AlreadyActive,
+    InvalidCode,
+}
+
+fn activation_hash(user_id: i64, code: &str) -> String {
+    hash_token(&format!("{}:{}", user_id, code))
+}
+
+pub async fn send_activation_code(pool: &PgPool, email: &str) -> Result<(), ServiceError> {
+    let user = match user_service::get_user_by_email(pool, email).await? {
+        Some(user) if !user.is_active => user,
+        _ => return Ok(()),
+    };
+    let contact = match user_service::get_user_contact(pool, email).await? {
+        Some(contact) => contact,
+        None => return Ok(()),
+    };
+
+    let code: String = rand::rng().random_range(100_000..1_000_000).to_string();
+    let expires_at = Utc::now() + Duration::hours(ACTIVATION_CODE_TTL_HOURS);
+
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        "UPDATE account_activation SET used_at = $1 WHERE user_id = $2 AND used_at IS NULL",
+    )
+    .bind(Utc::now())
+    .bind(user.user_id)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        "INSERT INTO account_activation (user_id, code_hash, expires_at) VALUES ($1, $2, $3)",
+    )
+    .bind(user.user_id)
+    .bind(activation_hash(user.user_id, &code))
+    .bind(expires_at)
+    .execute(&mut *tx)
+    .await?;
+
+    let locale = i18n::preferred_or_current(contact.locale.as_deref());
+    outbox::enqueue(
+        &mut tx,
+        Recipient::from(contact),
+        Notification::new(
+            i18n::translate(locale, "activation.subject"),
+            i18n::translate_with(locale, "activation.message", &[("code", &code)]),
+        ),
+        MessageContext::new(MessageKind::AccountActivation),
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+pub async fn activate(
+    pool: &PgPool,
+    email: &str,
+    code: &str,
+) -> Result<ActivationOutcome, sqlx::Error> {
+    let user = match user_service::get_user_by_email(pool, email).await? {
+        Some(user) => user,
+        None => return Ok(ActivationOutcome::InvalidCode),
+    };
+    if user.is_active {
+        return Ok(ActivationOutcome::AlreadyActive);
+    }
+
+    let mut tx = pool.begin().await?;
+
+    let pending: Option<(i64, String, i32)> = sqlx::query_as(
+        r#"
+        SELECT account_activation_id, code_hash, attempts
+        FROM account_activation
+        WHERE user_id = $1 AND used_at IS NULL AND expires_at > $2
+        ORDER BY created_at DESC
+        LIMIT 1
+        FOR UPDATE
+        "#,
+    )
+    .bind(user.user_id)
+    .bind(Utc::now())
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let (activation_id, code_hash, attempts) = match pending {
+        Some(pending) if pending.2 < MAX_ACTIVATION_ATTEMPTS => pending,
+        _ => return Ok(ActivationOutcome::InvalidCode),
+    };
+
+    if code_hash.trim() != activation_hash(user.user_id, code) {
+        sqlx::query("UPDATE account_activation SET attempts = $1 WHERE account_activation_id = $2")
+            .bind(attempts + 1)
+            .bind(activation_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        return Ok(ActivationOutcome::InvalidCode);
+    }
+
+    sqlx::query("UPDATE account_activation SET used_at = $1 WHERE account_activation_id = $2")
+        .bind(Utc::now())
+        .bind(activation_id)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query("UPDATE user_account SET is_active = TRUE, updated_at = $1 WHERE user_id = $2")
+        .bind(Utc::now())
+        .bind(user.user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(ActivationOutcome::Activated)
+}
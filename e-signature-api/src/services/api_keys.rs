@@ -0,0 +1,184 @@
+use crate::services::auth::{load_authenticated_user, AuthenticatedUser};
+use crate::services::sessions::hash_token;
+use actix_web::http::Method;
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+
+const KEY_PREFIX: &str = "esk_";
+const DISPLAY_PREFIX_LEN: usize = 12;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ApiKeyScope {
+    #[serde(rename = "read")]
+    Read,
+    #[serde(rename = "documents:write")]
+    DocumentsWrite,
+}
+
+impl ApiKeyScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiKeyScope::Read => "read",
+            ApiKeyScope::DocumentsWrite => "documents:write",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ApiKeyAuthentication {
+    Authenticated(AuthenticatedUser),
+    MissingScope,
+    Invalid,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct ApiKey {
+    pub api_key_id: i64,
+    pub company_id: i64,
+    pub name: String,
+    pub key_prefix: String,
+    pub scopes: Vec<String>,
+    pub created_by: i64,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreatedApiKey {
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+    pub key: String,
+}
+
+/// Escopo exigido de uma chave de API para a requisição. `None` quando a rota
+/// não pode ser acessada por chave (gestão de chaves, senha, sessões...).
+pub fn required_scope(method: &Method, path: &str) -> Option<ApiKeyScope> {
+    if path.starts_with("/api/api-keys") || path.starts_with("/api/auth") {
+        return None;
+    }
+    if method == Method::GET || method == Method::HEAD {
+        return Some(ApiKeyScope::Read);
+    }
+    if path == "/documents" || path.starts_with("/documents/") {
+        return Some(ApiKeyScope::DocumentsWrite);
+    }
+    None
+}
+
+pub async fn create_api_key(
+    pool: &PgPool,
+    company_id: i64,
+    created_by: i64,
+    name: &str,
+    scopes: &[ApiKeyScope],
+) -> Result<CreatedApiKey, sqlx::Error> {
+    let bytes: [u8; 32] = rand::random();
+    let key = format!(
+        "{}{}",
+        KEY_PREFIX,
+        general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+    );
+    let scopes: Vec<String> = scopes.iter().map(|s| s.as_str().to_string()).collect();
+
+    let api_key = sqlx::query_as::<_, ApiKey>(
+        r#"
+        INSERT INTO api_key (company_id, name, key_prefix, key_hash, scopes, created_by)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING api_key_id, company_id, name, key_prefix, scopes, created_by, created_at, last_used_at, revoked_at
+        "#,
+    )
+    .bind(company_id)
+    .bind(name)
+    .bind(&key[..DISPLAY_PREFIX_LEN])
+    .bind(hash_token(&key))
+    .bind(&scopes)
+    .bind(created_by)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(CreatedApiKey { api_key, key })
+}
+
+pub async fn list_api_keys(pool: &PgPool, company_id: i64) -> Result<Vec<ApiKey>, sqlx::Error> {
+    sqlx::query_as::<_, ApiKey>(
+        r#"
+        SELECT api_key_id, company_id, name, key_prefix, scopes, created_by, created_at, last_used_at, revoked_at
+        FROM api_key
+        WHERE company_id = $1
+        ORDER BY created_at DESC
+        "#,
+    )
+    .bind(company_id)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn revoke_api_key(
+    pool: &PgPool,
+    company_id: i64,
+    api_key_id: i64,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+        UPDATE api_key SET revoked_at = $1
+        WHERE api_key_id = $2 AND company_id = $3 AND revoked_at IS NULL
+        "#,
+    )
+    .bind(Utc::now())
+    .bind(api_key_id)
+    .bind(company_id)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+/// Resolve a chave para o usuário dono da empresa; o último uso é gravado no
+/// máximo uma vez por minuto.
+pub async fn authenticate(
+    pool: &PgPool,
+    key: &str,
+    required: ApiKeyScope,
+) -> Result<ApiKeyAuthentication, sqlx::Error> {
+    let record: Option<(i64, i64, i64, Vec<String>)> = sqlx::query_as(
+        r#"
+        SELECT k.api_key_id, k.company_id, c.user_id, k.scopes
+        FROM api_key k
+        JOIN company c ON c.company_id = k.company_id AND c.deleted_at IS NULL
+        WHERE k.key_hash = $1 AND k.revoked_at IS NULL
+        "#,
+    )
+    .bind(hash_token(key))
+    .fetch_optional(pool)
+    .await?;
+
+    let (api_key_id, company_id, user_id, scopes) = match record {
+        Some(record) => record,
+        None => return Ok(ApiKeyAuthentication::Invalid),
+    };
+    if !scopes.iter().any(|s| s == required.as_str()) {
+        return Ok(ApiKeyAuthentication::MissingScope);
+    }
+
+    sqlx::query(
+        r#"
+        UPDATE api_key SET last_used_at = $1
+        WHERE api_key_id = $2 AND (last_used_at IS NULL OR last_used_at < $1 - INTERVAL '1 minute')
+        "#,
+    )
+    .bind(Utc::now())
+    .bind(api_key_id)
+    .execute(pool)
+    .await?;
+
+    let mut user = match load_authenticated_user(pool, user_id).await? {
+        Some(user) => user,
+        None => return Ok(ApiKeyAuthentication::Invalid),
+    };
+    user.company_id = Some(company_id);
+    user.api_key_id = Some(api_key_id);
+    Ok(ApiKeyAuthentication::Authenticated(user))
+}
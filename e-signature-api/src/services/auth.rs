@@ -1,16 +1,39 @@
+use crate::services::api_keys::{self, ApiKeyAuthentication};
+use crate::services::errors::AppError;
+use crate::services::i18n::{self, Locale};
+use crate::services::sessions;
+use crate::services::signing_keys;
+use crate::services::users::models::Role;
+use crate::AppState;
+use actix_web::dev::Payload;
+use actix_web::{web, FromRequest, HttpRequest};
 use chrono::{Duration, Utc};
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::{decode, decode_header, encode, Algorithm, Header, Validation};
 use serde::{Deserialize, Serialize};
-use std::env;
+use sqlx::{FromRow, PgPool};
+use std::future::Future;
+use std::pin::Pin;
+use uuid::Uuid;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Claims {
     pub sub: String,
     pub exp: usize,
     pub iat: usize,
+    pub jti: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
 }
 
 pub fn create_jwt(user_id: &str) -> Result<String, jsonwebtoken::errors::Error> {
+    create_session_jwt(user_id, None)
+}
+
+pub fn create_session_jwt(
+    user_id: &str,
+    session_id: Option<&str>,
+) -> Result<String, jsonwebtoken::errors::Error> {
     let expiration = Utc::now()
         .checked_add_signed(Duration::hours(1))
         .expect("valid timestamp")
@@ -20,19 +43,167 @@ pub fn create_jwt(user_id: &str) -> Result<String, jsonwebtoken::errors::Error>
         sub: user_id.to_owned(),
         iat: Utc::now().timestamp() as usize,
         exp: expiration as usize,
+        jti: Uuid::new_v4().to_string(),
+        sid: session_id.map(|s| s.to_owned()),
     };
 
-    let secret = env::var("JSON_WEB_TOKEN_SECRET").expect("JWT_SECRET must be set");
-    let header = Header::default();
-    let encoding_key = EncodingKey::from_secret(secret.as_ref());
+    let signing_key = signing_keys::current_signing_key().ok_or(ErrorKind::InvalidKeyFormat)?;
+    let mut header = Header::new(Algorithm::EdDSA);
+    header.kid = Some(signing_key.kid);
 
-    encode(&header, &claims, &encoding_key)
+    encode(&header, &claims, &signing_key.encoding_key)
 }
 
 pub fn validate_jwt(token: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
-    let secret = env::var("JSON_WEB_TOKEN_SECRET").expect("JWT_SECRET must be set");
-    let decoding_key = DecodingKey::from_secret(secret.as_ref());
-    let validation = Validation::default();
+    let header = decode_header(token)?;
+    let kid = header.kid.ok_or(ErrorKind::InvalidToken)?;
+    let decoding_key = signing_keys::decoding_key(&kid).ok_or(ErrorKind::InvalidToken)?;
+    let validation = Validation::new(Algorithm::EdDSA);
 
     decode::<Claims>(token, &decoding_key, &validation).map(|data| data.claims)
 }
+
+#[derive(Debug, Clone, FromRow)]
+pub struct AuthenticatedUser {
+    pub user_id: i64,
+    pub role: Role,
+    pub company_id: Option<i64>,
+    /// Dono da empresa (`company.user_id`), e não só membro via `user_account.company_id`.
+    pub is_company_owner: bool,
+    pub signer_id: Option<i64>,
+    pub locale: Option<String>,
+    #[sqlx(skip)]
+    pub claims: Option<Claims>,
+    #[sqlx(skip)]
+    pub api_key_id: Option<i64>,
+}
+
+enum Credentials {
+    Bearer(String),
+    ApiKey(String),
+}
+
+fn credentials(req: &HttpRequest) -> Result<Credentials, actix_web::Error> {
+    let auth_str = req
+        .headers()
+        .get("Authorization")
+        .ok_or_else(|| AppError::unauthorized("auth.token_missing"))?
+        .to_str()
+        .map_err(|_| AppError::unauthorized("auth.invalid_header"))?;
+
+    if let Some(key) = auth_str.strip_prefix("ApiKey ") {
+        return Ok(Credentials::ApiKey(key.trim().to_string()));
+    }
+    bearer_token(req).map(Credentials::Bearer)
+}
+
+pub fn bearer_token(req: &HttpRequest) -> Result<String, actix_web::Error> {
+    let auth_str = req
+        .headers()
+        .get("Authorization")
+        .ok_or_else(|| AppError::unauthorized("auth.token_missing"))?
+        .to_str()
+        .map_err(|_| AppError::unauthorized("auth.invalid_header"))?;
+
+    auth_str
+        .strip_prefix("Bearer ")
+        .map(|token| token.to_string())
+        .ok_or_else(|| AppError::unauthorized("auth.invalid_token_format").into())
+}
+
+pub async fn load_authenticated_user(
+    pool: &PgPool,
+    user_id: i64,
+) -> Result<Option<AuthenticatedUser>, sqlx::Error> {
+    sqlx::query_as::<_, AuthenticatedUser>(
+        r#"
+        SELECT u.user_id, u.role, c.company_id,
+               COALESCE(c.user_id = u.user_id, FALSE) AS is_company_owner,
+               s.signer_id, u.locale
+        FROM user_account u
+        LEFT JOIN company c ON c.user_id = u.user_id OR c.company_id = u.company_id
+        LEFT JOIN signer s ON s.user_id = u.user_id AND s.deleted_at IS NULL
+        WHERE u.user_id = $1 AND u.deleted_at IS NULL
+        ORDER BY is_company_owner DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// A preferência salva no perfil vence o Accept-Language da requisição.
+fn apply_locale_preference(user: &AuthenticatedUser) {
+    if let Some(locale) = user.locale.as_deref().and_then(Locale::parse) {
+        i18n::set_current(locale);
+    }
+}
+
+impl FromRequest for AuthenticatedUser {
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let credentials = credentials(req);
+        let required_scope = api_keys::required_scope(req.method(), req.path());
+        let state = req.app_data::<web::Data<AppState>>().cloned();
+
+        Box::pin(async move {
+            let credentials = credentials?;
+            let state = state
+                .ok_or_else(|| AppError::internal("error.internal", "AppState not configured"))?;
+
+            let token = match credentials {
+                Credentials::Bearer(token) => token,
+                Credentials::ApiKey(key) => {
+                    let required_scope = required_scope
+                        .ok_or_else(|| AppError::forbidden_for("auth.api_key_route_forbidden"))?;
+                    return match api_keys::authenticate(
+                        &state.postgres_client,
+                        &key,
+                        required_scope,
+                    )
+                    .await
+                    .map_err(AppError::from)?
+                    {
+                        ApiKeyAuthentication::Authenticated(user) => {
+                            apply_locale_preference(&user);
+                            Ok(user)
+                        }
+                        ApiKeyAuthentication::MissingScope => {
+                            Err(AppError::forbidden_for("auth.api_key_missing_scope")
+                                .with_var("scope", required_scope.as_str())
+                                .into())
+                        }
+                        ApiKeyAuthentication::Invalid => {
+                            Err(AppError::unauthorized("auth.api_key_invalid").into())
+                        }
+                    };
+                }
+            };
+
+            let claims =
+                validate_jwt(&token).map_err(|_| AppError::unauthorized("auth.token_invalid"))?;
+            let user_id: i64 = claims
+                .sub
+                .parse()
+                .map_err(|_| AppError::unauthorized("auth.token_invalid"))?;
+
+            if sessions::is_revoked(&state.postgres_client, &claims)
+                .await
+                .map_err(AppError::from)?
+            {
+                return Err(AppError::unauthorized("auth.token_revoked").into());
+            }
+
+            let mut user = load_authenticated_user(&state.postgres_client, user_id)
+                .await
+                .map_err(AppError::from)?
+                .ok_or_else(|| AppError::unauthorized("user.not_found"))?;
+            user.claims = Some(claims);
+            apply_locale_preference(&user);
+            Ok(user)
+        })
+    }
+}
@@ -0,0 +1,341 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{FromRow, PgConnection, PgExecutor, PgPool};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageKind {
+    Otp,
+    AccountActivation,
+    PasswordReset,
+    SigningInvitation,
+    SigningReminder,
+    SigningCompleted,
+}
+
+impl MessageKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MessageKind::Otp => "otp",
+            MessageKind::AccountActivation => "account_activation",
+            MessageKind::PasswordReset => "password_reset",
+            MessageKind::SigningInvitation => "signing_invitation",
+            MessageKind::SigningReminder => "signing_reminder",
+            MessageKind::SigningCompleted => "signing_completed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryStatus {
+    Pending,
+    Queued,
+    Sent,
+    Delivered,
+    Failed,
+}
+
+impl DeliveryStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryStatus::Pending => "pending",
+            DeliveryStatus::Queued => "queued",
+            DeliveryStatus::Sent => "sent",
+            DeliveryStatus::Delivered => "delivered",
+            DeliveryStatus::Failed => "failed",
+        }
+    }
+
+    /// Converte o `MessageStatus` dos callbacks da Twilio. Status de mensagens
+    /// recebidas (ex.: "received") não interessam aqui.
+    pub fn from_twilio(status: &str) -> Option<Self> {
+        match status {
+            "accepted" | "scheduled" | "queued" | "sending" => Some(DeliveryStatus::Queued),
+            "sent" => Some(DeliveryStatus::Sent),
+            "delivered" | "read" => Some(DeliveryStatus::Delivered),
+            "failed" | "undelivered" | "canceled" => Some(DeliveryStatus::Failed),
+            _ => None,
+        }
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            DeliveryStatus::Pending => 0,
+            DeliveryStatus::Queued => 1,
+            DeliveryStatus::Sent => 2,
+            DeliveryStatus::Delivered | DeliveryStatus::Failed => 3,
+        }
+    }
+
+    /// Status que podem ser substituídos por este. Callbacks chegam fora de ordem,
+    /// então um "sent" atrasado não pode desfazer um "delivered".
+    fn replaces(&self) -> Vec<&'static str> {
+        [
+            DeliveryStatus::Pending,
+            DeliveryStatus::Queued,
+            DeliveryStatus::Sent,
+        ]
+        .iter()
+        .filter(|status| status.rank() < self.rank())
+        .map(|status| status.as_str())
+        .collect()
+    }
+}
+
+/// A que se refere a mensagem, para consultar o status depois.
+#[derive(Debug, Clone, Copy)]
+pub struct MessageContext {
+    pub kind: MessageKind,
+    pub otp_code_id: Option<i64>,
+    pub document_id: Option<i64>,
+}
+
+impl MessageContext {
+    pub fn new(kind: MessageKind) -> Self {
+        MessageContext {
+            kind,
+            otp_code_id: None,
+            document_id: None,
+        }
+    }
+
+    pub fn for_otp(otp_code_id: i64, document_id: Option<i64>) -> Self {
+        MessageContext {
+            kind: MessageKind::Otp,
+            otp_code_id: Some(otp_code_id),
+            document_id,
+        }
+    }
+
+    pub fn for_document(kind: MessageKind, document_id: i64) -> Self {
+        MessageContext {
+            kind,
+            otp_code_id: None,
+            document_id: Some(document_id),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct OutboundMessage {
+    #[serde(skip_serializing)]
+    pub message_id: i64,
+    #[serde(rename = "delivery_id")]
+    pub public_id: String,
+    pub kind: String,
+    pub channel: Option<String>,
+    pub recipient: Option<String>,
+    pub status: String,
+    pub error_code: Option<String>,
+    pub error_message: Option<String>,
+    pub attempts: i32,
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+const MESSAGE_COLUMNS: &str = "message_id, public_id, kind, channel, recipient, status, error_code, error_message, attempts, next_attempt_at, created_at, updated_at";
+
+async fn record_event<'c>(
+    executor: impl PgExecutor<'c>,
+    message_id: i64,
+    status: DeliveryStatus,
+    error_code: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO outbound_message_event (message_id, status, error_code) VALUES ($1, $2, $3)",
+    )
+    .bind(message_id)
+    .bind(status.as_str())
+    .bind(error_code)
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+/// Grava a mensagem na caixa de saída, pronta para o worker. Usa a conexão (ou
+/// transação) de quem chama para que a mensagem só exista se o resto for gravado.
+pub async fn create(
+    conn: &mut PgConnection,
+    context: MessageContext,
+    payload: serde_json::Value,
+) -> Result<OutboundMessage, sqlx::Error> {
+    let message = sqlx::query_as::<_, OutboundMessage>(&format!(
+        r#"
+        INSERT INTO outbound_message (public_id, kind, otp_code_id, document_id, payload, next_attempt_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING {}
+        "#,
+        MESSAGE_COLUMNS
+    ))
+    .bind(Uuid::new_v4().to_string())
+    .bind(context.kind.as_str())
+    .bind(context.otp_code_id)
+    .bind(context.document_id)
+    .bind(payload)
+    .bind(Utc::now())
+    .fetch_one(&mut *conn)
+    .await?;
+
+    record_event(
+        &mut *conn,
+        message.message_id,
+        DeliveryStatus::Pending,
+        None,
+    )
+    .await?;
+    Ok(message)
+}
+
+/// Anota por onde a mensagem saiu e o id devolvido pelo provedor. O conteúdo é
+/// descartado: pode conter códigos que no resto do banco só existem como hash.
+pub async fn record_dispatch(
+    pool: &PgPool,
+    message_id: i64,
+    channel: &str,
+    recipient: &str,
+    provider_sid: &str,
+    status: DeliveryStatus,
+) -> Result<(), sqlx::Error> {
+    let updated = sqlx::query(
+        r#"
+        UPDATE outbound_message
+        SET channel = $1, recipient = $2, provider_sid = $3, status = $4, updated_at = $5,
+            payload = NULL, next_attempt_at = NULL, error_message = NULL
+        WHERE message_id = $6 AND status = ANY($7)
+        "#,
+    )
+    .bind(channel)
+    .bind(recipient)
+    .bind(provider_sid)
+    .bind(status.as_str())
+    .bind(Utc::now())
+    .bind(message_id)
+    .bind(status.replaces())
+    .execute(pool)
+    .await?
+    .rows_affected();
+
+    if updated > 0 {
+        record_event(pool, message_id, status, None).await?;
+    }
+    Ok(())
+}
+
+/// Nenhum canal entregou; a mensagem volta para a fila em `next_attempt_at`.
+pub async fn schedule_retry(
+    pool: &PgPool,
+    message_id: i64,
+    error_message: &str,
+    next_attempt_at: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE outbound_message
+        SET error_message = $1, next_attempt_at = $2, updated_at = $3
+        WHERE message_id = $4
+        "#,
+    )
+    .bind(error_message)
+    .bind(next_attempt_at)
+    .bind(Utc::now())
+    .bind(message_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Mensagens cujo conteúdo traz um código ou link de uso único.
+const SECRET_KINDS: [MessageKind; 3] = [
+    MessageKind::Otp,
+    MessageKind::AccountActivation,
+    MessageKind::PasswordReset,
+];
+
+/// Desiste da mensagem. O conteúdo fica para inspeção, exceto quando traz um código
+/// ou link de uso único, que é descartado como em `record_dispatch`.
+pub async fn dead_letter(
+    pool: &PgPool,
+    message_id: i64,
+    error_message: &str,
+) -> Result<(), sqlx::Error> {
+    let now = Utc::now();
+    sqlx::query(
+        r#"
+        UPDATE outbound_message
+        SET status = $1, error_message = $2, next_attempt_at = NULL, dead_lettered_at = $3,
+            updated_at = $3,
+            payload = CASE WHEN kind = ANY($5) THEN NULL ELSE payload END
+        WHERE message_id = $4
+        "#,
+    )
+    .bind(DeliveryStatus::Failed.as_str())
+    .bind(error_message)
+    .bind(now)
+    .bind(message_id)
+    .bind(SECRET_KINDS.map(|kind| kind.as_str()).to_vec())
+    .execute(pool)
+    .await?;
+
+    record_event(pool, message_id, DeliveryStatus::Failed, None).await
+}
+
+/// Aplica um status vindo do provedor. Devolve `false` se o SID é desconhecido
+/// ou o status é anterior ao já registrado.
+pub async fn apply_provider_status(
+    pool: &PgPool,
+    provider_sid: &str,
+    status: DeliveryStatus,
+    error_code: Option<&str>,
+) -> Result<bool, sqlx::Error> {
+    let updated: Option<(i64,)> = sqlx::query_as(
+        r#"
+        UPDATE outbound_message
+        SET status = $1, error_code = COALESCE($2, error_code), updated_at = $3
+        WHERE provider_sid = $4 AND status = ANY($5)
+        RETURNING message_id
+        "#,
+    )
+    .bind(status.as_str())
+    .bind(error_code)
+    .bind(Utc::now())
+    .bind(provider_sid)
+    .bind(status.replaces())
+    .fetch_optional(pool)
+    .await?;
+
+    match updated {
+        Some((message_id,)) => {
+            record_event(pool, message_id, status, error_code).await?;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+pub async fn find_by_public_id(
+    pool: &PgPool,
+    public_id: &str,
+) -> Result<Option<OutboundMessage>, sqlx::Error> {
+    sqlx::query_as::<_, OutboundMessage>(&format!(
+        "SELECT {} FROM outbound_message WHERE public_id = $1",
+        MESSAGE_COLUMNS
+    ))
+    .bind(public_id)
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn list_for_document(
+    pool: &PgPool,
+    document_id: i64,
+) -> Result<Vec<OutboundMessage>, sqlx::Error> {
+    sqlx::query_as::<_, OutboundMessage>(&format!(
+        "SELECT {} FROM outbound_message WHERE document_id = $1 ORDER BY created_at DESC",
+        MESSAGE_COLUMNS
+    ))
+    .bind(document_id)
+    .fetch_all(pool)
+    .await
+}
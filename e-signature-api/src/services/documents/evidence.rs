@@ -0,0 +1,65 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{FromRow, PgPool};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvidenceKind {
+    FaceVerification,
+}
+
+impl EvidenceKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EvidenceKind::FaceVerification => "face_verification",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct SigningEvidence {
+    pub evidence_id: i64,
+    pub signer_id: i64,
+    pub kind: String,
+    pub details: serde_json::Value,
+    pub recorded_at: DateTime<Utc>,
+}
+
+pub async fn record(
+    pool: &PgPool,
+    document_id: i64,
+    signer_id: i64,
+    kind: EvidenceKind,
+    details: serde_json::Value,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO signing_evidence (document_id, signer_id, kind, details, recorded_at)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+    )
+    .bind(document_id)
+    .bind(signer_id)
+    .bind(kind.as_str())
+    .bind(details)
+    .bind(Utc::now())
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn list_for_document(
+    pool: &PgPool,
+    document_id: i64,
+) -> Result<Vec<SigningEvidence>, sqlx::Error> {
+    sqlx::query_as::<_, SigningEvidence>(
+        r#"
+        SELECT evidence_id, signer_id, kind, details, recorded_at
+        FROM signing_evidence
+        WHERE document_id = $1
+        ORDER BY recorded_at
+        "#,
+    )
+    .bind(document_id)
+    .fetch_all(pool)
+    .await
+}
@@ -1,2 +1,4 @@
+pub mod evidence;
 pub mod models;
+pub mod notifications;
 pub mod services;
@@ -1,3 +1,4 @@
+use crate::services::tax_ids;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
@@ -29,6 +30,18 @@ pub struct CreateDocument {
     pub photo_id_url: Option<String>,
 }
 
+pub const DOCUMENT_STATUS_PENDING: i32 = 1;
+pub const DOCUMENT_STATUS_SIGNED: i32 = 2;
+
+#[derive(Debug, FromRow)]
+pub struct DocumentSignerContact {
+    pub full_name: String,
+    pub contact_email: String,
+    pub phone_number: String,
+    pub signed_at: Option<DateTime<Utc>>,
+    pub locale: Option<String>,
+}
+
 #[derive(Deserialize, Debug)]
 pub struct UpdateDocument {
     pub file_name: Option<String>,
@@ -39,6 +52,7 @@ pub struct UpdateDocument {
 pub struct Signer {
     pub signer_id: i64,
     pub full_name: String,
+    #[serde(serialize_with = "tax_ids::serialize_cpf")]
     pub national_id: String,
     pub phone_number: String,
     pub contact_email: String,
@@ -49,13 +63,3 @@ pub struct Signer {
     pub user_id: Option<i64>,
     pub photo_id_url: Option<String>,
 }
-
-#[derive(Deserialize, Debug)]
-pub struct CreateSigner {
-    pub full_name: String,
-    pub doc_id: i64,
-    pub email: String,
-    pub phone_number: String,
-    pub national_id: String,
-    pub photo_id_url: String,
-}
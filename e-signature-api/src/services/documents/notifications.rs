@@ -0,0 +1,179 @@
+use super::models::{Document, DocumentSignerContact};
+use super::services as document_service;
+use crate::services::deliveries::{MessageContext, MessageKind};
+use crate::services::email::email::Attachment;
+use crate::services::email::templates::{self, EmailTemplate};
+use crate::services::errors::ServiceError;
+use crate::services::i18n::{self, Locale};
+use crate::services::notifications::{ChannelKind, Notification, Recipient};
+use crate::services::outbox;
+use crate::services::policy::DocumentScope;
+use sqlx::PgPool;
+use std::path::Path;
+
+fn signer_recipient(signer: &DocumentSignerContact) -> Recipient {
+    // Mensagens de documento vão primeiro por e-mail; os outros canais ficam de reserva.
+    Recipient {
+        email: Some(signer.contact_email.clone()),
+        phone_number: Some(signer.phone_number.clone()),
+        preferred_channels: vec![ChannelKind::Email],
+        locale: signer.locale.as_deref().and_then(Locale::parse),
+        ..Recipient::default()
+    }
+}
+
+async fn company_contact(
+    pool: &PgPool,
+    company_id: i64,
+) -> Result<Option<(String, String)>, sqlx::Error> {
+    sqlx::query_as("SELECT legal_name, contact_email FROM company WHERE company_id = $1")
+        .bind(company_id)
+        .fetch_optional(pool)
+        .await
+}
+
+pub async fn send_invitations(
+    pool: &PgPool,
+    scope: DocumentScope,
+    document: &Document,
+) -> Result<(), ServiceError> {
+    let company_name = company_contact(pool, document.company_id)
+        .await?
+        .map(|(legal_name, _)| legal_name)
+        .unwrap_or_else(|| i18n::t("email.unknown_company"));
+    let sign_url = templates::document_url(document.document_id);
+    let signers =
+        document_service::get_document_signer_contacts(pool, scope, document.document_id).await?;
+
+    // Todos os convites entram juntos na caixa de saída.
+    let mut tx = pool.begin().await?;
+    for signer in signers {
+        let recipient = signer_recipient(&signer);
+        let email = templates::render(
+            EmailTemplate::SigningInvitation,
+            recipient.locale(),
+            &[
+                ("name", &signer.full_name),
+                ("company_name", &company_name),
+                ("document_name", &document.file_name),
+                ("sign_url", &sign_url),
+            ],
+        );
+        outbox::enqueue(
+            &mut tx,
+            recipient,
+            email.into(),
+            MessageContext::for_document(MessageKind::SigningInvitation, document.document_id),
+        )
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Reenvia o pedido de assinatura para quem ainda não assinou. Devolve quantos foram avisados.
+pub async fn send_reminders(
+    pool: &PgPool,
+    scope: DocumentScope,
+    document: &Document,
+) -> Result<usize, ServiceError> {
+    let sign_url = templates::document_url(document.document_id);
+    let pending: Vec<DocumentSignerContact> =
+        document_service::get_document_signer_contacts(pool, scope, document.document_id)
+            .await?
+            .into_iter()
+            .filter(|signer| signer.signed_at.is_none())
+            .collect();
+
+    let mut tx = pool.begin().await?;
+    for signer in &pending {
+        let recipient = signer_recipient(signer);
+        let email = templates::render(
+            EmailTemplate::SigningReminder,
+            recipient.locale(),
+            &[
+                ("name", &signer.full_name),
+                ("document_name", &document.file_name),
+                ("sign_url", &sign_url),
+            ],
+        );
+        outbox::enqueue(
+            &mut tx,
+            recipient,
+            email.into(),
+            MessageContext::for_document(MessageKind::SigningReminder, document.document_id),
+        )
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(pending.len())
+}
+
+/// Avisa signatários e empresa que o documento foi concluído, com o PDF enviado em anexo.
+/// Ainda não há uma via assinada gerada pelo sistema; o hash permite conferir o arquivo.
+pub async fn send_completion_notices(
+    pool: &PgPool,
+    scope: DocumentScope,
+    document: &Document,
+) -> Result<(), ServiceError> {
+    let attachment = match tokio::fs::read(&document.file_path).await {
+        Ok(content) => Some(Attachment {
+            filename: Path::new(&document.file_name)
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| format!("documento-{}.pdf", document.document_id)),
+            content_type: "application/pdf".to_string(),
+            content,
+        }),
+        Err(e) => {
+            eprintln!(
+                "Falha ao ler o documento {} para anexar: {:?}",
+                document.document_id, e
+            );
+            None
+        }
+    };
+
+    let mut recipients: Vec<(String, Recipient)> =
+        document_service::get_document_signer_contacts(pool, scope, document.document_id)
+            .await?
+            .iter()
+            .map(|signer| (signer.full_name.clone(), signer_recipient(signer)))
+            .collect();
+    if let Some((legal_name, contact_email)) = company_contact(pool, document.company_id).await? {
+        recipients.push((
+            legal_name,
+            Recipient {
+                email: Some(contact_email),
+                preferred_channels: vec![ChannelKind::Email],
+                ..Recipient::default()
+            },
+        ));
+    }
+
+    let mut tx = pool.begin().await?;
+    for (name, recipient) in recipients {
+        let email = templates::render(
+            EmailTemplate::SigningCompleted,
+            recipient.locale(),
+            &[
+                ("name", &name),
+                ("document_name", &document.file_name),
+                ("document_hash", document.hash_sha256.trim()),
+            ],
+        );
+        let mut notification = Notification::from(email);
+        if let Some(attachment) = &attachment {
+            notification = notification.with_attachment(attachment.clone());
+        }
+        outbox::enqueue(
+            &mut tx,
+            recipient,
+            notification,
+            MessageContext::for_document(MessageKind::SigningCompleted, document.document_id),
+        )
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
@@ -1,11 +1,28 @@
-use super::models::{CreateDocument, CreateSigner, Document, Signer, UpdateDocument};
+use super::models::{CreateDocument, Document, DocumentSignerContact, UpdateDocument};
+use crate::services::errors::AppError;
+use crate::services::phones;
+use crate::services::policy::DocumentScope;
+use crate::services::tax_ids;
+use crate::services::tenant;
 use sqlx::PgPool;
 
 pub async fn create_document_and_signer(
     pool: &PgPool,
+    scope: DocumentScope,
     new_document: CreateDocument,
-) -> Result<Document, sqlx::Error> {
-    let mut tx = pool.begin().await?;
+) -> Result<Document, AppError> {
+    let national_id = new_document
+        .signer_national_id
+        .as_deref()
+        .and_then(tax_ids::normalize_cpf)
+        .ok_or_else(|| AppError::unprocessable("signer.invalid_national_id"))?;
+    let phone_number = new_document
+        .signer_phone_number
+        .as_deref()
+        .and_then(phones::normalize)
+        .ok_or_else(|| AppError::unprocessable("phone.invalid"))?;
+
+    let mut tx = tenant::begin(pool, scope).await?;
     let document = sqlx::query_as!(
         Document,
         r#"
@@ -22,13 +39,17 @@ pub async fn create_document_and_signer(
     .fetch_one(&mut *tx)
     .await?;
 
+    // Signatários são compartilhados entre empresas (deduplicados pelo CPF),
+    // então a busca/criação roda fora do escopo do tenant.
+    tenant::set_scope(&mut tx, DocumentScope::All).await?;
+
     let existing_signer = sqlx::query!(
         r#"
         SELECT signer_id
         FROM signer
         WHERE national_id = $1 AND deleted_at IS NULL
         "#,
-        new_document.signer_national_id
+        national_id
     )
     .fetch_optional(&mut *tx)
     .await?;
@@ -43,9 +64,9 @@ pub async fn create_document_and_signer(
             RETURNING signer_id
             "#,
             new_document.signer_full_name,
-            new_document.signer_phone_number,
+            phone_number,
             new_document.signer_email,
-            new_document.signer_national_id,
+            national_id,
             new_document.photo_id_url
         )
         .fetch_one(&mut *tx)
@@ -53,6 +74,8 @@ pub async fn create_document_and_signer(
         new_signer.signer_id
     };
 
+    tenant::set_scope(&mut tx, scope).await?;
+
     sqlx::query!(
         r#"
         INSERT INTO document_signer (document_id, signer_id, status_id)
@@ -70,45 +93,76 @@ pub async fn create_document_and_signer(
     Ok(document)
 }
 
-pub async fn get_all_documents(pool: &PgPool) -> Result<Vec<Document>, sqlx::Error> {
+pub async fn get_all_documents(
+    pool: &PgPool,
+    scope: DocumentScope,
+) -> Result<Vec<Document>, sqlx::Error> {
+    if scope == DocumentScope::Nothing {
+        return Ok(Vec::new());
+    }
+
+    let mut tx = tenant::begin(pool, scope).await?;
     let documents = sqlx::query_as!(
         Document,
         r#"
         SELECT document_id, company_id, file_name, file_path, hash_sha256, status_id, created_at, updated_at, deleted_at
-        FROM document
+        FROM document d
         WHERE deleted_at IS NULL
+          AND ($1::BIGINT IS NULL OR company_id = $1)
+          AND ($2::BIGINT IS NULL OR EXISTS (
+                SELECT 1 FROM document_signer ds
+                WHERE ds.document_id = d.document_id AND ds.signer_id = $2
+              ))
         ORDER BY document_id
-        "#
+        "#,
+        scope.company_id(),
+        scope.signer_id()
     )
-    .fetch_all(pool)
+    .fetch_all(&mut *tx)
     .await?;
+    tx.commit().await?;
     Ok(documents)
 }
 
 pub async fn get_document_by_id(
     pool: &PgPool,
+    scope: DocumentScope,
     document_id: i64,
 ) -> Result<Option<Document>, sqlx::Error> {
+    if scope == DocumentScope::Nothing {
+        return Ok(None);
+    }
+
+    let mut tx = tenant::begin(pool, scope).await?;
     let document = sqlx::query_as!(
         Document,
         r#"
         SELECT document_id, company_id, file_name, file_path, hash_sha256, status_id, created_at, updated_at, deleted_at
-        FROM document
+        FROM document d
         WHERE document_id = $1 AND deleted_at IS NULL
+          AND ($2::BIGINT IS NULL OR company_id = $2)
+          AND ($3::BIGINT IS NULL OR EXISTS (
+                SELECT 1 FROM document_signer ds
+                WHERE ds.document_id = d.document_id AND ds.signer_id = $3
+              ))
         "#,
-        document_id
+        document_id,
+        scope.company_id(),
+        scope.signer_id()
     )
-    .fetch_optional(pool)
+    .fetch_optional(&mut *tx)
     .await?;
+    tx.commit().await?;
     Ok(document)
 }
 
 pub async fn update_document(
     pool: &PgPool,
+    scope: DocumentScope,
     document_id: i64,
     data: UpdateDocument,
 ) -> Result<Option<Document>, sqlx::Error> {
-    let current_document = match get_document_by_id(pool, document_id).await? {
+    let current_document = match get_document_by_id(pool, scope, document_id).await? {
         Some(document) => document,
         None => return Ok(None),
     };
@@ -117,34 +171,47 @@ pub async fn update_document(
     let status_id = data.status_id.unwrap_or(current_document.status_id);
     let now = chrono::Utc::now();
 
+    let mut tx = tenant::begin(pool, scope).await?;
     let updated_document = sqlx::query_as!(
         Document,
         r#"
         UPDATE document
         SET file_name = $1, status_id = $2, updated_at = $3
-        WHERE document_id = $4
+        WHERE document_id = $4 AND ($5::BIGINT IS NULL OR company_id = $5)
         RETURNING document_id, company_id, file_name, file_path, hash_sha256, status_id, created_at, updated_at, deleted_at
         "#,
         file_name,
         status_id,
         now,
-        document_id
+        document_id,
+        scope.company_id()
     )
-    .fetch_one(pool)
+    .fetch_optional(&mut *tx)
     .await?;
+    tx.commit().await?;
 
-    Ok(Some(updated_document))
+    Ok(updated_document)
 }
 
-pub async fn delete_document(pool: &PgPool, document_id: i64) -> Result<u64, sqlx::Error> {
+pub async fn delete_document(
+    pool: &PgPool,
+    scope: DocumentScope,
+    document_id: i64,
+) -> Result<u64, sqlx::Error> {
     let now = chrono::Utc::now();
+    let mut tx = tenant::begin(pool, scope).await?;
     let result = sqlx::query!(
-        "UPDATE document SET deleted_at = $1 WHERE document_id = $2 AND deleted_at IS NULL",
+        r#"
+        UPDATE document SET deleted_at = $1
+        WHERE document_id = $2 AND deleted_at IS NULL AND ($3::BIGINT IS NULL OR company_id = $3)
+        "#,
         now,
-        document_id
+        document_id,
+        scope.company_id()
     )
-    .execute(pool)
+    .execute(&mut *tx)
     .await?;
+    tx.commit().await?;
 
     Ok(result.rows_affected())
 }
@@ -168,3 +235,30 @@ pub async fn delete_document(pool: &PgPool, document_id: i64) -> Result<u64, sql
 
     Ok(signers)
 }*/
+
+pub async fn get_document_signer_contacts(
+    pool: &PgPool,
+    scope: DocumentScope,
+    document_id: i64,
+) -> Result<Vec<DocumentSignerContact>, sqlx::Error> {
+    if scope == DocumentScope::Nothing {
+        return Ok(Vec::new());
+    }
+
+    let mut tx = tenant::begin(pool, scope).await?;
+    let signers = sqlx::query_as::<_, DocumentSignerContact>(
+        r#"
+        SELECT s.full_name, s.contact_email, s.phone_number, ds.signed_at, u.locale
+        FROM document_signer ds
+        JOIN signer s ON s.signer_id = ds.signer_id AND s.deleted_at IS NULL
+        LEFT JOIN user_account u ON u.user_id = s.user_id
+        WHERE ds.document_id = $1
+        ORDER BY ds.sign_order NULLS LAST, s.full_name
+        "#,
+    )
+    .bind(document_id)
+    .fetch_all(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(signers)
+}
@@ -0,0 +1,111 @@
+use lettre::message::header::ContentType;
+use lettre::message::{MultiPart, SinglePart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::error::Error;
+use std::sync::LazyLock;
+use uuid::Uuid;
+
+type SendError = Box<dyn Error + Send + Sync>;
+
+/// Transporte SMTP configurado por SMTP_HOST, SMTP_PORT, SMTP_TLS (none, starttls
+/// ou tls), SMTP_USERNAME e SMTP_PASSWORD. Sem SMTP_HOST o canal de e-mail fica desligado.
+static TRANSPORT: LazyLock<Option<AsyncSmtpTransport<Tokio1Executor>>> = LazyLock::new(|| {
+    let host = env::var("SMTP_HOST").ok()?;
+    let tls = env::var("SMTP_TLS").unwrap_or_else(|_| "starttls".to_string());
+
+    let builder = match tls.as_str() {
+        "none" => Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(
+            &host,
+        )),
+        "tls" => AsyncSmtpTransport::<Tokio1Executor>::relay(&host),
+        _ => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&host),
+    };
+    let mut builder = match builder {
+        Ok(builder) => builder,
+        Err(e) => {
+            eprintln!("Configuração SMTP inválida: {:?}", e);
+            return None;
+        }
+    };
+
+    if let Some(port) = env::var("SMTP_PORT").ok().and_then(|p| p.parse().ok()) {
+        builder = builder.port(port);
+    }
+    if let (Ok(username), Ok(password)) = (env::var("SMTP_USERNAME"), env::var("SMTP_PASSWORD")) {
+        builder = builder.credentials(Credentials::new(username, password));
+    }
+
+    Some(builder.build())
+});
+
+fn from_address() -> String {
+    env::var("SMTP_FROM").unwrap_or_else(|_| "e-Signature <no-reply@e-signature.local>".to_string())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attachment {
+    pub filename: String,
+    pub content_type: String,
+    #[serde(with = "base64_content")]
+    pub content: Vec<u8>,
+}
+
+/// Anexos vão para a caixa de saída como base64, não como array JSON de bytes.
+mod base64_content {
+    use base64::{engine::general_purpose, Engine as _};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(content: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&general_purpose::STANDARD.encode(content))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        general_purpose::STANDARD
+            .decode(encoded)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Envia um e-mail com versão texto e, se houver, HTML e anexos. Devolve o Message-ID gerado.
+pub async fn send_email(
+    to: &str,
+    subject: &str,
+    text: &str,
+    html: Option<&str>,
+    attachments: &[Attachment],
+) -> Result<String, SendError> {
+    let transport = TRANSPORT.as_ref().ok_or("SMTP_HOST não está definido")?;
+    let message_id = format!("<{}@e-signature>", Uuid::new_v4());
+
+    let mut body = match html {
+        Some(html) => MultiPart::alternative_plain_html(text.to_string(), html.to_string()),
+        None => MultiPart::alternative().singlepart(SinglePart::plain(text.to_string())),
+    };
+    if !attachments.is_empty() {
+        let mut mixed = MultiPart::mixed().multipart(body);
+        for attachment in attachments {
+            mixed = mixed.singlepart(
+                lettre::message::Attachment::new(attachment.filename.clone()).body(
+                    attachment.content.clone(),
+                    ContentType::parse(&attachment.content_type)?,
+                ),
+            );
+        }
+        body = mixed;
+    }
+
+    let message = Message::builder()
+        .from(from_address().parse()?)
+        .to(to.parse()?)
+        .subject(subject)
+        .message_id(Some(message_id.clone()))
+        .multipart(body)?;
+
+    transport.send(message).await?;
+    println!("E-mail enviado para {}", to);
+    Ok(message_id)
+}
@@ -0,0 +1,3 @@
+#[allow(clippy::module_inception)]
+pub mod email;
+pub mod templates;
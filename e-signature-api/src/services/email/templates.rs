@@ -0,0 +1,156 @@
+use crate::services::i18n::{self, Locale};
+use std::env;
+
+const LAYOUT: &str = include_str!("../../../templates/email/layout.html");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmailTemplate {
+    SigningInvitation,
+    SigningReminder,
+    SigningCompleted,
+    OtpCode,
+    PasswordReset,
+}
+
+#[derive(Debug, Clone)]
+pub struct RenderedEmail {
+    pub subject: String,
+    pub html: String,
+    pub text: String,
+}
+
+/// Versões HTML e texto de um template no idioma pedido.
+macro_rules! localized_sources {
+    ($locale:expr, $name:literal) => {
+        match $locale {
+            Locale::PtBr => (
+                include_str!(concat!("../../../templates/email/pt-BR/", $name, ".html")),
+                include_str!(concat!("../../../templates/email/pt-BR/", $name, ".txt")),
+            ),
+            Locale::En => (
+                include_str!(concat!("../../../templates/email/en/", $name, ".html")),
+                include_str!(concat!("../../../templates/email/en/", $name, ".txt")),
+            ),
+            Locale::Es => (
+                include_str!(concat!("../../../templates/email/es/", $name, ".html")),
+                include_str!(concat!("../../../templates/email/es/", $name, ".txt")),
+            ),
+        }
+    };
+}
+
+impl EmailTemplate {
+    /// Chave do assunto no catálogo de mensagens.
+    fn subject_key(&self) -> &'static str {
+        match self {
+            EmailTemplate::SigningInvitation => "email.subject.signing_invitation",
+            EmailTemplate::SigningReminder => "email.subject.signing_reminder",
+            EmailTemplate::SigningCompleted => "email.subject.signing_completed",
+            EmailTemplate::OtpCode => "email.subject.otp_code",
+            EmailTemplate::PasswordReset => "email.subject.password_reset",
+        }
+    }
+
+    fn sources(&self, locale: Locale) -> (&'static str, &'static str) {
+        match self {
+            EmailTemplate::SigningInvitation => localized_sources!(locale, "signing_invitation"),
+            EmailTemplate::SigningReminder => localized_sources!(locale, "signing_reminder"),
+            EmailTemplate::SigningCompleted => localized_sources!(locale, "signing_completed"),
+            EmailTemplate::OtpCode => localized_sources!(locale, "otp_code"),
+            EmailTemplate::PasswordReset => localized_sources!(locale, "password_reset"),
+        }
+    }
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Troca cada `{{nome}}` numa única passada, para que um valor que contenha `{{outro}}`
+/// não seja expandido de novo. Campos sem valor ficam como estão.
+fn substitute(source: &str, vars: &[(&str, String)]) -> String {
+    let mut output = String::with_capacity(source.len());
+    let mut rest = source;
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            break;
+        };
+        output.push_str(&rest[..start]);
+        let name = &after[..end];
+        match vars.iter().find(|(var, _)| *var == name) {
+            Some((_, value)) => output.push_str(value),
+            None => output.push_str(&rest[start..start + 2 + end + 2]),
+        }
+        rest = &after[end + 2..];
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Preenche os `{{campos}}` do template no idioma pedido. Os valores são escapados na
+/// versão HTML.
+pub fn render(template: EmailTemplate, locale: Locale, vars: &[(&str, &str)]) -> RenderedEmail {
+    let (html_source, text_source) = template.sources(locale);
+    let plain: Vec<(&str, String)> = vars.iter().map(|(k, v)| (*k, v.to_string())).collect();
+    let escaped: Vec<(&str, String)> = vars.iter().map(|(k, v)| (*k, escape_html(v))).collect();
+
+    let subject = i18n::translate_with(locale, template.subject_key(), vars);
+    let content = substitute(html_source, &escaped);
+    let html = substitute(
+        LAYOUT,
+        &[
+            ("lang", locale.tag().to_string()),
+            ("subject", escape_html(&subject)),
+            (
+                "footer",
+                escape_html(&i18n::translate(locale, "email.footer")),
+            ),
+            ("content", content),
+        ],
+    );
+
+    RenderedEmail {
+        subject,
+        html,
+        text: substitute(text_source, &plain),
+    }
+}
+
+/// Endereço do front-end usado nos links dos e-mails (APP_URL).
+pub fn document_url(document_id: i64) -> String {
+    let base = env::var("APP_URL").unwrap_or_else(|_| "http://localhost:3000".to_string());
+    format!("{}/documents/{}", base.trim_end_matches('/'), document_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn substitute_fills_fields() {
+        let vars = [("name", "Ana".to_string()), ("code", "123456".to_string())];
+        assert_eq!(
+            substitute("Olá, {{name}}. Código: {{code}}.", &vars),
+            "Olá, Ana. Código: 123456."
+        );
+        assert_eq!(
+            substitute("{{missing}} {{name", &vars),
+            "{{missing}} {{name"
+        );
+    }
+
+    #[test]
+    fn substitute_does_not_expand_values() {
+        let vars = [
+            ("name", "{{code}}".to_string()),
+            ("code", "123456".to_string()),
+        ];
+        assert_eq!(substitute("{{name}}: {{code}}", &vars), "{{code}}: 123456");
+    }
+}
@@ -0,0 +1,317 @@
+use crate::services::i18n;
+use crate::services::request_id;
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, ResponseError};
+use serde_json::{json, Map, Value};
+use std::fmt;
+use std::time::Duration;
+
+const PROBLEM_CONTENT_TYPE: &str = "application/problem+json";
+
+/// Código estável do erro, que também é a chave da mensagem nos catálogos, com as
+/// variáveis da mensagem e campos extras do corpo (ex.: `reasons`). A causa de falhas
+/// internas só vai para o log.
+#[derive(Debug)]
+pub struct ErrorDetail {
+    code: &'static str,
+    vars: Vec<(&'static str, String)>,
+    extensions: Map<String, Value>,
+    cause: Option<String>,
+}
+
+impl ErrorDetail {
+    fn new(code: &'static str) -> Self {
+        ErrorDetail {
+            code,
+            vars: Vec::new(),
+            extensions: Map::new(),
+            cause: None,
+        }
+    }
+
+    fn message(&self) -> String {
+        let vars: Vec<(&str, &str)> = self
+            .vars
+            .iter()
+            .map(|(name, value)| (*name, value.as_str()))
+            .collect();
+        i18n::t_with(self.code, &vars)
+    }
+}
+
+/// Erro de uma requisição. Vira uma resposta RFC 7807 (`application/problem+json`) com
+/// o código estável em `code` e o id da requisição em `request_id`.
+#[derive(Debug)]
+pub enum AppError {
+    BadRequest(ErrorDetail),
+    Unauthorized(ErrorDetail),
+    Forbidden(ErrorDetail),
+    NotFound(ErrorDetail),
+    Conflict(ErrorDetail),
+    Unprocessable(ErrorDetail),
+    TooManyRequests(Duration),
+    Unavailable(ErrorDetail),
+    /// Um serviço externo (ex.: o provedor OIDC) falhou ou respondeu algo inválido.
+    BadGateway(ErrorDetail),
+    /// Falha interna: o cliente recebe só o código e uma mensagem fixa.
+    Internal(ErrorDetail),
+    Database(sqlx::Error),
+}
+
+impl AppError {
+    pub fn bad_request(code: &'static str) -> Self {
+        AppError::BadRequest(ErrorDetail::new(code))
+    }
+
+    pub fn unauthorized(code: &'static str) -> Self {
+        AppError::Unauthorized(ErrorDetail::new(code))
+    }
+
+    pub fn forbidden() -> Self {
+        AppError::forbidden_for("policy.forbidden")
+    }
+
+    pub fn forbidden_for(code: &'static str) -> Self {
+        AppError::Forbidden(ErrorDetail::new(code))
+    }
+
+    pub fn not_found(code: &'static str) -> Self {
+        AppError::NotFound(ErrorDetail::new(code))
+    }
+
+    pub fn conflict(code: &'static str) -> Self {
+        AppError::Conflict(ErrorDetail::new(code))
+    }
+
+    pub fn unprocessable(code: &'static str) -> Self {
+        AppError::Unprocessable(ErrorDetail::new(code))
+    }
+
+    pub fn unavailable(code: &'static str) -> Self {
+        AppError::Unavailable(ErrorDetail::new(code))
+    }
+
+    pub fn bad_gateway(code: &'static str, cause: impl fmt::Display) -> Self {
+        let mut detail = ErrorDetail::new(code);
+        detail.cause = Some(cause.to_string());
+        AppError::BadGateway(detail)
+    }
+
+    pub fn internal(code: &'static str, cause: impl fmt::Display) -> Self {
+        let mut detail = ErrorDetail::new(code);
+        detail.cause = Some(cause.to_string());
+        AppError::Internal(detail)
+    }
+
+    fn detail_mut(&mut self) -> Option<&mut ErrorDetail> {
+        match self {
+            AppError::BadRequest(detail)
+            | AppError::Unauthorized(detail)
+            | AppError::Forbidden(detail)
+            | AppError::NotFound(detail)
+            | AppError::Conflict(detail)
+            | AppError::Unprocessable(detail)
+            | AppError::Unavailable(detail)
+            | AppError::BadGateway(detail)
+            | AppError::Internal(detail) => Some(detail),
+            AppError::TooManyRequests(_) | AppError::Database(_) => None,
+        }
+    }
+
+    /// Valor para `{name}` na mensagem.
+    pub fn with_var(mut self, name: &'static str, value: impl fmt::Display) -> Self {
+        if let Some(detail) = self.detail_mut() {
+            detail.vars.push((name, value.to_string()));
+        }
+        self
+    }
+
+    /// Campo extra no corpo do problema, ao lado dos campos padrão.
+    pub fn with_extension(mut self, name: &str, value: impl Into<Value>) -> Self {
+        if let Some(detail) = self.detail_mut() {
+            detail.extensions.insert(name.to_string(), value.into());
+        }
+        self
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::BadRequest(detail)
+            | AppError::Unauthorized(detail)
+            | AppError::Forbidden(detail)
+            | AppError::NotFound(detail)
+            | AppError::Conflict(detail)
+            | AppError::Unprocessable(detail)
+            | AppError::Unavailable(detail)
+            | AppError::BadGateway(detail)
+            | AppError::Internal(detail) => detail.code,
+            AppError::TooManyRequests(_) => "rate_limit.too_many_requests",
+            AppError::Database(_) => "database.query_failed",
+        }
+    }
+}
+
+impl From<sqlx::Error> for AppError {
+    fn from(e: sqlx::Error) -> Self {
+        AppError::Database(e)
+    }
+}
+
+/// Falha de um serviço fora das requisições HTTP: banco, ou as etapas em volta dele que
+/// não são consultas (serialização, hash de senha, tokens, QR code, criptografia).
+#[derive(Debug)]
+pub enum ServiceError {
+    Database(sqlx::Error),
+    Serialization(serde_json::Error),
+    PasswordHash(bcrypt::BcryptError),
+    Token(jsonwebtoken::errors::Error),
+    QrCode(String),
+    Crypto(&'static str),
+}
+
+impl From<sqlx::Error> for ServiceError {
+    fn from(e: sqlx::Error) -> Self {
+        ServiceError::Database(e)
+    }
+}
+
+impl From<serde_json::Error> for ServiceError {
+    fn from(e: serde_json::Error) -> Self {
+        ServiceError::Serialization(e)
+    }
+}
+
+impl From<bcrypt::BcryptError> for ServiceError {
+    fn from(e: bcrypt::BcryptError) -> Self {
+        ServiceError::PasswordHash(e)
+    }
+}
+
+impl From<jsonwebtoken::errors::Error> for ServiceError {
+    fn from(e: jsonwebtoken::errors::Error) -> Self {
+        ServiceError::Token(e)
+    }
+}
+
+impl fmt::Display for ServiceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServiceError::Database(e) => write!(f, "{}", e),
+            ServiceError::Serialization(e) => write!(f, "falha de serialização: {}", e),
+            ServiceError::PasswordHash(e) => write!(f, "falha no hash da senha: {}", e),
+            ServiceError::Token(e) => write!(f, "falha ao criar token: {}", e),
+            ServiceError::QrCode(e) => write!(f, "falha ao gerar QR code: {}", e),
+            ServiceError::Crypto(e) => write!(f, "falha de criptografia: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ServiceError {}
+
+impl From<ServiceError> for AppError {
+    fn from(e: ServiceError) -> Self {
+        match e {
+            ServiceError::Database(e) => AppError::Database(e),
+            e => AppError::internal("error.internal", e),
+        }
+    }
+}
+
+/// Para `map_err`: violação de unicidade vira 409 com `code`; outros erros do banco, 500.
+pub fn conflict_on_unique(code: &'static str) -> impl Fn(sqlx::Error) -> AppError {
+    move |e| match e.as_database_error() {
+        Some(db_err) if db_err.is_unique_violation() => AppError::conflict(code),
+        _ => AppError::Database(e),
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::Database(e) => write!(f, "{}: {}", self.code(), e),
+            AppError::TooManyRequests(retry_after) => {
+                write!(f, "{} ({}s)", self.code(), retry_after.as_secs())
+            }
+            AppError::Internal(detail) | AppError::BadGateway(detail) => match &detail.cause {
+                Some(cause) => write!(f, "{}: {}", detail.code, cause),
+                None => write!(f, "{}", detail.code),
+            },
+            _ => write!(f, "{}", self.code()),
+        }
+    }
+}
+
+impl ResponseError for AppError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AppError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::Unprocessable(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+            AppError::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            AppError::BadGateway(_) => StatusCode::BAD_GATEWAY,
+            AppError::Internal(_) | AppError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let status = self.status_code();
+        let request_id = request_id::current();
+        if status.is_server_error() {
+            eprintln!(
+                "Erro na requisição {}: {}",
+                request_id.as_deref().unwrap_or("-"),
+                self
+            );
+        }
+
+        let (detail, mut body) = match self {
+            AppError::TooManyRequests(retry_after) => {
+                let mut extensions = Map::new();
+                extensions.insert(
+                    "retry_after_seconds".to_string(),
+                    json!(retry_after.as_secs().max(1)),
+                );
+                (i18n::t(self.code()), extensions)
+            }
+            AppError::Internal(_) | AppError::Database(_) => (
+                i18n::t_with(
+                    "error.internal",
+                    &[("request_id", request_id.as_deref().unwrap_or("-"))],
+                ),
+                Map::new(),
+            ),
+            AppError::BadRequest(detail)
+            | AppError::Unauthorized(detail)
+            | AppError::Forbidden(detail)
+            | AppError::NotFound(detail)
+            | AppError::Conflict(detail)
+            | AppError::Unprocessable(detail)
+            | AppError::Unavailable(detail)
+            | AppError::BadGateway(detail) => (detail.message(), detail.extensions.clone()),
+        };
+
+        body.insert(
+            "type".to_string(),
+            json!(format!("urn:e-signature:error:{}", self.code())),
+        );
+        body.insert(
+            "title".to_string(),
+            json!(status.canonical_reason().unwrap_or_default()),
+        );
+        body.insert("status".to_string(), json!(status.as_u16()));
+        body.insert("detail".to_string(), json!(detail));
+        body.insert("code".to_string(), json!(self.code()));
+        body.insert("request_id".to_string(), json!(request_id));
+
+        let mut response = HttpResponse::build(status);
+        response.content_type(PROBLEM_CONTENT_TYPE);
+        if let AppError::TooManyRequests(retry_after) = self {
+            response.insert_header(("Retry-After", retry_after.as_secs().max(1).to_string()));
+        }
+        response.body(Value::Object(body).to_string())
+    }
+}
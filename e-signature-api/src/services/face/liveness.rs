@@ -0,0 +1,137 @@
+use image::imageops::{self, FilterType};
+use image::Rgba32FImage;
+use insightface::Face;
+use ndarray::Array4;
+use serde::Serialize;
+use std::f32::consts::PI;
+
+/// Entrada do MiniFASNet (Silent-Face-Anti-Spoofing): recorte 2.7x o rosto, em 80x80.
+pub(super) const ANTI_SPOOF_SIZE: u32 = 80;
+pub(super) const ANTI_SPOOF_CROP_SCALE: f32 = 2.7;
+/// Lado do recorte analisado em frequência; a DFT direta custa O(n³).
+const MOIRE_SIZE: usize = 64;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Liveness {
+    pub passed: bool,
+    pub score: f32,
+    pub threshold: f32,
+    pub moire_score: f32,
+    pub moire_threshold: f32,
+}
+
+/// Recorta um quadro `scale` vezes maior que o rosto, centrado nele e sem sair da
+/// imagem, redimensionado para `size` x `size`.
+pub(super) fn crop_around(
+    image: &Rgba32FImage,
+    face: &Face,
+    scale: f32,
+    size: u32,
+) -> Rgba32FImage {
+    let (width, height) = (image.width() as f32, image.height() as f32);
+    let (x1, y1, x2, y2) = face.bbox;
+    let (box_width, box_height) = ((x2 - x1).max(1.0), (y2 - y1).max(1.0));
+    let scale = scale
+        .min((width - 1.0) / box_width)
+        .min((height - 1.0) / box_height)
+        .max(1.0);
+
+    let crop_width = (box_width * scale).min(width);
+    let crop_height = (box_height * scale).min(height);
+    let left = ((x1 + x2) / 2.0 - crop_width / 2.0).clamp(0.0, width - crop_width);
+    let top = ((y1 + y2) / 2.0 - crop_height / 2.0).clamp(0.0, height - crop_height);
+
+    let crop = imageops::crop_imm(
+        image,
+        left as u32,
+        top as u32,
+        (crop_width as u32).max(1),
+        (crop_height as u32).max(1),
+    )
+    .to_image();
+    imageops::resize(&crop, size, size, FilterType::Triangle)
+}
+
+/// O MiniFASNet foi treinado com imagens BGR em 0..255, sem normalização.
+pub(super) fn anti_spoof_tensor(crop: &Rgba32FImage) -> Array4<f32> {
+    let (width, height) = crop.dimensions();
+    Array4::from_shape_fn(
+        (1, 3, height as usize, width as usize),
+        |(_, channel, y, x)| crop.get_pixel(x as u32, y as u32)[2 - channel] * 255.0,
+    )
+}
+
+/// Probabilidade da classe "rosto real" (índice 1) nas saídas do modelo.
+pub(super) fn real_probability(logits: &[f32]) -> f32 {
+    let max = logits.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let exps: Vec<f32> = logits.iter().map(|logit| (logit - max).exp()).collect();
+    let total: f32 = exps.iter().sum();
+    match exps.get(1) {
+        Some(real) if total > 0.0 => real / total,
+        _ => 0.0,
+    }
+}
+
+/// Uma tela fotografada deixa moiré: picos isolados nas altas frequências do
+/// espectro do rosto. Devolve a razão entre o maior pico dessa faixa e a média dela;
+/// pele e fundo reais dão um espectro espalhado e razões baixas.
+pub(super) fn moire_score(image: &Rgba32FImage, face: &Face) -> f32 {
+    let crop = crop_around(image, face, 1.0, MOIRE_SIZE as u32);
+    let n = MOIRE_SIZE;
+
+    let gray: Vec<f32> = crop
+        .pixels()
+        .map(|p| 0.299 * p[0] + 0.587 * p[1] + 0.114 * p[2])
+        .collect();
+    let mean = gray.iter().sum::<f32>() / gray.len() as f32;
+
+    let (cos, sin): (Vec<f32>, Vec<f32>) = (0..n)
+        .map(|k| {
+            let angle = 2.0 * PI * k as f32 / n as f32;
+            (angle.cos(), angle.sin())
+        })
+        .unzip();
+
+    // DFT separável: primeiro as linhas, depois as colunas.
+    let mut rows_re = vec![0f32; n * n];
+    let mut rows_im = vec![0f32; n * n];
+    for y in 0..n {
+        for u in 0..n {
+            let (mut re, mut im) = (0.0, 0.0);
+            for x in 0..n {
+                let value = gray[y * n + x] - mean;
+                re += value * cos[(u * x) % n];
+                im -= value * sin[(u * x) % n];
+            }
+            rows_re[y * n + u] = re;
+            rows_im[y * n + u] = im;
+        }
+    }
+
+    let (mut peak, mut total, mut count) = (0f32, 0f32, 0usize);
+    for v in 0..n {
+        for u in 0..n {
+            let (mut re, mut im) = (0.0, 0.0);
+            for y in 0..n {
+                let (c, s) = (cos[(v * y) % n], sin[(v * y) % n]);
+                re += rows_re[y * n + u] * c + rows_im[y * n + u] * s;
+                im += rows_im[y * n + u] * c - rows_re[y * n + u] * s;
+            }
+
+            let fu = u.min(n - u) as f32;
+            let fv = v.min(n - v) as f32;
+            if (fu * fu + fv * fv).sqrt() < n as f32 / 4.0 {
+                continue;
+            }
+            let magnitude = (re * re + im * im).sqrt();
+            peak = peak.max(magnitude);
+            total += magnitude;
+            count += 1;
+        }
+    }
+
+    if count == 0 || total == 0.0 {
+        return 0.0;
+    }
+    peak / (total / count as f32)
+}
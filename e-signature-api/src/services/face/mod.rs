@@ -0,0 +1,473 @@
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Utc};
+use image::imageops::{self, FilterType};
+use image::{ImageFormat, Rgba32FImage};
+use insightface::{calculate_embedding, crop_face, detect_faces, Face};
+use ndarray::Array4;
+use ort::execution_providers::CPUExecutionProvider;
+use ort::inputs;
+use ort::session::builder::GraphOptimizationLevel;
+use ort::session::Session;
+use ort::value::Value;
+use serde::Serialize;
+use std::env;
+use std::io::Cursor;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+mod liveness;
+mod quality;
+
+pub use liveness::Liveness;
+use quality::QualityThresholds;
+pub use quality::{FaceQuality, ImageRole, QualityIssue, QualityIssueCode};
+
+/// Lado da entrada do detector (SCRFD `det_10g` do pacote buffalo_l).
+const DETECTOR_SIZE: u32 = 640;
+/// Lado do rosto alinhado que o ArcFace espera.
+const ALIGNED_SIZE: u32 = 112;
+/// Retrato recortado de documento: folga em volta do rosto e lado final, em pixels.
+const PORTRAIT_CROP_SCALE: f32 = 1.8;
+const PORTRAIT_SIZE: u32 = 320;
+const DEFAULT_DETECTOR_MODEL: &str = "models/det_10g.onnx";
+const DEFAULT_EMBEDDING_MODEL: &str = "models/w600k_r50.onnx";
+const DEFAULT_ANTI_SPOOF_MODEL: &str = "models/2.7_80x80_MiniFASNetV2.onnx";
+const DEFAULT_DETECTION_THRESHOLD: f32 = 0.5;
+const DEFAULT_MATCH_THRESHOLD: f32 = 0.4;
+const DEFAULT_LIVENESS_THRESHOLD: f32 = 0.8;
+const DEFAULT_MOIRE_THRESHOLD: f32 = 30.0;
+const NMS_THRESHOLD: f32 = 0.4;
+
+pub type Embedding = Vec<f32>;
+
+#[derive(Debug)]
+pub enum FaceError {
+    NotLoaded,
+    InvalidImage(String),
+    QualityRejected(Vec<QualityIssue>),
+    NoReferencePhoto,
+    NotEnrolled,
+    SpoofDetected(Liveness),
+    ReferenceMismatch(FaceMatch),
+    TooManyAttempts(DateTime<Utc>),
+    Inference(String),
+    Database(sqlx::Error),
+}
+
+impl From<sqlx::Error> for FaceError {
+    fn from(e: sqlx::Error) -> Self {
+        FaceError::Database(e)
+    }
+}
+
+impl From<ort::Error> for FaceError {
+    fn from(e: ort::Error) -> Self {
+        FaceError::Inference(e.to_string())
+    }
+}
+
+impl std::fmt::Display for FaceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FaceError::NotLoaded => write!(f, "modelos de reconhecimento facial não carregados"),
+            FaceError::InvalidImage(e) => write!(f, "imagem inválida: {}", e),
+            FaceError::QualityRejected(issues) => write!(
+                f,
+                "imagem recusada: {}",
+                issues
+                    .iter()
+                    .map(|issue| issue.code.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            FaceError::NoReferencePhoto => write!(f, "signatário sem foto de referência"),
+            FaceError::NotEnrolled => write!(f, "rosto não cadastrado"),
+            FaceError::SpoofDetected(liveness) => write!(
+                f,
+                "prova de vida reprovada (score {:.3}, moiré {:.1})",
+                liveness.score, liveness.moire_score
+            ),
+            FaceError::ReferenceMismatch(face_match) => write!(
+                f,
+                "rosto não confere com a referência atual (similaridade {:.3})",
+                face_match.similarity
+            ),
+            FaceError::TooManyAttempts(until) => {
+                write!(f, "limite de tentativas atingido até {}", until)
+            }
+            FaceError::Inference(e) => write!(f, "falha na inferência: {}", e),
+            FaceError::Database(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FaceMatch {
+    #[serde(rename = "match")]
+    pub matched: bool,
+    pub similarity: f32,
+    pub threshold: f32,
+    pub model_version: String,
+    pub liveness: Liveness,
+    pub quality: FaceQuality,
+}
+
+/// Sessões do ONNX Runtime. `Session::run` exige acesso exclusivo, daí o `Mutex`.
+struct FaceEngine {
+    detector: Mutex<Session>,
+    recognizer: Mutex<Session>,
+    anti_spoof: Mutex<Session>,
+    model_version: String,
+    detection_threshold: f32,
+    match_threshold: f32,
+    liveness_threshold: f32,
+    moire_threshold: f32,
+    live_quality: QualityThresholds,
+    reference_quality: QualityThresholds,
+}
+
+/// Rosto que passou pelas verificações de qualidade, já alinhado para o embedding.
+struct InspectedFace {
+    face: Face,
+    aligned: Rgba32FImage,
+    quality: FaceQuality,
+}
+
+static ENGINE: OnceLock<FaceEngine> = OnceLock::new();
+
+fn env_f32(name: &str, default: f32) -> f32 {
+    env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+/// O ONNX Runtime é carregado em tempo de execução (`ORT_DYLIB_PATH`, ou `libonnxruntime.so`
+/// no caminho de bibliotecas do sistema). O `ort` entra em pânico se a biblioteca não
+/// existe; aqui isso vira erro, para o servidor subir sem os recursos que dependem dela.
+fn load_runtime() -> Result<(), ort::Error> {
+    std::panic::catch_unwind(|| ort::init().commit())
+        .map_err(|_| ort::Error::new("biblioteca do ONNX Runtime não encontrada (ORT_DYLIB_PATH)"))?
+        .map(|_| ())
+}
+
+pub(crate) fn load_session(path: &str) -> Result<Session, ort::Error> {
+    load_runtime()?;
+    let threads = env::var("FACE_ONNX_THREADS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(1);
+
+    Session::builder()?
+        .with_execution_providers([CPUExecutionProvider::default().build()])?
+        .with_optimization_level(GraphOptimizationLevel::Level3)?
+        .with_intra_threads(threads)?
+        .commit_from_file(path)
+}
+
+/// Carrega os modelos uma única vez, na subida do servidor. Só usa CPU.
+pub fn initialize() -> Result<(), ort::Error> {
+    let detector_path =
+        env::var("FACE_DETECTOR_MODEL").unwrap_or_else(|_| DEFAULT_DETECTOR_MODEL.to_string());
+    let embedding_path =
+        env::var("FACE_EMBEDDING_MODEL").unwrap_or_else(|_| DEFAULT_EMBEDDING_MODEL.to_string());
+    let anti_spoof_path =
+        env::var("FACE_ANTI_SPOOF_MODEL").unwrap_or_else(|_| DEFAULT_ANTI_SPOOF_MODEL.to_string());
+
+    // A versão acompanha o arquivo do modelo: embeddings de modelos diferentes não se comparam.
+    let model_version = Path::new(&embedding_path)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("unknown")
+        .to_string();
+
+    let engine = FaceEngine {
+        detector: Mutex::new(load_session(&detector_path)?),
+        recognizer: Mutex::new(load_session(&embedding_path)?),
+        anti_spoof: Mutex::new(load_session(&anti_spoof_path)?),
+        model_version,
+        detection_threshold: env_f32("FACE_DETECTION_THRESHOLD", DEFAULT_DETECTION_THRESHOLD),
+        match_threshold: env_f32("FACE_MATCH_THRESHOLD", DEFAULT_MATCH_THRESHOLD),
+        liveness_threshold: env_f32("FACE_LIVENESS_THRESHOLD", DEFAULT_LIVENESS_THRESHOLD),
+        moire_threshold: env_f32("FACE_MOIRE_THRESHOLD", DEFAULT_MOIRE_THRESHOLD),
+        live_quality: QualityThresholds::from_env(ImageRole::Live),
+        reference_quality: QualityThresholds::from_env(ImageRole::Reference),
+    };
+    let _ = ENGINE.set(engine);
+    Ok(())
+}
+
+fn engine() -> Result<&'static FaceEngine, FaceError> {
+    ENGINE.get().ok_or(FaceError::NotLoaded)
+}
+
+/// Aceita base64 puro ou uma data URL (`data:image/jpeg;base64,...`).
+pub fn decode_base64_image(value: &str) -> Result<Vec<u8>, FaceError> {
+    let encoded = match value.split_once(";base64,") {
+        Some((prefix, data)) if prefix.starts_with("data:") => data,
+        _ => value,
+    };
+    general_purpose::STANDARD
+        .decode(encoded.trim())
+        .map_err(|e| FaceError::InvalidImage(e.to_string()))
+}
+
+fn to_tensor(image: &Rgba32FImage) -> Array4<f32> {
+    let (width, height) = image.dimensions();
+    Array4::from_shape_fn(
+        (1, 3, height as usize, width as usize),
+        |(_, channel, y, x)| (image.get_pixel(x as u32, y as u32)[channel] - 0.5) / 0.5,
+    )
+}
+
+/// Supressão de não-máximos sobre faces já ordenadas por score.
+fn non_maximum_suppression(faces: Vec<Face>) -> Vec<Face> {
+    let area = |face: &Face| (face.bbox.2 - face.bbox.0) * (face.bbox.3 - face.bbox.1);
+    let mut keep: Vec<Face> = Vec::new();
+
+    for face in faces {
+        let overlaps = keep.iter().any(|kept| {
+            let width = (face.bbox.2.min(kept.bbox.2) - face.bbox.0.max(kept.bbox.0)).max(0.0);
+            let height = (face.bbox.3.min(kept.bbox.3) - face.bbox.1.max(kept.bbox.1)).max(0.0);
+            let intersection = width * height;
+            intersection / (area(&face) + area(kept) - intersection) > NMS_THRESHOLD
+        });
+        if !overlaps {
+            keep.push(face);
+        }
+    }
+    keep
+}
+
+impl FaceEngine {
+    /// Reduz a imagem para caber em 640x640 sem distorcer, completando com preto, e
+    /// devolve as faces em coordenadas da imagem original.
+    fn detect(&self, image: &Rgba32FImage) -> Vec<Face> {
+        let (width, height) = image.dimensions();
+        let scale = DETECTOR_SIZE as f32 / width.max(height) as f32;
+        let resized = imageops::resize(
+            image,
+            ((width as f32 * scale).round() as u32).max(1),
+            ((height as f32 * scale).round() as u32).max(1),
+            FilterType::Triangle,
+        );
+        let mut canvas = Rgba32FImage::new(DETECTOR_SIZE, DETECTOR_SIZE);
+        imageops::replace(&mut canvas, &resized, 0, 0);
+
+        let mut detector = self.detector.lock().unwrap_or_else(|e| e.into_inner());
+        let faces = detect_faces(&mut detector, to_tensor(&canvas), self.detection_threshold);
+        non_maximum_suppression(faces)
+            .into_iter()
+            .map(|face| face * (1.0 / scale))
+            .collect()
+    }
+
+    /// Exige exatamente um rosto e checa tamanho, nitidez, brilho e pose. Todos os
+    /// problemas encontrados voltam juntos, para o usuário corrigir de uma vez.
+    fn inspect(&self, image: &Rgba32FImage, role: ImageRole) -> Result<InspectedFace, FaceError> {
+        let faces = self.detect(image);
+        let face = match faces.as_slice() {
+            [face] => *face,
+            [] => {
+                return Err(FaceError::QualityRejected(vec![QualityIssue::new(
+                    QualityIssueCode::NoFace,
+                    role,
+                )]))
+            }
+            _ => {
+                return Err(FaceError::QualityRejected(vec![QualityIssue::new(
+                    QualityIssueCode::MultipleFaces,
+                    role,
+                )]))
+            }
+        };
+
+        // Alinha o rosto pelos 5 pontos de referência, no formato que o ArcFace espera.
+        let aligned = crop_face(image, &face.keypoints, ALIGNED_SIZE);
+        let quality = quality::measure(&face, &aligned);
+        let thresholds = match role {
+            ImageRole::Live => &self.live_quality,
+            ImageRole::Reference => &self.reference_quality,
+        };
+        let issues = thresholds.issues(&quality, role);
+        if !issues.is_empty() {
+            return Err(FaceError::QualityRejected(issues));
+        }
+        Ok(InspectedFace {
+            face,
+            aligned,
+            quality,
+        })
+    }
+
+    fn embed(&self, inspected: &InspectedFace) -> Embedding {
+        let mut recognizer = self.recognizer.lock().unwrap_or_else(|e| e.into_inner());
+        let embedding = calculate_embedding(&mut recognizer, to_tensor(&inspected.aligned));
+        normalize(&embedding)
+    }
+
+    /// Prova de vida passiva: o modelo anti-spoofing precisa reconhecer um rosto real e
+    /// o recorte não pode ter o moiré típico de uma tela fotografada.
+    fn check_liveness(&self, image: &Rgba32FImage, face: &Face) -> Result<Liveness, FaceError> {
+        let crop = liveness::crop_around(
+            image,
+            face,
+            liveness::ANTI_SPOOF_CROP_SCALE,
+            liveness::ANTI_SPOOF_SIZE,
+        );
+        let input = Value::from_array(liveness::anti_spoof_tensor(&crop))?;
+        let score = {
+            let mut anti_spoof = self.anti_spoof.lock().unwrap_or_else(|e| e.into_inner());
+            let outputs = anti_spoof.run(inputs![input])?;
+            let (_, logits) = outputs[0].try_extract_tensor::<f32>()?;
+            liveness::real_probability(logits)
+        };
+        let moire_score = liveness::moire_score(image, face);
+
+        Ok(Liveness {
+            passed: score >= self.liveness_threshold && moire_score < self.moire_threshold,
+            score,
+            threshold: self.liveness_threshold,
+            moire_score,
+            moire_threshold: self.moire_threshold,
+        })
+    }
+}
+
+fn decode_image(bytes: &[u8]) -> Result<Rgba32FImage, FaceError> {
+    image::load_from_memory(bytes)
+        .map(|image| image.to_rgba32f())
+        .map_err(|e| FaceError::InvalidImage(e.to_string()))
+}
+
+fn normalize(vector: &[f32]) -> Embedding {
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm == 0.0 {
+        return vector.to_vec();
+    }
+    vector.iter().map(|v| v / norm).collect()
+}
+
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norms =
+        a.iter().map(|v| v * v).sum::<f32>().sqrt() * b.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norms == 0.0 {
+        0.0
+    } else {
+        dot / norms
+    }
+}
+
+/// Roda a inferência fora das threads do servidor. Um pânico dentro do ONNX Runtime
+/// vira erro da verificação em vez de derrubar o worker.
+async fn run_blocking<T, F>(task: F) -> Result<T, FaceError>
+where
+    T: Send + 'static,
+    F: FnOnce(&'static FaceEngine) -> Result<T, FaceError> + Send + 'static,
+{
+    let engine = engine()?;
+    tokio::task::spawn_blocking(move || task(engine))
+        .await
+        .map_err(|e| FaceError::Inference(e.to_string()))?
+}
+
+/// Versão do modelo de embedding carregado, gravada junto de cada cadastro facial.
+pub fn model_version() -> Option<&'static str> {
+    ENGINE.get().map(|engine| engine.model_version.as_str())
+}
+
+/// Embedding do rosto de uma imagem de referência (selfie ou foto do documento), para
+/// cadastro.
+pub async fn compute_embedding(image: Vec<u8>) -> Result<Embedding, FaceError> {
+    run_blocking(move |engine| {
+        let image = decode_image(&image)?;
+        let inspected = engine.inspect(&image, ImageRole::Reference)?;
+        Ok(engine.embed(&inspected))
+    })
+    .await
+}
+
+/// Passa a imagem ao vivo pelas verificações de qualidade e pela prova de vida e compara
+/// o rosto com um embedding já cadastrado pela similaridade de cosseno. Uma fraude é
+/// rejeitada antes da comparação.
+pub async fn verify(reference: Embedding, live: Vec<u8>) -> Result<FaceMatch, FaceError> {
+    run_blocking(move |engine| {
+        let image = decode_image(&live)?;
+        let inspected = engine.inspect(&image, ImageRole::Live)?;
+        let liveness = engine.check_liveness(&image, &inspected.face)?;
+        if !liveness.passed {
+            return Err(FaceError::SpoofDetected(liveness));
+        }
+
+        let similarity = cosine_similarity(&reference, &engine.embed(&inspected));
+        Ok(FaceMatch {
+            matched: similarity >= engine.match_threshold,
+            similarity,
+            threshold: engine.match_threshold,
+            model_version: engine.model_version.clone(),
+            liveness,
+            quality: inspected.quality,
+        })
+    })
+    .await
+}
+
+/// Só a prova de vida de uma imagem ao vivo, para quando ainda não há com o que comparar.
+pub async fn verify_liveness(live: Vec<u8>) -> Result<Liveness, FaceError> {
+    run_blocking(move |engine| {
+        let image = decode_image(&live)?;
+        let inspected = engine.inspect(&image, ImageRole::Live)?;
+        let liveness = engine.check_liveness(&image, &inspected.face)?;
+        if !liveness.passed {
+            return Err(FaceError::SpoofDetected(liveness));
+        }
+        Ok(liveness)
+    })
+    .await
+}
+
+/// Recorta o retrato de uma foto de documento (RG/CNH) com folga em volta do rosto e
+/// devolve o recorte em PNG, pronto para o cadastro facial. `None` se não há rosto.
+pub async fn crop_portrait(document: Vec<u8>) -> Result<Option<Vec<u8>>, FaceError> {
+    run_blocking(move |engine| {
+        let image = decode_image(&document)?;
+        let area = |face: &Face| (face.bbox.2 - face.bbox.0) * (face.bbox.3 - face.bbox.1);
+        let Some(face) = engine
+            .detect(&image)
+            .into_iter()
+            .max_by(|a, b| area(a).total_cmp(&area(b)))
+        else {
+            return Ok(None);
+        };
+
+        let crop = liveness::crop_around(&image, &face, PORTRAIT_CROP_SCALE, PORTRAIT_SIZE);
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgba32F(crop)
+            .to_rgb8()
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .map_err(|e| FaceError::InvalidImage(e.to_string()))?;
+        Ok(Some(png))
+    })
+    .await
+}
+
+/// Formato gravado em `user_account.face_embedding`: f32 little-endian em sequência.
+pub fn embedding_to_bytes(embedding: &[f32]) -> Vec<u8> {
+    embedding
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect()
+}
+
+pub fn embedding_from_bytes(bytes: &[u8]) -> Option<Embedding> {
+    if bytes.is_empty() || !bytes.len().is_multiple_of(4) {
+        return None;
+    }
+    Some(
+        bytes
+            .chunks_exact(4)
+            .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+            .collect(),
+    )
+}
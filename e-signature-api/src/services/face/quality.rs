@@ -0,0 +1,253 @@
+use image::Rgba32FImage;
+use insightface::Face;
+use serde::Serialize;
+
+const DEFAULT_MIN_FACE_SIZE: f32 = 80.0;
+const DEFAULT_MIN_SHARPNESS: f32 = 40.0;
+const DEFAULT_MIN_BRIGHTNESS: f32 = 50.0;
+const DEFAULT_MAX_BRIGHTNESS: f32 = 210.0;
+const DEFAULT_MAX_POSE_ANGLE: f32 = 25.0;
+// A foto de referência (documento, selfie de cadastro) costuma ser pequena, comprimida e
+// com iluminação de scanner, então os limites são mais frouxos que os da captura ao vivo.
+const REFERENCE_MIN_FACE_SIZE: f32 = 40.0;
+const REFERENCE_MIN_SHARPNESS: f32 = 15.0;
+const REFERENCE_MIN_BRIGHTNESS: f32 = 30.0;
+const REFERENCE_MAX_BRIGHTNESS: f32 = 235.0;
+const REFERENCE_MAX_POSE_ANGLE: f32 = 35.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageRole {
+    Live,
+    Reference,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QualityIssueCode {
+    NoFace,
+    MultipleFaces,
+    FaceTooSmall,
+    TooBlurry,
+    TooDark,
+    TooBright,
+    PoseNotFrontal,
+}
+
+impl QualityIssueCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QualityIssueCode::NoFace => "no_face",
+            QualityIssueCode::MultipleFaces => "multiple_faces",
+            QualityIssueCode::FaceTooSmall => "face_too_small",
+            QualityIssueCode::TooBlurry => "too_blurry",
+            QualityIssueCode::TooDark => "too_dark",
+            QualityIssueCode::TooBright => "too_bright",
+            QualityIssueCode::PoseNotFrontal => "pose_not_frontal",
+        }
+    }
+}
+
+/// Motivo de recusa de uma imagem, com o valor medido e o limite configurado.
+#[derive(Debug, Clone, Serialize)]
+pub struct QualityIssue {
+    pub code: QualityIssueCode,
+    pub image: ImageRole,
+    pub value: Option<f32>,
+    pub limit: Option<f32>,
+}
+
+impl QualityIssue {
+    pub(super) fn new(code: QualityIssueCode, image: ImageRole) -> Self {
+        QualityIssue {
+            code,
+            image,
+            value: None,
+            limit: None,
+        }
+    }
+
+    fn measured(code: QualityIssueCode, image: ImageRole, value: f32, limit: f32) -> Self {
+        QualityIssue {
+            code,
+            image,
+            value: Some(value),
+            limit: Some(limit),
+        }
+    }
+}
+
+/// Medidas do rosto: tamanho em pixels (menor lado da caixa), nitidez (variância do
+/// laplaciano), brilho médio em 0..255 e ângulos aproximados da cabeça em graus.
+#[derive(Debug, Clone, Serialize)]
+pub struct FaceQuality {
+    pub face_size: f32,
+    pub sharpness: f32,
+    pub brightness: f32,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub roll: f32,
+}
+
+pub(super) struct QualityThresholds {
+    min_face_size: f32,
+    min_sharpness: f32,
+    min_brightness: f32,
+    max_brightness: f32,
+    max_pose_angle: f32,
+}
+
+impl QualityThresholds {
+    /// Limites da imagem ao vivo (`FACE_*`) ou da de referência (`FACE_REFERENCE_*`).
+    pub(super) fn from_env(role: ImageRole) -> Self {
+        match role {
+            ImageRole::Live => QualityThresholds {
+                min_face_size: super::env_f32("FACE_MIN_SIZE", DEFAULT_MIN_FACE_SIZE),
+                min_sharpness: super::env_f32("FACE_MIN_SHARPNESS", DEFAULT_MIN_SHARPNESS),
+                min_brightness: super::env_f32("FACE_MIN_BRIGHTNESS", DEFAULT_MIN_BRIGHTNESS),
+                max_brightness: super::env_f32("FACE_MAX_BRIGHTNESS", DEFAULT_MAX_BRIGHTNESS),
+                max_pose_angle: super::env_f32("FACE_MAX_POSE_ANGLE", DEFAULT_MAX_POSE_ANGLE),
+            },
+            ImageRole::Reference => QualityThresholds {
+                min_face_size: super::env_f32("FACE_REFERENCE_MIN_SIZE", REFERENCE_MIN_FACE_SIZE),
+                min_sharpness: super::env_f32(
+                    "FACE_REFERENCE_MIN_SHARPNESS",
+                    REFERENCE_MIN_SHARPNESS,
+                ),
+                min_brightness: super::env_f32(
+                    "FACE_REFERENCE_MIN_BRIGHTNESS",
+                    REFERENCE_MIN_BRIGHTNESS,
+                ),
+                max_brightness: super::env_f32(
+                    "FACE_REFERENCE_MAX_BRIGHTNESS",
+                    REFERENCE_MAX_BRIGHTNESS,
+                ),
+                max_pose_angle: super::env_f32(
+                    "FACE_REFERENCE_MAX_POSE_ANGLE",
+                    REFERENCE_MAX_POSE_ANGLE,
+                ),
+            },
+        }
+    }
+
+    pub(super) fn issues(&self, quality: &FaceQuality, image: ImageRole) -> Vec<QualityIssue> {
+        let mut issues = Vec::new();
+        if quality.face_size < self.min_face_size {
+            issues.push(QualityIssue::measured(
+                QualityIssueCode::FaceTooSmall,
+                image,
+                quality.face_size,
+                self.min_face_size,
+            ));
+        }
+        if quality.sharpness < self.min_sharpness {
+            issues.push(QualityIssue::measured(
+                QualityIssueCode::TooBlurry,
+                image,
+                quality.sharpness,
+                self.min_sharpness,
+            ));
+        }
+        if quality.brightness < self.min_brightness {
+            issues.push(QualityIssue::measured(
+                QualityIssueCode::TooDark,
+                image,
+                quality.brightness,
+                self.min_brightness,
+            ));
+        }
+        if quality.brightness > self.max_brightness {
+            issues.push(QualityIssue::measured(
+                QualityIssueCode::TooBright,
+                image,
+                quality.brightness,
+                self.max_brightness,
+            ));
+        }
+        let pose = quality
+            .yaw
+            .abs()
+            .max(quality.pitch.abs())
+            .max(quality.roll.abs());
+        if pose > self.max_pose_angle {
+            issues.push(QualityIssue::measured(
+                QualityIssueCode::PoseNotFrontal,
+                image,
+                pose,
+                self.max_pose_angle,
+            ));
+        }
+        issues
+    }
+}
+
+fn luminance(image: &Rgba32FImage) -> Vec<f32> {
+    image
+        .pixels()
+        .map(|p| (0.299 * p[0] + 0.587 * p[1] + 0.114 * p[2]) * 255.0)
+        .collect()
+}
+
+/// Variância do laplaciano: bordas nítidas dão valores altos, fotos tremidas ou fora
+/// de foco ficam perto de zero.
+fn laplacian_variance(gray: &[f32], width: usize, height: usize) -> f32 {
+    if width < 3 || height < 3 {
+        return 0.0;
+    }
+    let mut responses = Vec::with_capacity((width - 2) * (height - 2));
+    for y in 1..height - 1 {
+        for x in 1..width - 1 {
+            let center = gray[y * width + x];
+            responses.push(
+                gray[(y - 1) * width + x]
+                    + gray[(y + 1) * width + x]
+                    + gray[y * width + x - 1]
+                    + gray[y * width + x + 1]
+                    - 4.0 * center,
+            );
+        }
+    }
+    let mean = responses.iter().sum::<f32>() / responses.len() as f32;
+    responses.iter().map(|r| (r - mean).powi(2)).sum::<f32>() / responses.len() as f32
+}
+
+/// Ângulos estimados pelos 5 pontos (olhos, nariz, cantos da boca). O giro (roll) vem
+/// da linha dos olhos; desfeito o giro, o desvio do nariz em relação ao meio dos olhos
+/// dá a guinada (yaw) e a altura do nariz entre olhos e boca dá a inclinação (pitch).
+fn head_pose(keypoints: &[(f32, f32); 5]) -> (f32, f32, f32) {
+    let [left_eye, right_eye, nose, left_mouth, right_mouth] = *keypoints;
+    let roll = (right_eye.1 - left_eye.1).atan2(right_eye.0 - left_eye.0);
+    let (sin, cos) = (-roll).sin_cos();
+    let rotate = |(x, y): (f32, f32)| (x * cos - y * sin, x * sin + y * cos);
+
+    let (left_eye, right_eye, nose) = (rotate(left_eye), rotate(right_eye), rotate(nose));
+    let mouth = rotate((
+        (left_mouth.0 + right_mouth.0) / 2.0,
+        (left_mouth.1 + right_mouth.1) / 2.0,
+    ));
+    let eyes = (
+        (left_eye.0 + right_eye.0) / 2.0,
+        (left_eye.1 + right_eye.1) / 2.0,
+    );
+    let eye_distance = (right_eye.0 - left_eye.0).abs().max(1.0);
+    let eyes_to_mouth = (mouth.1 - eyes.1).abs().max(1.0);
+
+    let yaw = (2.0 * (nose.0 - eyes.0) / eye_distance).atan();
+    let pitch = (2.0 * ((nose.1 - eyes.1) / eyes_to_mouth - 0.5)).atan();
+    (yaw.to_degrees(), pitch.to_degrees(), roll.to_degrees())
+}
+
+/// Mede o rosto detectado; nitidez e brilho são calculados no rosto já alinhado, para
+/// não dependerem da resolução da foto.
+pub(super) fn measure(face: &Face, aligned: &Rgba32FImage) -> FaceQuality {
+    let gray = luminance(aligned);
+    let (yaw, pitch, roll) = head_pose(&face.keypoints);
+    FaceQuality {
+        face_size: (face.bbox.2 - face.bbox.0).min(face.bbox.3 - face.bbox.1),
+        sharpness: laplacian_variance(&gray, aligned.width() as usize, aligned.height() as usize),
+        brightness: gray.iter().sum::<f32>() / gray.len().max(1) as f32,
+        yaw,
+        pitch,
+        roll,
+    }
+}
@@ -0,0 +1,219 @@
+use crate::services::face::{FaceError, FaceMatch};
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Duration, Utc};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use sqlx::{PgConnection, PgPool};
+use std::env;
+
+const DEFAULT_MAX_FAILURES: i64 = 5;
+const DEFAULT_WINDOW_HOURS: i64 = 24;
+const DEFAULT_FRAME_RETENTION_DAYS: i64 = 30;
+const PURGE_INTERVAL_MINUTES: u64 = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttemptOutcome {
+    Matched,
+    NotMatched,
+    SpoofDetected,
+    QualityRejected,
+    InvalidImage,
+    NotEnrolled,
+    Blocked,
+    Error,
+}
+
+impl AttemptOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AttemptOutcome::Matched => "matched",
+            AttemptOutcome::NotMatched => "not_matched",
+            AttemptOutcome::SpoofDetected => "spoof_detected",
+            AttemptOutcome::QualityRejected => "quality_rejected",
+            AttemptOutcome::InvalidImage => "invalid_image",
+            AttemptOutcome::NotEnrolled => "not_enrolled",
+            AttemptOutcome::Blocked => "blocked",
+            AttemptOutcome::Error => "error",
+        }
+    }
+
+    pub fn from_result(result: &Result<FaceMatch, FaceError>) -> Self {
+        match result {
+            Ok(face_match) if face_match.matched => AttemptOutcome::Matched,
+            Ok(_) => AttemptOutcome::NotMatched,
+            Err(FaceError::SpoofDetected(_)) => AttemptOutcome::SpoofDetected,
+            Err(FaceError::QualityRejected(_)) => AttemptOutcome::QualityRejected,
+            Err(FaceError::InvalidImage(_)) => AttemptOutcome::InvalidImage,
+            Err(FaceError::NotEnrolled | FaceError::NoReferencePhoto) => {
+                AttemptOutcome::NotEnrolled
+            }
+            Err(FaceError::TooManyAttempts(_)) => AttemptOutcome::Blocked,
+            Err(_) => AttemptOutcome::Error,
+        }
+    }
+}
+
+/// Só rosto diferente ou fraude contam para o limite; imagem ruim o usuário corrige
+/// e tenta de novo.
+const COUNTED_FAILURES: [AttemptOutcome; 2] =
+    [AttemptOutcome::NotMatched, AttemptOutcome::SpoofDetected];
+
+fn env_i64(name: &str, default: i64) -> i64 {
+    env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .filter(|value| *value > 0)
+        .unwrap_or(default)
+}
+
+/// Chave AES-256 (base64) para guardar o quadro ao vivo. Sem ela só o hash é gravado.
+fn frame_key() -> Option<LessSafeKey> {
+    let key = general_purpose::STANDARD
+        .decode(env::var("FACE_FRAME_KEY").ok()?)
+        .ok()?;
+    UnboundKey::new(&AES_256_GCM, &key)
+        .ok()
+        .map(LessSafeKey::new)
+}
+
+fn seal_frame(frame: &[u8]) -> Option<(Vec<u8>, Vec<u8>)> {
+    let key = frame_key()?;
+    let mut nonce = [0u8; NONCE_LEN];
+    SystemRandom::new().fill(&mut nonce).ok()?;
+
+    let mut ciphertext = frame.to_vec();
+    key.seal_in_place_append_tag(
+        Nonce::assume_unique_for_key(nonce),
+        Aad::empty(),
+        &mut ciphertext,
+    )
+    .ok()?;
+    Some((ciphertext, nonce.to_vec()))
+}
+
+/// Até quando o signatário está impedido de tentar de novo neste documento: depois de
+/// FACE_MAX_FAILURES falhas dentro da janela, até a mais antiga delas sair da janela.
+pub async fn blocked_until(
+    conn: &mut PgConnection,
+    signer_id: i64,
+    document_id: Option<i64>,
+) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+    let max_failures = env_i64("FACE_MAX_FAILURES", DEFAULT_MAX_FAILURES);
+    let window = Duration::hours(env_i64("FACE_ATTEMPT_WINDOW_HOURS", DEFAULT_WINDOW_HOURS));
+
+    let failures: Vec<(DateTime<Utc>,)> = sqlx::query_as(
+        r#"
+        SELECT attempted_at FROM face_verification_attempt
+        WHERE signer_id = $1 AND document_id IS NOT DISTINCT FROM $2
+          AND outcome = ANY($3) AND attempted_at > $4
+        ORDER BY attempted_at DESC
+        LIMIT $5
+        "#,
+    )
+    .bind(signer_id)
+    .bind(document_id)
+    .bind(COUNTED_FAILURES.map(|outcome| outcome.as_str()).to_vec())
+    .bind(Utc::now() - window)
+    .bind(max_failures)
+    .fetch_all(conn)
+    .await?;
+
+    if (failures.len() as i64) < max_failures {
+        return Ok(None);
+    }
+    Ok(failures
+        .last()
+        .map(|(attempted_at,)| *attempted_at + window))
+}
+
+/// Registra a tentativa com o resultado e, se houver, o quadro ao vivo enviado.
+pub async fn record(
+    conn: &mut PgConnection,
+    signer_id: i64,
+    document_id: Option<i64>,
+    result: &Result<FaceMatch, FaceError>,
+    frame: Option<&[u8]>,
+) -> Result<(), sqlx::Error> {
+    let (similarity, liveness_score, model_version, details) = match result {
+        Ok(face_match) => (
+            Some(face_match.similarity),
+            Some(face_match.liveness.score),
+            Some(face_match.model_version.clone()),
+            json!(face_match),
+        ),
+        Err(FaceError::SpoofDetected(liveness)) => (
+            None,
+            Some(liveness.score),
+            None,
+            json!({ "liveness": liveness }),
+        ),
+        Err(FaceError::QualityRejected(issues)) => (None, None, None, json!({ "reasons": issues })),
+        Err(e) => (None, None, None, json!({ "error": e.to_string() })),
+    };
+    let frame_sha256 = frame.map(|frame| format!("{:x}", Sha256::digest(frame)));
+    let sealed = frame.and_then(seal_frame);
+
+    sqlx::query(
+        r#"
+        INSERT INTO face_verification_attempt (
+            signer_id, document_id, outcome, similarity, liveness_score, model_version, details,
+            frame_sha256, frame_ciphertext, frame_nonce, attempted_at
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+        "#,
+    )
+    .bind(signer_id)
+    .bind(document_id)
+    .bind(AttemptOutcome::from_result(result).as_str())
+    .bind(similarity)
+    .bind(liveness_score)
+    .bind(model_version)
+    .bind(details)
+    .bind(frame_sha256)
+    .bind(sealed.as_ref().map(|(ciphertext, _)| ciphertext.clone()))
+    .bind(sealed.map(|(_, nonce)| nonce))
+    .bind(Utc::now())
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Apaga os quadros cifrados mais antigos que FACE_FRAME_RETENTION_DAYS. O hash e o
+/// resultado da tentativa continuam no histórico.
+pub async fn purge_expired_frames(pool: &PgPool) -> Result<u64, sqlx::Error> {
+    let retention = Duration::days(env_i64(
+        "FACE_FRAME_RETENTION_DAYS",
+        DEFAULT_FRAME_RETENTION_DAYS,
+    ));
+    let now = Utc::now();
+    let result = sqlx::query(
+        r#"
+        UPDATE face_verification_attempt
+        SET frame_ciphertext = NULL, frame_nonce = NULL, frame_purged_at = $1
+        WHERE frame_ciphertext IS NOT NULL AND attempted_at < $2
+        "#,
+    )
+    .bind(now)
+    .bind(now - retention)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+pub fn spawn_retention(pool: PgPool) {
+    tokio::spawn(async move {
+        let mut interval =
+            tokio::time::interval(std::time::Duration::from_secs(PURGE_INTERVAL_MINUTES * 60));
+        loop {
+            interval.tick().await;
+            match purge_expired_frames(&pool).await {
+                Ok(0) => {}
+                Ok(purged) => println!("{} quadros de verificação facial expurgados.", purged),
+                Err(e) => eprintln!("Falha ao expurgar quadros de verificação facial: {:?}", e),
+            }
+        }
+    });
+}
@@ -0,0 +1,170 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderValue, ACCEPT_LANGUAGE, CONTENT_LANGUAGE};
+use actix_web::middleware::Next;
+use actix_web::Error;
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum Locale {
+    #[default]
+    #[serde(rename = "pt-BR")]
+    PtBr,
+    #[serde(rename = "en")]
+    En,
+    #[serde(rename = "es")]
+    Es,
+}
+
+impl Locale {
+    pub fn tag(&self) -> &'static str {
+        match self {
+            Locale::PtBr => "pt-BR",
+            Locale::En => "en",
+            Locale::Es => "es",
+        }
+    }
+
+    /// Aceita tags como "pt", "pt-BR", "en-US" ou "es_AR"; só o idioma principal importa.
+    pub fn parse(tag: &str) -> Option<Self> {
+        let primary = tag.trim().split(['-', '_']).next()?.to_lowercase();
+        match primary.as_str() {
+            "pt" => Some(Locale::PtBr),
+            "en" => Some(Locale::En),
+            "es" => Some(Locale::Es),
+            _ => None,
+        }
+    }
+
+    /// Escolhe o idioma suportado de maior peso (`q`) no cabeçalho Accept-Language.
+    pub fn from_accept_language(header: &str) -> Option<Self> {
+        let mut candidates: Vec<(f32, Locale)> = header
+            .split(',')
+            .filter_map(|entry| {
+                let mut parts = entry.split(';');
+                let locale = Locale::parse(parts.next()?)?;
+                let quality = parts
+                    .find_map(|p| p.trim().strip_prefix("q="))
+                    .and_then(|q| q.parse().ok())
+                    .unwrap_or(1.0);
+                Some((quality, locale))
+            })
+            .collect();
+        candidates.sort_by(|a, b| b.0.total_cmp(&a.0));
+        candidates.first().map(|(_, locale)| *locale)
+    }
+}
+
+static CATALOGS: LazyLock<HashMap<Locale, HashMap<String, String>>> = LazyLock::new(|| {
+    [
+        (Locale::PtBr, include_str!("../../locales/pt-BR.json")),
+        (Locale::En, include_str!("../../locales/en.json")),
+        (Locale::Es, include_str!("../../locales/es.json")),
+    ]
+    .into_iter()
+    .map(|(locale, source)| {
+        let catalog = serde_json::from_str(source)
+            .unwrap_or_else(|e| panic!("Catálogo {} inválido: {}", locale.tag(), e));
+        (locale, catalog)
+    })
+    .collect()
+});
+
+tokio::task_local! {
+    static CURRENT: Cell<Locale>;
+}
+
+/// Idioma da requisição em andamento (pt-BR fora de uma requisição).
+pub fn current() -> Locale {
+    CURRENT.try_with(|locale| locale.get()).unwrap_or_default()
+}
+
+/// Troca o idioma da requisição em andamento, ex.: pela preferência do usuário autenticado.
+pub fn set_current(locale: Locale) {
+    let _ = CURRENT.try_with(|current| current.set(locale));
+}
+
+/// Idioma salvo no perfil, se válido; senão o da requisição em andamento.
+pub fn preferred_or_current(tag: Option<&str>) -> Locale {
+    tag.and_then(Locale::parse).unwrap_or_else(current)
+}
+
+/// Mensagem do catálogo no idioma pedido, caindo para pt-BR e, por fim, para a própria chave.
+pub fn translate(locale: Locale, key: &str) -> String {
+    CATALOGS
+        .get(&locale)
+        .and_then(|catalog| catalog.get(key))
+        .or_else(|| CATALOGS.get(&Locale::PtBr)?.get(key))
+        .cloned()
+        .unwrap_or_else(|| key.to_string())
+}
+
+/// Como `translate`, substituindo `{nome}` pelos valores informados.
+pub fn translate_with(locale: Locale, key: &str, vars: &[(&str, &str)]) -> String {
+    fill(&translate(locale, key), vars)
+}
+
+/// Uma passada só: um valor que contenha `{outro}` não é substituído de novo.
+fn fill(message: &str, vars: &[(&str, &str)]) -> String {
+    let mut output = String::with_capacity(message.len());
+    let mut rest = message;
+    while let Some(start) = rest.find('{') {
+        let after = &rest[start + 1..];
+        let Some(end) = after.find('}') else {
+            break;
+        };
+        output.push_str(&rest[..start]);
+        let name = &after[..end];
+        match vars.iter().find(|(var, _)| *var == name) {
+            Some((_, value)) => output.push_str(value),
+            None => output.push_str(&rest[start..start + 1 + end + 1]),
+        }
+        rest = &after[end + 1..];
+    }
+    output.push_str(rest);
+    output
+}
+
+pub fn t(key: &str) -> String {
+    translate(current(), key)
+}
+
+pub fn t_with(key: &str, vars: &[(&str, &str)]) -> String {
+    translate_with(current(), key, vars)
+}
+
+pub async fn negotiate_locale(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let locale = req
+        .headers()
+        .get(ACCEPT_LANGUAGE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(Locale::from_accept_language)
+        .unwrap_or_default();
+
+    CURRENT
+        .scope(Cell::new(locale), async move {
+            let mut res = next.call(req).await?;
+            res.headers_mut()
+                .insert(CONTENT_LANGUAGE, HeaderValue::from_static(current().tag()));
+            Ok(res)
+        })
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fill_does_not_expand_values() {
+        let vars = [("error", "{id}"), ("id", "42")];
+        assert_eq!(fill("{error} ({id})", &vars), "{id} (42)");
+        assert_eq!(fill("{missing} {id", &vars), "{missing} {id");
+    }
+}
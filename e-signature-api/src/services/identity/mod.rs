@@ -0,0 +1,363 @@
+use crate::services::documents::models::Signer;
+use crate::services::face::{self, FaceError};
+use crate::services::tax_ids;
+use crate::services::users::services as user_service;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::json;
+use sqlx::PgPool;
+
+mod ocr;
+
+pub use ocr::initialize;
+use ocr::TextLine;
+
+/// Fração mínima dos nomes do cadastro encontrados na linha lida.
+const NAME_MATCH_THRESHOLD: f32 = 0.8;
+/// Similaridade (Levenshtein normalizada) para aceitar uma palavra lida como um nome.
+const TOKEN_SIMILARITY: f64 = 0.8;
+/// Preposições que o OCR costuma perder e que não distinguem um nome de outro.
+const NAME_PARTICLES: [&str; 6] = ["DA", "DAS", "DE", "DO", "DOS", "E"];
+
+#[derive(Debug)]
+pub enum IdentityError {
+    NotLoaded,
+    NoDocumentImage,
+    InvalidImage(String),
+    Inference(String),
+    Database(sqlx::Error),
+}
+
+impl From<sqlx::Error> for IdentityError {
+    fn from(e: sqlx::Error) -> Self {
+        IdentityError::Database(e)
+    }
+}
+
+impl From<ort::Error> for IdentityError {
+    fn from(e: ort::Error) -> Self {
+        IdentityError::Inference(e.to_string())
+    }
+}
+
+impl std::fmt::Display for IdentityError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IdentityError::NotLoaded => write!(f, "modelos de OCR não carregados"),
+            IdentityError::NoDocumentImage => write!(f, "signatário sem foto do documento"),
+            IdentityError::InvalidImage(e) => write!(f, "imagem inválida: {}", e),
+            IdentityError::Inference(e) => write!(f, "falha no OCR: {}", e),
+            IdentityError::Database(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DocumentType {
+    Rg,
+    Cnh,
+    Unknown,
+}
+
+impl DocumentType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DocumentType::Rg => "rg",
+            DocumentType::Cnh => "cnh",
+            DocumentType::Unknown => "unknown",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IdentityStatus {
+    Verified,
+    Mismatch,
+    Unreadable,
+}
+
+impl IdentityStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IdentityStatus::Verified => "verified",
+            IdentityStatus::Mismatch => "mismatch",
+            IdentityStatus::Unreadable => "unreadable",
+        }
+    }
+}
+
+/// Um campo lido do documento: o texto extraído, se bate com o cadastro e a confiança
+/// (0..1) da leitura combinada com a da comparação.
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldCheck {
+    pub extracted: Option<String>,
+    pub matched: bool,
+    pub confidence: f32,
+}
+
+impl FieldCheck {
+    fn missing() -> Self {
+        FieldCheck {
+            extracted: None,
+            matched: false,
+            confidence: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PortraitCheck {
+    pub found: bool,
+    pub enrolled: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IdentityCheck {
+    pub check_id: i64,
+    pub signer_id: i64,
+    pub document_type: DocumentType,
+    pub status: IdentityStatus,
+    pub cpf: FieldCheck,
+    pub name: FieldCheck,
+    pub portrait: PortraitCheck,
+    pub checked_at: DateTime<Utc>,
+}
+
+/// Maiúsculas sem acento, só letras e espaços: "João  D'Ávila" vira "JOAO D AVILA".
+fn fold(text: &str) -> String {
+    let folded: String = text
+        .chars()
+        .flat_map(char::to_uppercase)
+        .map(|c| match c {
+            'Á' | 'À' | 'Â' | 'Ã' | 'Ä' => 'A',
+            'É' | 'È' | 'Ê' | 'Ë' => 'E',
+            'Í' | 'Ì' | 'Î' | 'Ï' => 'I',
+            'Ó' | 'Ò' | 'Ô' | 'Õ' | 'Ö' => 'O',
+            'Ú' | 'Ù' | 'Û' | 'Ü' => 'U',
+            'Ç' => 'C',
+            'Ñ' => 'N',
+            c if c.is_ascii_uppercase() => c,
+            _ => ' ',
+        })
+        .collect();
+    folded.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn detect_document_type(lines: &[TextLine]) -> DocumentType {
+    let text = lines
+        .iter()
+        .map(|line| fold(&line.text))
+        .collect::<Vec<_>>()
+        .join(" ");
+    if text.contains("HABILITACAO") || text.contains("PERMISSAO PARA DIRIGIR") {
+        DocumentType::Cnh
+    } else if text.contains("IDENTIDADE") || text.contains("REGISTRO GERAL") {
+        DocumentType::Rg
+    } else {
+        DocumentType::Unknown
+    }
+}
+
+/// CPFs válidos da linha, escritos com ou sem pontuação (`123.456.789-09`).
+fn cpf_candidates(text: &str) -> Vec<String> {
+    text.split(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-'))
+        .filter_map(tax_ids::normalize_cpf)
+        .collect()
+}
+
+/// Prefere o CPF igual ao do cadastro; senão, o primeiro CPF válido lido.
+fn extract_cpf(lines: &[TextLine], national_id: &str) -> FieldCheck {
+    let expected = tax_ids::normalize_cpf(national_id);
+    let candidates: Vec<(String, f32)> = lines
+        .iter()
+        .flat_map(|line| {
+            cpf_candidates(&line.text)
+                .into_iter()
+                .map(|cpf| (cpf, line.confidence))
+        })
+        .collect();
+
+    match candidates
+        .iter()
+        .find(|(cpf, _)| Some(cpf) == expected.as_ref())
+        .or(candidates.first())
+    {
+        Some((cpf, confidence)) => FieldCheck {
+            extracted: Some(tax_ids::format_cpf(cpf)),
+            matched: Some(cpf) == expected.as_ref(),
+            confidence: *confidence,
+        },
+        None => FieldCheck::missing(),
+    }
+}
+
+/// Fração dos nomes do cadastro que aparecem na linha, tolerando erros pequenos de leitura.
+fn name_score(expected: &[String], text: &str) -> f32 {
+    let folded = fold(text);
+    let tokens: Vec<&str> = folded.split_whitespace().collect();
+    let found = expected
+        .iter()
+        .filter(|name| {
+            tokens
+                .iter()
+                .any(|token| strsim::normalized_levenshtein(name, token) >= TOKEN_SIMILARITY)
+        })
+        .count();
+    found as f32 / expected.len().max(1) as f32
+}
+
+/// A linha que mais se parece com o nome do cadastro é tomada como o nome do documento.
+fn extract_name(lines: &[TextLine], full_name: &str) -> FieldCheck {
+    let expected: Vec<String> = fold(full_name)
+        .split_whitespace()
+        .filter(|name| !NAME_PARTICLES.contains(name))
+        .map(str::to_string)
+        .collect();
+
+    let best = lines
+        .iter()
+        .map(|line| (line, name_score(&expected, &line.text)))
+        .filter(|(_, score)| *score > 0.0)
+        .max_by(|(a, a_score), (b, b_score)| {
+            a_score
+                .total_cmp(b_score)
+                .then(a.confidence.total_cmp(&b.confidence))
+        });
+
+    match best {
+        Some((line, score)) => FieldCheck {
+            extracted: Some(line.text.clone()),
+            matched: score >= NAME_MATCH_THRESHOLD,
+            confidence: score * line.confidence,
+        },
+        None => FieldCheck::missing(),
+    }
+}
+
+async fn read_document(image: Vec<u8>) -> Result<Vec<TextLine>, IdentityError> {
+    if !ocr::is_loaded() {
+        return Err(IdentityError::NotLoaded);
+    }
+    tokio::task::spawn_blocking(move || {
+        let image = image::load_from_memory(&image)
+            .map_err(|e| IdentityError::InvalidImage(e.to_string()))?
+            .to_rgba32f();
+        Ok(ocr::read_text(&image)?)
+    })
+    .await
+    .map_err(|e| IdentityError::Inference(e.to_string()))?
+}
+
+/// Recorta o retrato e, com `enroll` e o documento conferido, cadastra o rosto do usuário
+/// do signatário a partir dele, sem substituir um cadastro existente. Falhas aqui não
+/// invalidam a conferência dos campos.
+async fn enroll_portrait(
+    pool: &PgPool,
+    signer: &Signer,
+    image: Vec<u8>,
+    status: IdentityStatus,
+    enroll: bool,
+) -> PortraitCheck {
+    let portrait = match face::crop_portrait(image).await {
+        Ok(Some(portrait)) => portrait,
+        Ok(None) => {
+            return PortraitCheck {
+                found: false,
+                enrolled: false,
+                error: None,
+            }
+        }
+        Err(e) => {
+            return PortraitCheck {
+                found: false,
+                enrolled: false,
+                error: Some(e.to_string()),
+            }
+        }
+    };
+
+    let enrollment = match (status, signer.user_id) {
+        (IdentityStatus::Verified, Some(user_id)) if enroll => {
+            user_service::enroll_face_image(pool, user_id, portrait, false).await
+        }
+        _ => Ok(None),
+    };
+    match enrollment {
+        Ok(enrollment) => PortraitCheck {
+            found: true,
+            enrolled: enrollment.is_some(),
+            error: None,
+        },
+        Err(e) => PortraitCheck {
+            found: true,
+            enrolled: false,
+            error: Some(e.to_string()),
+        },
+    }
+}
+
+/// Lê o RG/CNH enviado (ou, sem imagem, a foto do documento já cadastrada), compara CPF
+/// e nome com o cadastro do signatário e grava o resultado. O rosto só é cadastrado a
+/// partir da foto já cadastrada e com `may_enroll`; uma imagem enviada na requisição
+/// nunca vira referência facial.
+pub async fn check_signer_document(
+    pool: &PgPool,
+    signer: &Signer,
+    image_base64: Option<&str>,
+    may_enroll: bool,
+) -> Result<IdentityCheck, IdentityError> {
+    let image = match (image_base64, &signer.photo_id_url) {
+        (Some(image_base64), _) => {
+            face::decode_base64_image(image_base64).map_err(|e| match e {
+                FaceError::InvalidImage(e) => IdentityError::InvalidImage(e),
+                e => IdentityError::Inference(e.to_string()),
+            })?
+        }
+        (None, Some(photo_id_url)) => tokio::fs::read(photo_id_url)
+            .await
+            .map_err(|e| IdentityError::InvalidImage(format!("foto do documento: {}", e)))?,
+        (None, None) => return Err(IdentityError::NoDocumentImage),
+    };
+
+    let lines = read_document(image.clone()).await?;
+    let document_type = detect_document_type(&lines);
+    let cpf = extract_cpf(&lines, &signer.national_id);
+    let name = extract_name(&lines, &signer.full_name);
+    let status = match (cpf.matched, name.matched) {
+        (true, true) => IdentityStatus::Verified,
+        _ if cpf.extracted.is_none() => IdentityStatus::Unreadable,
+        _ => IdentityStatus::Mismatch,
+    };
+    let enroll = may_enroll && image_base64.is_none();
+    let portrait = enroll_portrait(pool, signer, image, status, enroll).await;
+
+    let (check_id, checked_at): (i64, DateTime<Utc>) = sqlx::query_as(
+        r#"
+        INSERT INTO identity_check (signer_id, document_type, status, details, checked_at)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING check_id, checked_at
+        "#,
+    )
+    .bind(signer.signer_id)
+    .bind(document_type.as_str())
+    .bind(status.as_str())
+    .bind(json!({ "cpf": cpf, "name": name, "portrait": portrait }))
+    .bind(Utc::now())
+    .fetch_one(pool)
+    .await?;
+
+    Ok(IdentityCheck {
+        check_id,
+        signer_id: signer.signer_id,
+        document_type,
+        status,
+        cpf,
+        name,
+        portrait,
+        checked_at,
+    })
+}
@@ -0,0 +1,253 @@
+use crate::services::face;
+use image::imageops::{self, FilterType};
+use image::Rgba32FImage;
+use ndarray::Array4;
+use ort::inputs;
+use ort::session::Session;
+use ort::value::Value;
+use std::collections::VecDeque;
+use std::env;
+use std::sync::{Mutex, OnceLock};
+
+const DEFAULT_DETECTOR_MODEL: &str = "models/ocr/det.onnx";
+const DEFAULT_RECOGNIZER_MODEL: &str = "models/ocr/rec.onnx";
+const DEFAULT_CHARSET: &str = "models/ocr/dict.txt";
+/// Maior lado da imagem na detecção; o DBNet exige dimensões múltiplas de 32.
+const DETECTOR_MAX_SIDE: u32 = 960;
+const RECOGNIZER_HEIGHT: u32 = 48;
+const RECOGNIZER_MAX_WIDTH: u32 = 1280;
+const BINARY_THRESHOLD: f32 = 0.3;
+const BOX_THRESHOLD: f32 = 0.6;
+const UNCLIP_RATIO: f32 = 1.5;
+const MEAN: [f32; 3] = [0.485, 0.456, 0.406];
+const STD: [f32; 3] = [0.229, 0.224, 0.225];
+
+#[derive(Debug, Clone)]
+pub struct TextLine {
+    pub text: String,
+    pub confidence: f32,
+}
+
+/// Modelos PP-OCR (detecção DBNet + reconhecimento CRNN/CTC) exportados para ONNX e o
+/// dicionário de caracteres do reconhecedor.
+struct OcrEngine {
+    detector: Mutex<Session>,
+    recognizer: Mutex<Session>,
+    charset: Vec<String>,
+}
+
+static ENGINE: OnceLock<OcrEngine> = OnceLock::new();
+
+/// Carrega os modelos uma única vez, na subida do servidor. Só usa CPU.
+pub fn initialize() -> Result<(), ort::Error> {
+    let detector_path =
+        env::var("OCR_DETECTOR_MODEL").unwrap_or_else(|_| DEFAULT_DETECTOR_MODEL.to_string());
+    let recognizer_path =
+        env::var("OCR_RECOGNIZER_MODEL").unwrap_or_else(|_| DEFAULT_RECOGNIZER_MODEL.to_string());
+    let charset_path = env::var("OCR_CHARSET").unwrap_or_else(|_| DEFAULT_CHARSET.to_string());
+
+    // Índice 0 é o "branco" do CTC; o espaço vem depois do dicionário.
+    let mut charset: Vec<String> = std::fs::read_to_string(&charset_path)
+        .map_err(ort::Error::wrap)?
+        .lines()
+        .map(|line| line.to_string())
+        .collect();
+    charset.push(" ".to_string());
+
+    let engine = OcrEngine {
+        detector: Mutex::new(face::load_session(&detector_path)?),
+        recognizer: Mutex::new(face::load_session(&recognizer_path)?),
+        charset,
+    };
+    let _ = ENGINE.set(engine);
+    Ok(())
+}
+
+pub(super) fn is_loaded() -> bool {
+    ENGINE.get().is_some()
+}
+
+fn normalized_tensor(image: &Rgba32FImage) -> Array4<f32> {
+    let (width, height) = image.dimensions();
+    Array4::from_shape_fn(
+        (1, 3, height as usize, width as usize),
+        |(_, channel, y, x)| {
+            (image.get_pixel(x as u32, y as u32)[channel] - MEAN[channel]) / STD[channel]
+        },
+    )
+}
+
+fn centered_tensor(image: &Rgba32FImage) -> Array4<f32> {
+    let (width, height) = image.dimensions();
+    Array4::from_shape_fn(
+        (1, 3, height as usize, width as usize),
+        |(_, channel, y, x)| (image.get_pixel(x as u32, y as u32)[channel] - 0.5) / 0.5,
+    )
+}
+
+fn round_to_32(value: f32) -> u32 {
+    (((value / 32.0).round() as u32) * 32).max(32)
+}
+
+/// Caixa (x0, y0, x1, y1) em coordenadas da imagem original.
+type TextBox = (u32, u32, u32, u32);
+
+/// Componentes conexos do mapa de probabilidade binarizado, cada um virando uma caixa
+/// alinhada aos eixos e expandida ("unclip"), como no pós-processamento do DBNet.
+fn boxes_from_map(map: &[f32], width: usize, height: usize) -> Vec<(f32, f32, f32, f32)> {
+    let mut visited = vec![false; map.len()];
+    let mut boxes = Vec::new();
+
+    for start in 0..map.len() {
+        if visited[start] || map[start] < BINARY_THRESHOLD {
+            continue;
+        }
+        visited[start] = true;
+        let mut queue = VecDeque::from([start]);
+        let (mut min_x, mut min_y, mut max_x, mut max_y) = (width, height, 0, 0);
+        let (mut score, mut pixels) = (0f32, 0usize);
+
+        while let Some(index) = queue.pop_front() {
+            let (x, y) = (index % width, index / width);
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
+            score += map[index];
+            pixels += 1;
+
+            let neighbors = [
+                (x > 0).then(|| index - 1),
+                (x + 1 < width).then(|| index + 1),
+                (y > 0).then(|| index - width),
+                (y + 1 < height).then(|| index + width),
+            ];
+            for neighbor in neighbors.into_iter().flatten() {
+                if !visited[neighbor] && map[neighbor] >= BINARY_THRESHOLD {
+                    visited[neighbor] = true;
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+
+        let (box_width, box_height) = ((max_x - min_x + 1) as f32, (max_y - min_y + 1) as f32);
+        if box_width.min(box_height) < 3.0 || score / (pixels as f32) < BOX_THRESHOLD {
+            continue;
+        }
+        let distance = box_width * box_height * UNCLIP_RATIO / (2.0 * (box_width + box_height));
+        boxes.push((
+            min_x as f32 - distance,
+            min_y as f32 - distance,
+            max_x as f32 + 1.0 + distance,
+            max_y as f32 + 1.0 + distance,
+        ));
+    }
+    boxes
+}
+
+impl OcrEngine {
+    fn detect(&self, image: &Rgba32FImage) -> Result<Vec<TextBox>, ort::Error> {
+        let (width, height) = image.dimensions();
+        let scale = (DETECTOR_MAX_SIDE as f32 / width.max(height) as f32).min(1.0);
+        let (resized_width, resized_height) = (
+            round_to_32(width as f32 * scale),
+            round_to_32(height as f32 * scale),
+        );
+        let resized = imageops::resize(image, resized_width, resized_height, FilterType::Triangle);
+
+        let input = Value::from_array(normalized_tensor(&resized))?;
+        let candidates = {
+            let mut detector = self.detector.lock().unwrap_or_else(|e| e.into_inner());
+            let outputs = detector.run(inputs![input])?;
+            let (shape, map) = outputs[0].try_extract_tensor::<f32>()?;
+            let (map_height, map_width) = (shape[2] as usize, shape[3] as usize);
+            boxes_from_map(map, map_width, map_height)
+        };
+
+        let (scale_x, scale_y) = (
+            width as f32 / resized_width as f32,
+            height as f32 / resized_height as f32,
+        );
+        let mut boxes: Vec<TextBox> = candidates
+            .into_iter()
+            .map(|(x0, y0, x1, y1)| {
+                (
+                    (x0 * scale_x).clamp(0.0, width as f32 - 1.0) as u32,
+                    (y0 * scale_y).clamp(0.0, height as f32 - 1.0) as u32,
+                    (x1 * scale_x).clamp(1.0, width as f32) as u32,
+                    (y1 * scale_y).clamp(1.0, height as f32) as u32,
+                )
+            })
+            .filter(|(x0, y0, x1, y1)| x1 > x0 && y1 > y0)
+            .collect();
+        // Ordem de leitura: de cima para baixo e, na mesma linha, da esquerda para a direita.
+        boxes.sort_by_key(|(x0, y0, _, y1)| ((y0 + y1) / 2 / 10, *x0));
+        Ok(boxes)
+    }
+
+    fn recognize_box(
+        &self,
+        image: &Rgba32FImage,
+        text_box: TextBox,
+    ) -> Result<TextLine, ort::Error> {
+        let (x0, y0, x1, y1) = text_box;
+        let crop = imageops::crop_imm(image, x0, y0, x1 - x0, y1 - y0).to_image();
+        let width = ((RECOGNIZER_HEIGHT as f32 * (x1 - x0) as f32 / (y1 - y0) as f32).ceil()
+            as u32)
+            .clamp(RECOGNIZER_HEIGHT / 3, RECOGNIZER_MAX_WIDTH);
+        let resized = imageops::resize(&crop, width, RECOGNIZER_HEIGHT, FilterType::Triangle);
+
+        let input = Value::from_array(centered_tensor(&resized))?;
+        let mut recognizer = self.recognizer.lock().unwrap_or_else(|e| e.into_inner());
+        let outputs = recognizer.run(inputs![input])?;
+        let (shape, probabilities) = outputs[0].try_extract_tensor::<f32>()?;
+        let (steps, classes) = (shape[1] as usize, shape[2] as usize);
+
+        // Decodificação gulosa do CTC: junta repetições e descarta o branco.
+        let (mut text, mut scores, mut previous) = (String::new(), Vec::new(), 0usize);
+        for step in probabilities.chunks_exact(classes).take(steps) {
+            let (class, probability) =
+                step.iter()
+                    .enumerate()
+                    .fold((0, f32::MIN), |best, (class, probability)| {
+                        if *probability > best.1 {
+                            (class, *probability)
+                        } else {
+                            best
+                        }
+                    });
+            if class != 0 && class != previous {
+                if let Some(character) = self.charset.get(class - 1) {
+                    text.push_str(character);
+                    scores.push(probability);
+                }
+            }
+            previous = class;
+        }
+
+        let confidence = if scores.is_empty() {
+            0.0
+        } else {
+            scores.iter().sum::<f32>() / scores.len() as f32
+        };
+        Ok(TextLine {
+            text: text.trim().to_string(),
+            confidence,
+        })
+    }
+}
+
+/// Linhas de texto da imagem em ordem de leitura, com a confiança média de cada uma.
+pub(super) fn read_text(image: &Rgba32FImage) -> Result<Vec<TextLine>, ort::Error> {
+    let engine = ENGINE
+        .get()
+        .ok_or_else(|| ort::Error::new("modelos de OCR não carregados"))?;
+    let mut lines = Vec::new();
+    for text_box in engine.detect(image)? {
+        let line = engine.recognize_box(image, text_box)?;
+        if !line.text.is_empty() {
+            lines.push(line);
+        }
+    }
+    Ok(lines)
+}
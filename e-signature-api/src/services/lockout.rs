@@ -0,0 +1,70 @@
+use chrono::{DateTime, Duration, Utc};
+use sqlx::PgPool;
+
+const FREE_ATTEMPTS: i32 = 5;
+const MAX_LOCKOUT_MINUTES: i64 = 60;
+
+pub async fn locked_until(
+    pool: &PgPool,
+    user_id: i64,
+) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+    let record: Option<(Option<DateTime<Utc>>,)> =
+        sqlx::query_as("SELECT locked_until FROM user_account WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_optional(pool)
+            .await?;
+
+    Ok(record
+        .and_then(|(locked_until,)| locked_until)
+        .filter(|locked_until| *locked_until > Utc::now()))
+}
+
+/// Registra uma senha errada. A partir da quinta falha seguida a conta fica
+/// bloqueada por 1, 2, 4... minutos, até o máximo de uma hora.
+pub async fn record_failure(
+    pool: &PgPool,
+    user_id: i64,
+) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+    let (failed_attempts,): (i32,) = sqlx::query_as(
+        r#"
+        UPDATE user_account SET failed_login_attempts = failed_login_attempts + 1
+        WHERE user_id = $1
+        RETURNING failed_login_attempts
+        "#,
+    )
+    .bind(user_id)
+    .fetch_one(pool)
+    .await?;
+
+    if failed_attempts < FREE_ATTEMPTS {
+        return Ok(None);
+    }
+
+    let exponent = (failed_attempts - FREE_ATTEMPTS).min(6) as u32;
+    let minutes = 2_i64.pow(exponent).min(MAX_LOCKOUT_MINUTES);
+    let locked_until = Utc::now() + Duration::minutes(minutes);
+
+    sqlx::query("UPDATE user_account SET locked_until = $1 WHERE user_id = $2")
+        .bind(locked_until)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+
+    Ok(Some(locked_until))
+}
+
+pub async fn record_success(pool: &PgPool, user_id: i64) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE user_account SET failed_login_attempts = 0, locked_until = NULL WHERE user_id = $1",
+    )
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub fn retry_after(locked_until: DateTime<Utc>) -> std::time::Duration {
+    (locked_until - Utc::now())
+        .to_std()
+        .unwrap_or(std::time::Duration::from_secs(1))
+}
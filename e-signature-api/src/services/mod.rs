@@ -1,5 +1,30 @@
+pub mod activation;
+pub mod api_keys;
 pub mod auth;
+pub mod deliveries;
 pub mod documents;
+pub mod email;
+pub mod errors;
+pub mod face;
+pub mod face_attempts;
+pub mod i18n;
+pub mod identity;
+pub mod lockout;
+pub mod notifications;
+pub mod oidc;
+pub mod otp_codes;
+pub mod outbox;
+pub mod passwords;
+pub mod phones;
+pub mod policy;
+pub mod rate_limit;
+pub mod request_id;
+pub mod sessions;
+pub mod signing_keys;
+pub mod tax_ids;
 pub mod telegram;
+pub mod tenant;
+pub mod totp;
+pub mod twilio;
 pub mod users;
 pub mod whatsapp;
@@ -0,0 +1,415 @@
+use crate::services::deliveries::DeliveryStatus;
+use crate::services::email::email::{self, Attachment};
+use crate::services::email::templates::RenderedEmail;
+use crate::services::i18n::{self, Locale};
+use crate::services::phones;
+use crate::services::telegram::telegram;
+use crate::services::twilio;
+use crate::services::users as user_service;
+use crate::services::users::models::UserContact;
+use crate::services::whatsapp::whatsapp;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::env;
+use std::error::Error;
+use std::sync::LazyLock;
+
+pub type SendError = Box<dyn Error + Send + Sync>;
+
+const DEFAULT_CHANNEL_ORDER: &str = "whatsapp,telegram,sms,email";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChannelKind {
+    Whatsapp,
+    Sms,
+    Telegram,
+    Email,
+    Log,
+}
+
+impl ChannelKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChannelKind::Whatsapp => "whatsapp",
+            ChannelKind::Sms => "sms",
+            ChannelKind::Telegram => "telegram",
+            ChannelKind::Email => "email",
+            ChannelKind::Log => "log",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "whatsapp" => Some(ChannelKind::Whatsapp),
+            "sms" => Some(ChannelKind::Sms),
+            "telegram" => Some(ChannelKind::Telegram),
+            "email" | "e-mail" => Some(ChannelKind::Email),
+            "log" => Some(ChannelKind::Log),
+            _ => None,
+        }
+    }
+
+    /// Status logo após o provedor aceitar a mensagem. Os canais da Twilio ainda
+    /// avisam a entrega pelo webhook; nos demais, aceitar já é o fim da linha.
+    pub fn accepted_status(&self) -> DeliveryStatus {
+        match self {
+            ChannelKind::Whatsapp | ChannelKind::Sms => DeliveryStatus::Queued,
+            _ => DeliveryStatus::Sent,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Notification {
+    pub subject: String,
+    pub body: String,
+    pub html: Option<String>,
+    pub attachments: Vec<Attachment>,
+}
+
+impl Notification {
+    pub fn new(subject: impl Into<String>, body: impl Into<String>) -> Self {
+        Notification {
+            subject: subject.into(),
+            body: body.into(),
+            html: None,
+            attachments: Vec::new(),
+        }
+    }
+
+    pub fn with_attachment(mut self, attachment: Attachment) -> Self {
+        self.attachments.push(attachment);
+        self
+    }
+}
+
+impl From<RenderedEmail> for Notification {
+    fn from(email: RenderedEmail) -> Self {
+        Notification {
+            subject: email.subject,
+            body: email.text,
+            html: Some(email.html),
+            attachments: Vec::new(),
+        }
+    }
+}
+
+/// Destinatário de uma notificação. Nem todo destinatário tem conta (ex.: signatário
+/// recebendo OTP), por isso `user_id` é opcional.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Recipient {
+    pub user_id: Option<i64>,
+    pub email: Option<String>,
+    pub phone_number: Option<String>,
+    pub telegram_chat_id: Option<i64>,
+    pub preferred_channels: Vec<ChannelKind>,
+    pub locale: Option<Locale>,
+}
+
+impl Recipient {
+    /// Idioma das mensagens: o do perfil, senão o da requisição em andamento.
+    pub fn locale(&self) -> Locale {
+        self.locale.unwrap_or_else(i18n::current)
+    }
+}
+
+impl From<UserContact> for Recipient {
+    fn from(contact: UserContact) -> Self {
+        Recipient {
+            user_id: Some(contact.user_id),
+            email: Some(contact.email),
+            phone_number: contact.phone_number,
+            telegram_chat_id: contact.telegram_chat_id,
+            preferred_channels: contact
+                .notification_channels
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|c| ChannelKind::parse(c))
+                .collect(),
+            locale: contact.locale.as_deref().and_then(Locale::parse),
+        }
+    }
+}
+
+#[async_trait]
+pub trait NotificationChannel: Send + Sync {
+    fn kind(&self) -> ChannelKind;
+
+    /// Endereço do destinatário neste canal, se ele tiver um.
+    fn address(&self, recipient: &Recipient) -> Option<String>;
+
+    /// Envia e devolve o identificador da mensagem no provedor.
+    async fn send(&self, address: &str, notification: &Notification) -> Result<String, SendError>;
+}
+
+pub struct WhatsappChannel;
+pub struct SmsChannel;
+pub struct TelegramChannel;
+pub struct EmailChannel;
+pub struct LogChannel;
+
+#[async_trait]
+impl NotificationChannel for WhatsappChannel {
+    fn kind(&self) -> ChannelKind {
+        ChannelKind::Whatsapp
+    }
+
+    /// Só celulares: para um fixo o envio é pulado e vai para o próximo canal.
+    fn address(&self, recipient: &Recipient) -> Option<String> {
+        recipient
+            .phone_number
+            .as_deref()
+            .and_then(phones::parse)
+            .filter(|phone| phone.whatsapp_eligible())
+            .map(|phone| phone.e164)
+    }
+
+    async fn send(&self, address: &str, notification: &Notification) -> Result<String, SendError> {
+        whatsapp::send_whatsapp_message(address, &notification.body).await
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for SmsChannel {
+    fn kind(&self) -> ChannelKind {
+        ChannelKind::Sms
+    }
+
+    fn address(&self, recipient: &Recipient) -> Option<String> {
+        recipient
+            .phone_number
+            .as_deref()
+            .and_then(phones::normalize)
+    }
+
+    async fn send(&self, address: &str, notification: &Notification) -> Result<String, SendError> {
+        twilio::send_sms_message(address, &notification.body).await
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for TelegramChannel {
+    fn kind(&self) -> ChannelKind {
+        ChannelKind::Telegram
+    }
+
+    fn address(&self, recipient: &Recipient) -> Option<String> {
+        recipient
+            .telegram_chat_id
+            .map(|chat_id| chat_id.to_string())
+    }
+
+    async fn send(&self, address: &str, notification: &Notification) -> Result<String, SendError> {
+        let chat_id: i64 = address.parse()?;
+        telegram::send_telegram_message(chat_id, &notification.body).await
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for EmailChannel {
+    fn kind(&self) -> ChannelKind {
+        ChannelKind::Email
+    }
+
+    fn address(&self, recipient: &Recipient) -> Option<String> {
+        recipient.email.clone()
+    }
+
+    async fn send(&self, address: &str, notification: &Notification) -> Result<String, SendError> {
+        email::send_email(
+            address,
+            &notification.subject,
+            &notification.body,
+            notification.html.as_deref(),
+            &notification.attachments,
+        )
+        .await
+    }
+}
+
+/// Canal para desenvolvimento local e testes: só escreve a mensagem no log.
+#[async_trait]
+impl NotificationChannel for LogChannel {
+    fn kind(&self) -> ChannelKind {
+        ChannelKind::Log
+    }
+
+    fn address(&self, recipient: &Recipient) -> Option<String> {
+        recipient
+            .email
+            .clone()
+            .or_else(|| recipient.phone_number.clone())
+            .or_else(|| recipient.user_id.map(|id| format!("user:{}", id)))
+    }
+
+    async fn send(&self, address: &str, notification: &Notification) -> Result<String, SendError> {
+        println!(
+            "[notificação] para {} | {} | {}",
+            address, notification.subject, notification.body
+        );
+        Ok(format!("log-{}", uuid::Uuid::new_v4()))
+    }
+}
+
+fn channel(kind: ChannelKind) -> Box<dyn NotificationChannel> {
+    match kind {
+        ChannelKind::Whatsapp => Box::new(WhatsappChannel),
+        ChannelKind::Sms => Box::new(SmsChannel),
+        ChannelKind::Telegram => Box::new(TelegramChannel),
+        ChannelKind::Email => Box::new(EmailChannel),
+        ChannelKind::Log => Box::new(LogChannel),
+    }
+}
+
+/// Canais habilitados no servidor, na ordem padrão de tentativa. Definido por
+/// NOTIFICATION_CHANNELS (ex.: "log" em desenvolvimento).
+static CHANNELS: LazyLock<Vec<Box<dyn NotificationChannel>>> = LazyLock::new(|| {
+    env::var("NOTIFICATION_CHANNELS")
+        .unwrap_or_else(|_| DEFAULT_CHANNEL_ORDER.to_string())
+        .split(',')
+        .filter_map(ChannelKind::parse)
+        .map(channel)
+        .collect()
+});
+
+/// Ordem de tentativa para o destinatário: primeiro os canais preferidos dele,
+/// depois os demais habilitados, na ordem do servidor.
+fn channel_order(recipient: &Recipient) -> Vec<&'static dyn NotificationChannel> {
+    let enabled: &'static [Box<dyn NotificationChannel>] = &CHANNELS;
+    let mut order: Vec<&'static dyn NotificationChannel> = Vec::new();
+
+    for preferred in &recipient.preferred_channels {
+        if let Some(channel) = enabled.iter().find(|c| c.kind() == *preferred) {
+            order.push(channel.as_ref());
+        }
+    }
+    for channel in enabled {
+        if !order.iter().any(|c| c.kind() == channel.kind()) {
+            order.push(channel.as_ref());
+        }
+    }
+    order
+}
+
+/// Resultado de um envio aceito pelo provedor.
+#[derive(Debug, Clone)]
+pub struct Dispatched {
+    pub channel: ChannelKind,
+    pub address: String,
+    pub provider_id: String,
+}
+
+/// Tenta os canais em ordem até um entregar.
+pub async fn deliver(
+    recipient: &Recipient,
+    notification: &Notification,
+) -> Result<Dispatched, SendError> {
+    for channel in channel_order(recipient) {
+        let address = match channel.address(recipient) {
+            Some(address) => address,
+            None => continue,
+        };
+
+        match channel.send(&address, notification).await {
+            Ok(provider_id) => {
+                return Ok(Dispatched {
+                    channel: channel.kind(),
+                    address,
+                    provider_id,
+                })
+            }
+            Err(e) => eprintln!(
+                "Falha ao enviar mensagem por {}: {:?}",
+                channel.kind().as_str(),
+                e
+            ),
+        }
+    }
+
+    Err("Nenhum canal disponível entregou a mensagem".into())
+}
+
+/// Monta o destinatário a partir do e-mail, só com contatos já gravados: a conta,
+/// senão o signatário com esse e-mail, senão o chat do Telegram vinculado a ele.
+/// `None` quando o e-mail não tem contato nenhum.
+pub async fn recipient_for_email(
+    pool: &PgPool,
+    email: &str,
+) -> Result<Option<Recipient>, sqlx::Error> {
+    if let Some(contact) = user_service::get_user_contact(pool, email).await? {
+        return Ok(Some(Recipient::from(contact)));
+    }
+
+    let phone_number: Option<(String,)> = sqlx::query_as(
+        r#"
+        SELECT phone_number FROM signer
+        WHERE contact_email = $1 AND deleted_at IS NULL
+        ORDER BY user_id NULLS LAST, signer_id
+        LIMIT 1
+        "#,
+    )
+    .bind(email)
+    .fetch_optional(pool)
+    .await?;
+    let telegram_chat_id = confirmed_telegram_chat(pool, email).await?;
+
+    if phone_number.is_none() && telegram_chat_id.is_none() {
+        return Ok(None);
+    }
+    Ok(Some(Recipient {
+        email: Some(email.to_string()),
+        phone_number: phone_number.map(|(phone_number,)| phone_number),
+        telegram_chat_id,
+        ..Recipient::default()
+    }))
+}
+
+/// Destinatário do OTP de assinatura: o signatário de `document_id` com esse e-mail,
+/// pelos contatos gravados no cadastro dele. `None` se o e-mail não assina o documento.
+pub async fn recipient_for_document_signer(
+    pool: &PgPool,
+    email: &str,
+    document_id: i64,
+) -> Result<Option<Recipient>, sqlx::Error> {
+    let signer: Option<(Option<i64>, String, String)> = sqlx::query_as(
+        r#"
+        SELECT s.user_id, s.contact_email, s.phone_number
+        FROM signer s
+        JOIN document_signer ds ON ds.signer_id = s.signer_id
+        WHERE ds.document_id = $1 AND lower(s.contact_email) = lower($2) AND s.deleted_at IS NULL
+        "#,
+    )
+    .bind(document_id)
+    .bind(email)
+    .fetch_optional(pool)
+    .await?;
+    let Some((user_id, contact_email, phone_number)) = signer else {
+        return Ok(None);
+    };
+
+    let telegram_chat_id = confirmed_telegram_chat(pool, &contact_email).await?;
+    Ok(Some(Recipient {
+        user_id,
+        email: Some(contact_email),
+        phone_number: Some(phone_number),
+        telegram_chat_id,
+        ..Recipient::default()
+    }))
+}
+
+async fn confirmed_telegram_chat(pool: &PgPool, email: &str) -> Result<Option<i64>, sqlx::Error> {
+    let chat_id: Option<(Option<i64>,)> = sqlx::query_as(
+        r#"
+        SELECT chat_id FROM telegram_links
+        WHERE email = $1 AND confirmed
+        ORDER BY confirmed_at DESC NULLS LAST
+        LIMIT 1
+        "#,
+    )
+    .bind(email)
+    .fetch_optional(pool)
+    .await?;
+    Ok(chat_id.and_then(|(chat_id,)| chat_id))
+}
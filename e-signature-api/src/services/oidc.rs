@@ -0,0 +1,696 @@
+use crate::services::errors::ServiceError;
+use crate::services::sessions::{self, hash_token, SessionTokens};
+use crate::services::users::models::Role;
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::jwk::{AlgorithmParameters, EllipticCurve, Jwk, JwkSet};
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::{FromRow, PgPool};
+use std::env;
+use std::str::FromStr;
+
+const LOGIN_STATE_TTL_MINUTES: i64 = 10;
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct OidcProvider {
+    pub company_id: i64,
+    pub issuer: String,
+    pub client_id: String,
+    #[serde(skip_serializing)]
+    pub client_secret: String,
+    pub auto_provision: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpsertOidcProvider {
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: String,
+    #[serde(default)]
+    pub auto_provision: bool,
+}
+
+#[derive(Debug)]
+pub enum OidcError {
+    NotConfigured,
+    InvalidState,
+    Provider(String),
+    UnverifiedEmail,
+    UnknownUser,
+    NotCompanyMember,
+    Database(sqlx::Error),
+    Service(ServiceError),
+}
+
+impl From<ServiceError> for OidcError {
+    fn from(e: ServiceError) -> Self {
+        match e {
+            ServiceError::Database(e) => OidcError::Database(e),
+            e => OidcError::Service(e),
+        }
+    }
+}
+
+impl From<sqlx::Error> for OidcError {
+    fn from(e: sqlx::Error) -> Self {
+        OidcError::Database(e)
+    }
+}
+
+impl From<reqwest::Error> for OidcError {
+    fn from(e: reqwest::Error) -> Self {
+        OidcError::Provider(e.to_string())
+    }
+}
+
+#[derive(Deserialize)]
+struct Discovery {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+#[derive(Deserialize)]
+struct IdTokenClaims {
+    email: Option<String>,
+    email_verified: Option<bool>,
+    nonce: Option<String>,
+}
+
+pub fn redirect_uri() -> String {
+    env::var("OIDC_REDIRECT_URI")
+        .unwrap_or_else(|_| "http://localhost:8080/api/auth/oidc/callback".to_string())
+}
+
+fn random_token() -> String {
+    let bytes: [u8; 32] = rand::random();
+    general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+}
+
+fn code_challenge(verifier: &str) -> String {
+    general_purpose::URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+async fn discover(issuer: &str) -> Result<Discovery, OidcError> {
+    let url = format!(
+        "{}/.well-known/openid-configuration",
+        issuer.trim_end_matches('/')
+    );
+    let discovery: Discovery = reqwest::get(url).await?.error_for_status()?.json().await?;
+
+    if discovery.issuer.trim_end_matches('/') != issuer.trim_end_matches('/') {
+        return Err(OidcError::Provider(format!(
+            "Issuer divergente na descoberta: {}",
+            discovery.issuer
+        )));
+    }
+    Ok(discovery)
+}
+
+pub async fn get_provider(
+    pool: &PgPool,
+    company_id: i64,
+) -> Result<Option<OidcProvider>, sqlx::Error> {
+    sqlx::query_as::<_, OidcProvider>(
+        r#"
+        SELECT company_id, issuer, client_id, client_secret, auto_provision, created_at, updated_at
+        FROM company_oidc_provider
+        WHERE company_id = $1
+        "#,
+    )
+    .bind(company_id)
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn upsert_provider(
+    pool: &PgPool,
+    company_id: i64,
+    data: &UpsertOidcProvider,
+) -> Result<OidcProvider, sqlx::Error> {
+    sqlx::query_as::<_, OidcProvider>(
+        r#"
+        INSERT INTO company_oidc_provider (company_id, issuer, client_id, client_secret, auto_provision)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (company_id) DO UPDATE
+        SET issuer = EXCLUDED.issuer,
+            client_id = EXCLUDED.client_id,
+            client_secret = EXCLUDED.client_secret,
+            auto_provision = EXCLUDED.auto_provision,
+            updated_at = CURRENT_TIMESTAMP
+        RETURNING company_id, issuer, client_id, client_secret, auto_provision, created_at, updated_at
+        "#,
+    )
+    .bind(company_id)
+    .bind(data.issuer.trim_end_matches('/'))
+    .bind(&data.client_id)
+    .bind(&data.client_secret)
+    .bind(data.auto_provision)
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn delete_provider(pool: &PgPool, company_id: i64) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM company_oidc_provider WHERE company_id = $1")
+        .bind(company_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+/// Monta a URL de autorização do provedor da empresa (authorization code + PKCE S256).
+pub async fn authorization_url(pool: &PgPool, company_id: i64) -> Result<String, OidcError> {
+    let provider = get_provider(pool, company_id)
+        .await?
+        .ok_or(OidcError::NotConfigured)?;
+    let discovery = discover(&provider.issuer).await?;
+
+    let state = random_token();
+    let nonce = random_token();
+    let code_verifier = random_token();
+
+    sqlx::query(
+        r#"
+        INSERT INTO oidc_login_state (state, company_id, code_verifier, nonce, expires_at)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+    )
+    .bind(hash_token(&state))
+    .bind(company_id)
+    .bind(&code_verifier)
+    .bind(&nonce)
+    .bind(Utc::now() + Duration::minutes(LOGIN_STATE_TTL_MINUTES))
+    .execute(pool)
+    .await?;
+
+    let mut url = reqwest::Url::parse(&discovery.authorization_endpoint)
+        .map_err(|e| OidcError::Provider(e.to_string()))?;
+    url.query_pairs_mut()
+        .append_pair("response_type", "code")
+        .append_pair("client_id", &provider.client_id)
+        .append_pair("redirect_uri", &redirect_uri())
+        .append_pair("scope", "openid email profile")
+        .append_pair("state", &state)
+        .append_pair("nonce", &nonce)
+        .append_pair("code_challenge", &code_challenge(&code_verifier))
+        .append_pair("code_challenge_method", "S256");
+
+    Ok(url.to_string())
+}
+
+/// Algoritmo de assinatura da chave: o `alg` do JWK ou, sem ele, o padrão do tipo da
+/// chave. Só algoritmos assimétricos; HMAC com segredo publicado no JWKS não vale nada.
+fn key_algorithm(jwk: &Jwk) -> Option<Algorithm> {
+    let algorithm = match (jwk.common.key_algorithm, &jwk.algorithm) {
+        (Some(key_algorithm), _) => Algorithm::from_str(&key_algorithm.to_string()).ok()?,
+        (None, AlgorithmParameters::RSA(_)) => Algorithm::RS256,
+        (None, AlgorithmParameters::EllipticCurve(params)) => match params.curve {
+            EllipticCurve::P256 => Algorithm::ES256,
+            EllipticCurve::P384 => Algorithm::ES384,
+            _ => return None,
+        },
+        (None, AlgorithmParameters::OctetKeyPair(_)) => Algorithm::EdDSA,
+        (None, AlgorithmParameters::OctetKey(_)) => return None,
+    };
+    match algorithm {
+        Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => None,
+        algorithm => Some(algorithm),
+    }
+}
+
+async fn validate_id_token(
+    discovery: &Discovery,
+    provider: &OidcProvider,
+    id_token: &str,
+) -> Result<IdTokenClaims, OidcError> {
+    let header = decode_header(id_token).map_err(|e| OidcError::Provider(e.to_string()))?;
+    let jwks: JwkSet = reqwest::get(&discovery.jwks_uri)
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    let jwk = match &header.kid {
+        Some(kid) => jwks.find(kid),
+        None if jwks.keys.len() == 1 => jwks.keys.first(),
+        None => None,
+    }
+    .ok_or_else(|| OidcError::Provider("Chave do ID token não encontrada no JWKS".into()))?;
+    // O algoritmo vem da chave; o do cabeçalho, que quem monta o token escolhe, só
+    // precisa concordar com ele.
+    let algorithm = key_algorithm(jwk)
+        .ok_or_else(|| OidcError::Provider("Algoritmo da chave do JWKS não aceito".into()))?;
+    if header.alg != algorithm {
+        return Err(OidcError::Provider(format!(
+            "Algoritmo do ID token ({:?}) não confere com a chave ({:?})",
+            header.alg, algorithm
+        )));
+    }
+    let decoding_key =
+        DecodingKey::from_jwk(jwk).map_err(|e| OidcError::Provider(e.to_string()))?;
+
+    let mut validation = Validation::new(algorithm);
+    validation.set_issuer(&[&provider.issuer]);
+    validation.set_audience(&[&provider.client_id]);
+
+    decode::<IdTokenClaims>(id_token, &decoding_key, &validation)
+        .map(|data| data.claims)
+        .map_err(|e| OidcError::Provider(e.to_string()))
+}
+
+async fn find_or_provision_user(
+    pool: &PgPool,
+    provider: &OidcProvider,
+    email: &str,
+) -> Result<i64, OidcError> {
+    let existing: Option<(i64, bool)> = sqlx::query_as(
+        r#"
+        SELECT u.user_id,
+               (COALESCE(u.company_id = $2, FALSE) OR EXISTS (
+                   SELECT 1 FROM company c WHERE c.company_id = $2 AND c.user_id = u.user_id
+               )) AS is_member
+        FROM user_account u
+        WHERE lower(u.email) = lower($1) AND u.deleted_at IS NULL
+        "#,
+    )
+    .bind(email)
+    .bind(provider.company_id)
+    .fetch_optional(pool)
+    .await?;
+
+    match existing {
+        Some((user_id, true)) => {
+            // O provedor já confirmou o e-mail; a conta não precisa de ativação por código.
+            sqlx::query("UPDATE user_account SET is_active = TRUE WHERE user_id = $1")
+                .bind(user_id)
+                .execute(pool)
+                .await?;
+            Ok(user_id)
+        }
+        Some((_, false)) => Err(OidcError::NotCompanyMember),
+        None if provider.auto_provision => {
+            // Entra como membro (`user_account.company_id`), sem os direitos do dono da
+            // empresa. Senha aleatória: a conta só entra via SSO até que alguém redefina a
+            // senha.
+            let password_hash =
+                bcrypt::hash(random_token(), bcrypt::DEFAULT_COST).map_err(ServiceError::from)?;
+
+            let (user_id,): (i64,) = sqlx::query_as(
+                r#"
+                INSERT INTO user_account (email, password_hash, role, is_active, company_id)
+                VALUES ($1, $2, $3, TRUE, $4)
+                RETURNING user_id
+                "#,
+            )
+            .bind(email)
+            .bind(password_hash)
+            .bind(Role::Company)
+            .bind(provider.company_id)
+            .fetch_one(pool)
+            .await?;
+            Ok(user_id)
+        }
+        None => Err(OidcError::UnknownUser),
+    }
+}
+
+pub async fn complete_login(
+    pool: &PgPool,
+    state: &str,
+    code: &str,
+) -> Result<SessionTokens, OidcError> {
+    let login_state: Option<(i64, String, String)> = sqlx::query_as(
+        r#"
+        UPDATE oidc_login_state SET used_at = $1
+        WHERE state = $2 AND used_at IS NULL AND expires_at > $1
+        RETURNING company_id, code_verifier, nonce
+        "#,
+    )
+    .bind(Utc::now())
+    .bind(hash_token(state))
+    .fetch_optional(pool)
+    .await?;
+    let (company_id, code_verifier, nonce) = login_state.ok_or(OidcError::InvalidState)?;
+
+    let provider = get_provider(pool, company_id)
+        .await?
+        .ok_or(OidcError::NotConfigured)?;
+    let discovery = discover(&provider.issuer).await?;
+
+    let redirect_uri = redirect_uri();
+    let tokens: TokenResponse = reqwest::Client::new()
+        .post(&discovery.token_endpoint)
+        .form(&[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", redirect_uri.as_str()),
+            ("client_id", provider.client_id.as_str()),
+            ("client_secret", provider.client_secret.as_str()),
+            ("code_verifier", code_verifier.as_str()),
+        ])
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    let claims = validate_id_token(&discovery, &provider, &tokens.id_token).await?;
+    if claims.nonce.as_deref() != Some(nonce.as_str()) {
+        return Err(OidcError::Provider("Nonce do ID token não confere".into()));
+    }
+    // Sem `email_verified: true` o e-mail pode ser só um texto que o usuário digitou no
+    // provedor, e bastaria ele para entrar na conta de outra pessoa.
+    let email = match (claims.email, claims.email_verified) {
+        (Some(email), Some(true)) => email,
+        _ => return Err(OidcError::UnverifiedEmail),
+    };
+
+    let user_id = find_or_provision_user(pool, &provider, &email).await?;
+    Ok(sessions::start_session(pool, user_id).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::{auth, policy, signing_keys};
+    use actix_web::{web, App, HttpResponse, HttpServer};
+    use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+    use ring::rand::SystemRandom;
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+    use serde_json::{json, Value};
+    use std::net::TcpListener;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    const CLIENT_ID: &str = "e-signature-test";
+    const KID: &str = "mock-key";
+
+    #[derive(Clone)]
+    struct MockState {
+        issuer: String,
+        pkcs8: Arc<Vec<u8>>,
+        public_key: Arc<Vec<u8>>,
+        claims: Arc<Mutex<Value>>,
+        force_hs256: Arc<AtomicBool>,
+    }
+
+    /// Provedor OIDC mínimo: descoberta, JWKS e um token endpoint que assina `claims`.
+    struct MockProvider {
+        issuer: String,
+        claims: Arc<Mutex<Value>>,
+        /// Assina com HS256 usando a chave pública como segredo (troca de algoritmo).
+        force_hs256: Arc<AtomicBool>,
+    }
+
+    async fn mock_discovery(state: web::Data<MockState>) -> HttpResponse {
+        HttpResponse::Ok().json(json!({
+            "issuer": state.issuer,
+            "authorization_endpoint": format!("{}/authorize", state.issuer),
+            "token_endpoint": format!("{}/token", state.issuer),
+            "jwks_uri": format!("{}/jwks", state.issuer),
+        }))
+    }
+
+    async fn mock_jwks(state: web::Data<MockState>) -> HttpResponse {
+        HttpResponse::Ok().json(json!({
+            "keys": [{
+                "kty": "OKP",
+                "crv": "Ed25519",
+                "use": "sig",
+                "alg": "EdDSA",
+                "kid": KID,
+                "x": general_purpose::URL_SAFE_NO_PAD.encode(state.public_key.as_slice()),
+            }]
+        }))
+    }
+
+    async fn mock_token(state: web::Data<MockState>) -> HttpResponse {
+        let mut claims = state.claims.lock().unwrap().clone();
+        claims["iss"] = json!(state.issuer);
+        claims["aud"] = json!(CLIENT_ID);
+        claims["exp"] = json!((Utc::now() + Duration::minutes(5)).timestamp());
+
+        let (algorithm, key) = if state.force_hs256.load(Ordering::SeqCst) {
+            (
+                Algorithm::HS256,
+                EncodingKey::from_secret(&state.public_key),
+            )
+        } else {
+            (Algorithm::EdDSA, EncodingKey::from_ed_der(&state.pkcs8))
+        };
+        let mut header = Header::new(algorithm);
+        header.kid = Some(KID.to_string());
+        let id_token = encode(&header, &claims, &key).unwrap();
+        HttpResponse::Ok().json(json!({ "id_token": id_token, "token_type": "Bearer" }))
+    }
+
+    fn start_mock_provider() -> MockProvider {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let issuer = format!("http://{}", listener.local_addr().unwrap());
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        let key_pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+        let state = MockState {
+            issuer: issuer.clone(),
+            pkcs8: Arc::new(pkcs8.as_ref().to_vec()),
+            public_key: Arc::new(key_pair.public_key().as_ref().to_vec()),
+            claims: Arc::new(Mutex::new(json!({}))),
+            force_hs256: Arc::new(AtomicBool::new(false)),
+        };
+        let claims = state.claims.clone();
+        let force_hs256 = state.force_hs256.clone();
+
+        std::thread::spawn(move || {
+            actix_web::rt::System::new().block_on(async move {
+                HttpServer::new(move || {
+                    App::new()
+                        .app_data(web::Data::new(state.clone()))
+                        .route(
+                            "/.well-known/openid-configuration",
+                            web::get().to(mock_discovery),
+                        )
+                        .route("/jwks", web::get().to(mock_jwks))
+                        .route("/token", web::post().to(mock_token))
+                })
+                .workers(1)
+                .listen(listener)
+                .unwrap()
+                .run()
+                .await
+            })
+        });
+
+        MockProvider {
+            issuer,
+            claims,
+            force_hs256,
+        }
+    }
+
+    async fn insert_user(pool: &PgPool, email: &str, company_id: Option<i64>) -> i64 {
+        let (user_id,): (i64,) = sqlx::query_as(
+            r#"
+            INSERT INTO user_account (email, password_hash, role, is_active, company_id)
+            VALUES ($1, $2, $3, TRUE, $4)
+            RETURNING user_id
+            "#,
+        )
+        .bind(email)
+        .bind(bcrypt::hash("senha-de-teste", 4).unwrap())
+        .bind(Role::Company)
+        .bind(company_id)
+        .fetch_one(pool)
+        .await
+        .unwrap();
+        user_id
+    }
+
+    /// Empresa com dono e provedor OIDC apontando para o mock, com auto-provisionamento.
+    async fn setup(pool: &PgPool) -> (MockProvider, i64, i64) {
+        env::set_var(
+            "JWT_KEY_ENCRYPTION_KEY",
+            general_purpose::STANDARD.encode([7u8; 32]),
+        );
+        signing_keys::initialize(pool).await.unwrap();
+
+        let provider = start_mock_provider();
+        let owner_id = insert_user(pool, "dono@empresa.test", None).await;
+        let (company_id,): (i64,) = sqlx::query_as(
+            r#"
+            INSERT INTO company (legal_name, tax_id, contact_email, user_id)
+            VALUES ('Empresa Teste', '11222333000181', 'dono@empresa.test', $1)
+            RETURNING company_id
+            "#,
+        )
+        .bind(owner_id)
+        .fetch_one(pool)
+        .await
+        .unwrap();
+
+        upsert_provider(
+            pool,
+            company_id,
+            &UpsertOidcProvider {
+                issuer: provider.issuer.clone(),
+                client_id: CLIENT_ID.to_string(),
+                client_secret: "segredo".to_string(),
+                auto_provision: true,
+            },
+        )
+        .await
+        .unwrap();
+
+        (provider, company_id, owner_id)
+    }
+
+    async fn login(
+        pool: &PgPool,
+        provider: &MockProvider,
+        company_id: i64,
+        email: &str,
+        email_verified: Option<bool>,
+    ) -> Result<SessionTokens, OidcError> {
+        let url = reqwest::Url::parse(&authorization_url(pool, company_id).await?).unwrap();
+        let param = |name: &str| {
+            url.query_pairs()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.into_owned())
+                .unwrap()
+        };
+
+        let mut claims = json!({ "sub": email, "email": email, "nonce": param("nonce") });
+        if let Some(verified) = email_verified {
+            claims["email_verified"] = json!(verified);
+        }
+        *provider.claims.lock().unwrap() = claims;
+
+        complete_login(pool, &param("state"), "codigo-de-teste").await
+    }
+
+    async fn authenticated_user(pool: &PgPool, user_id: i64) -> auth::AuthenticatedUser {
+        auth::load_authenticated_user(pool, user_id)
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    #[test]
+    fn key_algorithm_comes_from_the_key() {
+        let okp: Jwk = serde_json::from_value(json!({
+            "kty": "OKP", "crv": "Ed25519", "x": "11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo"
+        }))
+        .unwrap();
+        assert_eq!(key_algorithm(&okp), Some(Algorithm::EdDSA));
+
+        let hmac: Jwk = serde_json::from_value(json!({
+            "kty": "oct", "alg": "HS256", "k": "c2VncmVkbw"
+        }))
+        .unwrap();
+        assert_eq!(key_algorithm(&hmac), None);
+    }
+
+    #[sqlx::test]
+    async fn header_algorithm_must_match_the_key(pool: PgPool) {
+        let (provider, company_id, _) = setup(&pool).await;
+        provider.force_hs256.store(true, Ordering::SeqCst);
+
+        let result = login(
+            &pool,
+            &provider,
+            company_id,
+            "dono@empresa.test",
+            Some(true),
+        )
+        .await;
+        assert!(matches!(result, Err(OidcError::Provider(e)) if e.contains("não confere")));
+    }
+
+    #[sqlx::test]
+    async fn owner_keeps_company_management(pool: PgPool) {
+        let (provider, company_id, owner_id) = setup(&pool).await;
+
+        login(
+            &pool,
+            &provider,
+            company_id,
+            "dono@empresa.test",
+            Some(true),
+        )
+        .await
+        .unwrap();
+
+        let owner = authenticated_user(&pool, owner_id).await;
+        assert!(owner.is_company_owner);
+        assert!(policy::can_manage_company(&owner, company_id));
+    }
+
+    #[sqlx::test]
+    async fn provisioned_user_is_member_without_owner_rights(pool: PgPool) {
+        let (provider, company_id, _) = setup(&pool).await;
+
+        login(
+            &pool,
+            &provider,
+            company_id,
+            "novo@empresa.test",
+            Some(true),
+        )
+        .await
+        .unwrap();
+
+        let (user_id,): (i64,) =
+            sqlx::query_as("SELECT user_id FROM user_account WHERE email = 'novo@empresa.test'")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        let member = authenticated_user(&pool, user_id).await;
+        assert_eq!(member.company_id, Some(company_id));
+        assert!(!member.is_company_owner);
+        assert!(!policy::can_manage_company(&member, company_id));
+        assert_eq!(
+            policy::company_for_api_keys(&member, Some(company_id)),
+            None
+        );
+    }
+
+    #[sqlx::test]
+    async fn existing_user_outside_company_is_rejected(pool: PgPool) {
+        let (provider, company_id, _) = setup(&pool).await;
+        insert_user(&pool, "outro@empresa.test", None).await;
+
+        let result = login(
+            &pool,
+            &provider,
+            company_id,
+            "outro@empresa.test",
+            Some(true),
+        )
+        .await;
+        assert!(matches!(result, Err(OidcError::NotCompanyMember)));
+    }
+
+    #[sqlx::test]
+    async fn login_requires_verified_email(pool: PgPool) {
+        let (provider, company_id, _) = setup(&pool).await;
+
+        for verified in [None, Some(false)] {
+            let result = login(&pool, &provider, company_id, "novo@empresa.test", verified).await;
+            assert!(matches!(result, Err(OidcError::UnverifiedEmail)));
+        }
+        let (provisioned,): (i64,) =
+            sqlx::query_as("SELECT COUNT(*) FROM user_account WHERE email = 'novo@empresa.test'")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(provisioned, 0);
+    }
+}
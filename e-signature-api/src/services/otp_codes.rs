@@ -0,0 +1,266 @@
+use crate::services::errors::AppError;
+use crate::services::phones;
+use crate::services::sessions::hash_token;
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Duration, Utc};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::{PgConnection, PgExecutor, PgPool};
+use subtle::ConstantTimeEq;
+
+pub const OTP_TTL_MINUTES: i64 = 5;
+const PROOF_TTL_MINUTES: i64 = 10;
+pub const MAX_OTP_ATTEMPTS: i32 = 5;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum OtpPurpose {
+    #[default]
+    Login,
+    SignDocument,
+    PasswordReset,
+}
+
+impl OtpPurpose {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OtpPurpose::Login => "login",
+            OtpPurpose::SignDocument => "sign_document",
+            OtpPurpose::PasswordReset => "password_reset",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct OtpProof {
+    pub proof_token: String,
+    pub purpose: OtpPurpose,
+    pub document_id: Option<i64>,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+pub enum OtpVerification {
+    Verified(OtpProof),
+    Used,
+    Expired,
+    Invalid,
+    TooManyAttempts,
+}
+
+#[derive(Debug)]
+pub struct IssuedOtp {
+    pub otp_code_id: i64,
+    pub code: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+fn salted_hash(salt: &str, code: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(salt.as_bytes());
+    hasher.update(b":");
+    hasher.update(code.trim().as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+/// Gera um novo código para (e-mail, finalidade, documento), invalidando o anterior.
+/// Roda na transação de quem chama, junto com o envio do código pela caixa de saída.
+/// Devolve o código em texto puro só para envio; no banco fica apenas o hash.
+pub async fn issue_code(
+    conn: &mut PgConnection,
+    email: &str,
+    phone_number: Option<&str>,
+    purpose: OtpPurpose,
+    document_id: Option<i64>,
+) -> Result<IssuedOtp, AppError> {
+    let phone_number = phone_number.and_then(phones::normalize);
+    let code = rand::rng().random_range(100_000..1_000_000).to_string();
+    let salt = format!("{:032x}", rand::random::<u128>());
+    let expires_at = Utc::now() + Duration::minutes(OTP_TTL_MINUTES);
+
+    sqlx::query(
+        r#"
+        UPDATE otp_codes SET used = TRUE
+        WHERE email = $1 AND purpose = $2 AND document_id IS NOT DISTINCT FROM $3 AND used = FALSE
+        "#,
+    )
+    .bind(email)
+    .bind(purpose.as_str())
+    .bind(document_id)
+    .execute(&mut *conn)
+    .await?;
+
+    let (otp_code_id,): (i64,) = sqlx::query_as(
+        r#"
+        INSERT INTO otp_codes (email, phone_number, purpose, document_id, code_salt, code_hash, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING otp_code_id
+        "#,
+    )
+    .bind(email)
+    .bind(phone_number)
+    .bind(purpose.as_str())
+    .bind(document_id)
+    .bind(&salt)
+    .bind(salted_hash(&salt, &code))
+    .bind(expires_at)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(IssuedOtp {
+        otp_code_id,
+        code,
+        expires_at,
+    })
+}
+
+pub async fn verify_code(
+    pool: &PgPool,
+    email: &str,
+    purpose: OtpPurpose,
+    document_id: Option<i64>,
+    code: &str,
+) -> Result<OtpVerification, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let record: Option<(i64, String, String, DateTime<Utc>, bool, i32)> = sqlx::query_as(
+        r#"
+        SELECT otp_code_id, code_salt, code_hash, expires_at, used, attempts
+        FROM otp_codes
+        WHERE email = $1 AND purpose = $2 AND document_id IS NOT DISTINCT FROM $3
+        ORDER BY created_at DESC
+        LIMIT 1
+        FOR UPDATE
+        "#,
+    )
+    .bind(email)
+    .bind(purpose.as_str())
+    .bind(document_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let (otp_code_id, salt, code_hash, expires_at, used, attempts) = match record {
+        Some(record) => record,
+        None => return Ok(OtpVerification::Invalid),
+    };
+    if attempts >= MAX_OTP_ATTEMPTS {
+        return Ok(OtpVerification::TooManyAttempts);
+    }
+    if used {
+        return Ok(OtpVerification::Used);
+    }
+    if Utc::now() > expires_at {
+        return Ok(OtpVerification::Expired);
+    }
+
+    let matches: bool = salted_hash(salt.trim(), code)
+        .as_bytes()
+        .ct_eq(code_hash.trim().as_bytes())
+        .into();
+
+    if !matches {
+        // Cada erro conta; ao atingir o limite o código é invalidado.
+        sqlx::query(
+            "UPDATE otp_codes SET attempts = attempts + 1, used = (attempts + 1 >= $2) WHERE otp_code_id = $1",
+        )
+        .bind(otp_code_id)
+        .bind(MAX_OTP_ATTEMPTS)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        return Ok(if attempts + 1 >= MAX_OTP_ATTEMPTS {
+            OtpVerification::TooManyAttempts
+        } else {
+            OtpVerification::Invalid
+        });
+    }
+
+    sqlx::query("UPDATE otp_codes SET used = TRUE WHERE otp_code_id = $1")
+        .bind(otp_code_id)
+        .execute(&mut *tx)
+        .await?;
+
+    let bytes: [u8; 32] = rand::random();
+    let proof_token = general_purpose::URL_SAFE_NO_PAD.encode(bytes);
+    let proof_expires_at = Utc::now() + Duration::minutes(PROOF_TTL_MINUTES);
+
+    sqlx::query(
+        r#"
+        INSERT INTO otp_proof (token_hash, email, purpose, document_id, expires_at)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+    )
+    .bind(hash_token(&proof_token))
+    .bind(email)
+    .bind(purpose.as_str())
+    .bind(document_id)
+    .bind(proof_expires_at)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(OtpVerification::Verified(OtpProof {
+        proof_token,
+        purpose,
+        document_id,
+        expires_at: proof_expires_at,
+    }))
+}
+
+/// Confere, sem gastar, se a prova ainda vale para `email`, `purpose` e `document_id`.
+pub async fn proof_is_valid(
+    executor: impl PgExecutor<'_>,
+    proof_token: &str,
+    email: &str,
+    purpose: OtpPurpose,
+    document_id: Option<i64>,
+) -> Result<bool, sqlx::Error> {
+    let (valid,): (bool,) = sqlx::query_as(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM otp_proof
+            WHERE token_hash = $1 AND lower(email) = lower($2) AND purpose = $3
+              AND document_id IS NOT DISTINCT FROM $4
+              AND consumed_at IS NULL AND expires_at > $5
+        )
+        "#,
+    )
+    .bind(hash_token(proof_token))
+    .bind(email)
+    .bind(purpose.as_str())
+    .bind(document_id)
+    .bind(Utc::now())
+    .fetch_one(executor)
+    .await?;
+    Ok(valid)
+}
+
+/// Gasta a prova devolvida por `verify_code`. Só a primeira chamada com uma prova válida
+/// para `email`, `purpose` e `document_id` devolve `true`.
+pub async fn consume_proof(
+    executor: impl PgExecutor<'_>,
+    proof_token: &str,
+    email: &str,
+    purpose: OtpPurpose,
+    document_id: Option<i64>,
+) -> Result<bool, sqlx::Error> {
+    let consumed = sqlx::query(
+        r#"
+        UPDATE otp_proof SET consumed_at = $5
+        WHERE token_hash = $1 AND lower(email) = lower($2) AND purpose = $3
+          AND document_id IS NOT DISTINCT FROM $4
+          AND consumed_at IS NULL AND expires_at > $5
+        "#,
+    )
+    .bind(hash_token(proof_token))
+    .bind(email)
+    .bind(purpose.as_str())
+    .bind(document_id)
+    .bind(Utc::now())
+    .execute(executor)
+    .await?;
+    Ok(consumed.rows_affected() == 1)
+}
@@ -0,0 +1,211 @@
+use crate::services::deliveries::{self, MessageContext, OutboundMessage};
+use crate::services::errors::ServiceError;
+use crate::services::notifications::{self, Notification, Recipient};
+use chrono::{Duration, Utc};
+use futures_util::future::join_all;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgListener;
+use sqlx::{PgConnection, PgPool};
+use std::env;
+
+const NOTIFY_CHANNEL: &str = "outbound_message";
+const DEFAULT_MAX_ATTEMPTS: i32 = 5;
+const BASE_RETRY_SECONDS: i64 = 30;
+const MAX_RETRY_SECONDS: i64 = 60 * 60;
+const BATCH_SIZE: i64 = 10;
+const POLL_INTERVAL_SECONDS: u64 = 5;
+/// Por quanto tempo uma mensagem fica reservada para o worker que a pegou. Se o
+/// processo cair no meio do envio, ela volta para a fila depois disso.
+const LEASE_SECONDS: i64 = 120;
+
+#[derive(Serialize, Deserialize)]
+struct Payload {
+    recipient: Recipient,
+    notification: Notification,
+}
+
+fn max_attempts() -> i32 {
+    env::var("OUTBOX_MAX_ATTEMPTS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .filter(|attempts| *attempts > 0)
+        .unwrap_or(DEFAULT_MAX_ATTEMPTS)
+}
+
+/// Espera exponencial a partir de 30s (30s, 1min, 2min...), limitada a 1h, com um
+/// pouco de variação para as novas tentativas não saírem todas juntas.
+fn retry_delay(attempts: i32) -> Duration {
+    let exponent = attempts.clamp(1, 16) as u32 - 1;
+    let seconds = BASE_RETRY_SECONDS
+        .saturating_mul(2_i64.pow(exponent))
+        .min(MAX_RETRY_SECONDS);
+    Duration::seconds(seconds + rand::rng().random_range(0..=seconds / 10))
+}
+
+/// Coloca a mensagem na caixa de saída dentro da transação de quem chama: se a
+/// transação não for confirmada, nada é enviado. O worker é avisado no commit.
+pub async fn enqueue(
+    conn: &mut PgConnection,
+    recipient: Recipient,
+    notification: Notification,
+    context: MessageContext,
+) -> Result<OutboundMessage, ServiceError> {
+    let payload = serde_json::to_value(Payload {
+        recipient,
+        notification,
+    })?;
+
+    let message = deliveries::create(&mut *conn, context, payload).await?;
+    sqlx::query("SELECT pg_notify($1, '')")
+        .bind(NOTIFY_CHANNEL)
+        .execute(&mut *conn)
+        .await?;
+    Ok(message)
+}
+
+/// Reserva as mensagens vencidas e conta a tentativa já na reserva, para que uma
+/// mensagem que derruba o processo não seja tentada para sempre.
+async fn claim_due(pool: &PgPool) -> Result<Vec<(i64, serde_json::Value, i32)>, sqlx::Error> {
+    let now = Utc::now();
+    sqlx::query_as(
+        r#"
+        UPDATE outbound_message
+        SET attempts = attempts + 1, next_attempt_at = $1, updated_at = $2
+        WHERE message_id IN (
+            SELECT message_id FROM outbound_message
+            WHERE status = 'pending' AND payload IS NOT NULL AND next_attempt_at <= $2
+            ORDER BY next_attempt_at
+            LIMIT $3
+            FOR UPDATE SKIP LOCKED
+        )
+        RETURNING message_id, payload, attempts
+        "#,
+    )
+    .bind(now + Duration::seconds(LEASE_SECONDS))
+    .bind(now)
+    .bind(BATCH_SIZE)
+    .fetch_all(pool)
+    .await
+}
+
+async fn process(
+    pool: &PgPool,
+    message_id: i64,
+    payload: serde_json::Value,
+    attempts: i32,
+) -> Result<(), sqlx::Error> {
+    let payload: Payload = match serde_json::from_value(payload) {
+        Ok(payload) => payload,
+        Err(e) => {
+            return deliveries::dead_letter(pool, message_id, &format!("Conteúdo inválido: {}", e))
+                .await
+        }
+    };
+
+    match notifications::deliver(&payload.recipient, &payload.notification).await {
+        Ok(dispatched) => {
+            deliveries::record_dispatch(
+                pool,
+                message_id,
+                dispatched.channel.as_str(),
+                &dispatched.address,
+                &dispatched.provider_id,
+                dispatched.channel.accepted_status(),
+            )
+            .await
+        }
+        Err(e) if attempts >= max_attempts() => {
+            eprintln!(
+                "Mensagem {} não entregue após {} tentativas; movida para dead-letter: {}",
+                message_id, attempts, e
+            );
+            deliveries::dead_letter(pool, message_id, &e.to_string()).await
+        }
+        Err(e) => {
+            deliveries::schedule_retry(
+                pool,
+                message_id,
+                &e.to_string(),
+                Utc::now() + retry_delay(attempts),
+            )
+            .await
+        }
+    }
+}
+
+/// Envia um lote de mensagens vencidas. Devolve quantas foram reservadas.
+async fn process_due(pool: &PgPool) -> Result<usize, sqlx::Error> {
+    let claimed = claim_due(pool).await?;
+    let count = claimed.len();
+
+    let results = join_all(
+        claimed
+            .into_iter()
+            .map(|(message_id, payload, attempts)| async move {
+                (
+                    message_id,
+                    process(pool, message_id, payload, attempts).await,
+                )
+            }),
+    )
+    .await;
+    for (message_id, result) in results {
+        if let Err(e) = result {
+            eprintln!(
+                "Falha ao registrar a entrega da mensagem {}: {:?}",
+                message_id, e
+            );
+        }
+    }
+    Ok(count)
+}
+
+/// Dorme até chegar um aviso de mensagem nova ou passar o intervalo de varredura,
+/// que cobre as novas tentativas agendadas e avisos perdidos.
+async fn wait_for_work(listener: &mut Option<PgListener>) {
+    let poll = tokio::time::sleep(std::time::Duration::from_secs(POLL_INTERVAL_SECONDS));
+    tokio::pin!(poll);
+
+    match listener {
+        Some(listener) => tokio::select! {
+            received = listener.recv() => {
+                if received.is_err() {
+                    poll.await;
+                }
+            }
+            _ = &mut poll => {}
+        },
+        None => poll.await,
+    }
+}
+
+/// Worker da caixa de saída. Como tudo fica no banco, o que estava pendente quando
+/// o servidor parou é enviado assim que ele volta.
+pub fn spawn_worker(pool: PgPool) {
+    tokio::spawn(async move {
+        let mut listener = match PgListener::connect_with(&pool).await {
+            Ok(mut listener) => match listener.listen(NOTIFY_CHANNEL).await {
+                Ok(_) => Some(listener),
+                Err(e) => {
+                    eprintln!("Falha ao escutar {}: {:?}", NOTIFY_CHANNEL, e);
+                    None
+                }
+            },
+            Err(e) => {
+                eprintln!("Falha ao conectar o listener da caixa de saída: {:?}", e);
+                None
+            }
+        };
+
+        loop {
+            match process_due(&pool).await {
+                // Lote cheio: provavelmente há mais mensagens vencidas.
+                Ok(count) if count as i64 == BATCH_SIZE => continue,
+                Ok(_) => {}
+                Err(e) => eprintln!("Falha ao processar a caixa de saída: {:?}", e),
+            }
+            wait_for_work(&mut listener).await;
+        }
+    });
+}
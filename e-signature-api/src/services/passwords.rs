@@ -0,0 +1,144 @@
+use crate::services::deliveries::{MessageContext, MessageKind};
+use crate::services::email::templates::{self, EmailTemplate};
+use crate::services::errors::ServiceError;
+use crate::services::i18n;
+use crate::services::notifications::Recipient;
+use crate::services::outbox;
+use crate::services::sessions::{self, hash_token};
+use crate::services::users as user_service;
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{Duration, Utc};
+use sqlx::PgPool;
+
+const RESET_TOKEN_TTL_MINUTES: i64 = 30;
+pub const MIN_PASSWORD_LENGTH: usize = 8;
+
+pub fn is_acceptable_password(password: &str) -> bool {
+    password.chars().count() >= MIN_PASSWORD_LENGTH
+}
+
+pub async fn request_reset(pool: &PgPool, email: &str) -> Result<(), ServiceError> {
+    let contact = match user_service::get_user_contact(pool, email).await? {
+        Some(contact) => contact,
+        None => return Ok(()),
+    };
+
+    let bytes: [u8; 32] = rand::random();
+    let token = general_purpose::URL_SAFE_NO_PAD.encode(bytes);
+    let expires_at = Utc::now() + Duration::minutes(RESET_TOKEN_TTL_MINUTES);
+
+    let mut tx = pool.begin().await?;
+
+    // Só o pedido mais recente continua válido.
+    sqlx::query(
+        "UPDATE password_reset_token SET used_at = $1 WHERE user_id = $2 AND used_at IS NULL",
+    )
+    .bind(Utc::now())
+    .bind(contact.user_id)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        "INSERT INTO password_reset_token (user_id, token_hash, expires_at) VALUES ($1, $2, $3)",
+    )
+    .bind(contact.user_id)
+    .bind(hash_token(&token))
+    .bind(expires_at)
+    .execute(&mut *tx)
+    .await?;
+
+    let email = templates::render(
+        EmailTemplate::PasswordReset,
+        i18n::preferred_or_current(contact.locale.as_deref()),
+        &[
+            ("token", &token),
+            ("minutes", &RESET_TOKEN_TTL_MINUTES.to_string()),
+        ],
+    );
+
+    outbox::enqueue(
+        &mut tx,
+        Recipient::from(contact),
+        email.into(),
+        MessageContext::new(MessageKind::PasswordReset),
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+pub async fn reset_password(
+    pool: &PgPool,
+    token: &str,
+    new_password: &str,
+) -> Result<bool, ServiceError> {
+    let password_hash = bcrypt::hash(new_password, bcrypt::DEFAULT_COST)?;
+
+    let mut tx = pool.begin().await?;
+
+    let record: Option<(i64, i64)> = sqlx::query_as(
+        r#"
+        SELECT password_reset_token_id, user_id
+        FROM password_reset_token
+        WHERE token_hash = $1 AND used_at IS NULL AND expires_at > $2
+        FOR UPDATE
+        "#,
+    )
+    .bind(hash_token(token))
+    .bind(Utc::now())
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let (token_id, user_id) = match record {
+        Some(record) => record,
+        None => return Ok(false),
+    };
+
+    sqlx::query("UPDATE password_reset_token SET used_at = $1 WHERE password_reset_token_id = $2")
+        .bind(Utc::now())
+        .bind(token_id)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query("UPDATE user_account SET password_hash = $1, updated_at = $2 WHERE user_id = $3")
+        .bind(password_hash)
+        .bind(Utc::now())
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    sessions::revoke_all_sessions(pool, user_id).await?;
+
+    Ok(true)
+}
+
+pub async fn change_password(
+    pool: &PgPool,
+    user_id: i64,
+    current_session_id: Option<&str>,
+    current_password: &str,
+    new_password: &str,
+) -> Result<bool, ServiceError> {
+    let password_hash: Option<(String,)> = sqlx::query_as(
+        "SELECT password_hash FROM user_account WHERE user_id = $1 AND deleted_at IS NULL",
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    let valid = password_hash
+        .map(|(hash,)| bcrypt::verify(current_password, &hash).unwrap_or(false))
+        .unwrap_or(false);
+    if !valid {
+        return Ok(false);
+    }
+
+    user_service::update_password(pool, user_id, new_password).await?;
+    sessions::revoke_other_sessions(pool, user_id, current_session_id).await?;
+
+    Ok(true)
+}
@@ -0,0 +1,151 @@
+/// Código do Brasil, usado para números digitados sem código de país.
+const DEFAULT_COUNTRY_CODE: &str = "55";
+
+/// DDDs em uso no Brasil (plano de numeração da Anatel).
+const BR_AREA_CODES: [u8; 67] = [
+    11, 12, 13, 14, 15, 16, 17, 18, 19, 21, 22, 24, 27, 28, 31, 32, 33, 34, 35, 37, 38, 41, 42, 43,
+    44, 45, 46, 47, 48, 49, 51, 53, 54, 55, 61, 62, 63, 64, 65, 66, 67, 68, 69, 71, 73, 74, 75, 77,
+    79, 81, 82, 83, 84, 85, 86, 87, 88, 89, 91, 92, 93, 94, 95, 96, 97, 98, 99,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineType {
+    Mobile,
+    Landline,
+    /// Número estrangeiro: o tipo de linha não é conhecido.
+    Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PhoneNumber {
+    pub e164: String,
+    pub line_type: LineType,
+}
+
+impl PhoneNumber {
+    /// Fixo brasileiro não tem WhatsApp; números estrangeiros recebem o benefício da dúvida.
+    pub fn whatsapp_eligible(&self) -> bool {
+        self.line_type != LineType::Landline
+    }
+}
+
+/// DDD + assinante: celular com 9 dígitos começando por 9, fixo com 8 dígitos
+/// começando de 2 a 5.
+fn parse_brazilian(national: &str) -> Option<PhoneNumber> {
+    let area_code: u8 = national.get(..2)?.parse().ok()?;
+    if !BR_AREA_CODES.contains(&area_code) {
+        return None;
+    }
+    let subscriber = &national[2..];
+    let line_type = match (subscriber.len(), subscriber.chars().next()?) {
+        (9, '9') => LineType::Mobile,
+        (8, '2'..='5') => LineType::Landline,
+        _ => return None,
+    };
+    Some(PhoneNumber {
+        e164: format!("+{}{}", DEFAULT_COUNTRY_CODE, national),
+        line_type,
+    })
+}
+
+/// Interpreta um telefone digitado com ou sem formatação. Sem "+", o número é tratado
+/// como brasileiro, inclusive com o 0 de longa distância e o código da operadora
+/// (ex.: "0 21 11 98765-4321").
+pub fn parse(value: &str) -> Option<PhoneNumber> {
+    let value = value.trim();
+    let (international, rest) = match value.strip_prefix('+') {
+        Some(rest) => (true, rest),
+        None => (false, value),
+    };
+    if !rest
+        .chars()
+        .all(|c| c.is_ascii_digit() || matches!(c, ' ' | '(' | ')' | '-' | '.'))
+    {
+        return None;
+    }
+    let digits: String = rest.chars().filter(char::is_ascii_digit).collect();
+
+    if international {
+        if let Some(national) = digits.strip_prefix(DEFAULT_COUNTRY_CODE) {
+            return parse_brazilian(national);
+        }
+        // E.164: até 15 dígitos e código de país sem zero na frente.
+        if !(8..=15).contains(&digits.len()) || digits.starts_with('0') {
+            return None;
+        }
+        return Some(PhoneNumber {
+            e164: format!("+{}", digits),
+            line_type: LineType::Unknown,
+        });
+    }
+
+    let national = match (digits.strip_prefix('0'), digits.len()) {
+        (Some(rest), 11 | 12) => rest,
+        (Some(rest), 13 | 14) => &rest[2..],
+        (None, 10 | 11) => &digits,
+        (None, 12 | 13) => digits.strip_prefix(DEFAULT_COUNTRY_CODE)?,
+        _ => return None,
+    };
+    parse_brazilian(national)
+}
+
+/// Telefone em E.164 (`+5511987654321`), ou `None` se o número não é válido.
+pub fn normalize(value: &str) -> Option<String> {
+    parse(value).map(|phone| phone.e164)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn brazilian_numbers() {
+        for (input, e164, line_type) in [
+            ("(11) 98765-4321", "+5511987654321", LineType::Mobile),
+            ("11987654321", "+5511987654321", LineType::Mobile),
+            ("+55 11 98765-4321", "+5511987654321", LineType::Mobile),
+            ("5511987654321", "+5511987654321", LineType::Mobile),
+            ("011 98765-4321", "+5511987654321", LineType::Mobile),
+            ("0 21 11 98765-4321", "+5511987654321", LineType::Mobile),
+            ("(21) 3456-7890", "+552134567890", LineType::Landline),
+            ("0 15 21 3456-7890", "+552134567890", LineType::Landline),
+            ("+55 (21) 3456.7890", "+552134567890", LineType::Landline),
+        ] {
+            let phone = parse(input).unwrap_or_else(|| panic!("{} deveria ser válido", input));
+            assert_eq!(phone.e164, e164, "{}", input);
+            assert_eq!(phone.line_type, line_type, "{}", input);
+        }
+    }
+
+    #[test]
+    fn foreign_numbers() {
+        let phone = parse("+1 (415) 555-2671").unwrap();
+        assert_eq!(phone.e164, "+14155552671");
+        assert_eq!(phone.line_type, LineType::Unknown);
+        assert!(phone.whatsapp_eligible());
+    }
+
+    #[test]
+    fn invalid_numbers() {
+        for input in [
+            "(20) 98765-4321",
+            "(11) 88765-4321",
+            "(11) 1234-5678",
+            "(11) 9876-543",
+            "+55 20 98765-4321",
+            "+0 123 456 789",
+            "+1234567",
+            "+1234567890123456",
+            "11 98765-4321 ramal 2",
+            "",
+        ] {
+            assert_eq!(parse(input), None, "{}", input);
+        }
+    }
+
+    #[test]
+    fn landlines_are_not_whatsapp_eligible() {
+        assert!(!parse("(21) 3456-7890").unwrap().whatsapp_eligible());
+        assert!(parse("(21) 98765-4321").unwrap().whatsapp_eligible());
+    }
+}
@@ -0,0 +1,121 @@
+use crate::services::auth::AuthenticatedUser;
+use crate::services::documents::models::Document;
+use crate::services::users::models::{Role, UpdateUser};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentScope {
+    All,
+    Company(i64),
+    Signer(i64),
+    Nothing,
+}
+
+impl DocumentScope {
+    pub fn company_id(&self) -> Option<i64> {
+        match self {
+            DocumentScope::Company(company_id) => Some(*company_id),
+            _ => None,
+        }
+    }
+
+    pub fn signer_id(&self) -> Option<i64> {
+        match self {
+            DocumentScope::Signer(signer_id) => Some(*signer_id),
+            _ => None,
+        }
+    }
+}
+
+pub fn is_admin(user: &AuthenticatedUser) -> bool {
+    user.role == Role::Admin
+}
+
+pub fn document_scope(user: &AuthenticatedUser) -> DocumentScope {
+    match (user.role, user.company_id, user.signer_id) {
+        (Role::Admin, _, _) => DocumentScope::All,
+        (Role::Company, Some(company_id), _) => DocumentScope::Company(company_id),
+        (Role::Signer, _, Some(signer_id)) => DocumentScope::Signer(signer_id),
+        _ => DocumentScope::Nothing,
+    }
+}
+
+pub fn can_list_users(user: &AuthenticatedUser) -> bool {
+    is_admin(user)
+}
+
+pub fn can_create_user_with_role(user: Option<&AuthenticatedUser>, role: Role) -> bool {
+    role != Role::Admin || user.is_some_and(is_admin)
+}
+
+pub fn can_view_user(user: &AuthenticatedUser, target_user_id: i64) -> bool {
+    is_admin(user) || user.user_id == target_user_id
+}
+
+pub fn can_update_user(user: &AuthenticatedUser, target_user_id: i64, data: &UpdateUser) -> bool {
+    if data.role.is_some() && !is_admin(user) {
+        return false;
+    }
+    can_view_user(user, target_user_id)
+}
+
+pub fn can_delete_user(user: &AuthenticatedUser, target_user_id: i64) -> bool {
+    can_view_user(user, target_user_id)
+}
+
+/// O cadastro facial é biométrico: só o próprio usuário ou um administrador o altera.
+pub fn can_manage_face_enrollment(user: &AuthenticatedUser, target_user_id: i64) -> bool {
+    can_view_user(user, target_user_id)
+}
+
+/// Só um administrador troca o rosto cadastrado por uma imagem sem prova de vida e sem
+/// conferir com a referência atual.
+pub fn can_enroll_face_unverified(user: &AuthenticatedUser) -> bool {
+    is_admin(user)
+}
+
+/// Só um administrador pode remover o segundo fator de uma conta (ex.: celular perdido).
+pub fn can_reset_second_factor(user: &AuthenticatedUser) -> bool {
+    is_admin(user)
+}
+
+pub fn company_for_new_document(user: &AuthenticatedUser, requested: Option<i64>) -> Option<i64> {
+    match user.role {
+        Role::Admin => requested,
+        Role::Company => user.company_id,
+        Role::Signer => None,
+    }
+}
+
+fn is_company_owner(user: &AuthenticatedUser) -> bool {
+    user.role == Role::Company && user.is_company_owner
+}
+
+/// Configurações da empresa (SSO, etc.): administrador ou o dono da empresa. Membros,
+/// como os criados no primeiro login via SSO, não gerenciam a empresa.
+pub fn can_manage_company(user: &AuthenticatedUser, company_id: i64) -> bool {
+    if user.api_key_id.is_some() {
+        return false;
+    }
+    is_admin(user) || (is_company_owner(user) && user.company_id == Some(company_id))
+}
+
+/// Empresa cujas chaves de API o usuário pode gerenciar: a própria, se for o dono, ou a
+/// informada quando for administrador. Chaves não gerenciam outras chaves.
+pub fn company_for_api_keys(user: &AuthenticatedUser, requested: Option<i64>) -> Option<i64> {
+    if user.api_key_id.is_some() {
+        return None;
+    }
+    match user.role {
+        Role::Admin => requested,
+        Role::Company if is_company_owner(user) => user.company_id,
+        _ => None,
+    }
+}
+
+pub fn can_manage_document(user: &AuthenticatedUser, document: &Document) -> bool {
+    match user.role {
+        Role::Admin => true,
+        Role::Company => user.company_id == Some(document.company_id),
+        Role::Signer => false,
+    }
+}
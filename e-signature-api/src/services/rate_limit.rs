@@ -0,0 +1,121 @@
+use crate::services::errors::AppError;
+use crate::AppState;
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::{web, Error, HttpResponse, ResponseError};
+use std::collections::HashMap;
+use std::env;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const PRUNE_THRESHOLD: usize = 10_000;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Limit {
+    pub max_requests: u32,
+    pub window: Duration,
+}
+
+pub const IP_LIMIT: Limit = Limit {
+    max_requests: 20,
+    window: Duration::from_secs(60),
+};
+pub const ACCOUNT_LIMIT: Limit = Limit {
+    max_requests: 10,
+    window: Duration::from_secs(15 * 60),
+};
+
+struct Window {
+    started_at: Instant,
+    window: Duration,
+    count: u32,
+}
+
+#[derive(Default)]
+pub struct RateLimiter {
+    windows: Mutex<HashMap<String, Window>>,
+}
+
+impl RateLimiter {
+    /// Conta uma requisição para `key`; devolve quanto falta para liberar quando o limite estoura.
+    pub fn check(&self, key: &str, limit: Limit) -> Result<(), Duration> {
+        let now = Instant::now();
+        let mut windows = match self.windows.lock() {
+            Ok(windows) => windows,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        if windows.len() > PRUNE_THRESHOLD {
+            windows.retain(|_, w| now.duration_since(w.started_at) < w.window);
+        }
+
+        let entry = windows.entry(key.to_string()).or_insert(Window {
+            started_at: now,
+            window: limit.window,
+            count: 0,
+        });
+
+        if now.duration_since(entry.started_at) >= entry.window {
+            entry.started_at = now;
+            entry.window = limit.window;
+            entry.count = 0;
+        }
+
+        if entry.count >= limit.max_requests {
+            return Err(entry.window - now.duration_since(entry.started_at));
+        }
+
+        entry.count += 1;
+        Ok(())
+    }
+}
+
+pub fn too_many_requests(retry_after: Duration) -> HttpResponse {
+    AppError::TooManyRequests(retry_after).error_response()
+}
+
+fn is_sensitive_route(path: &str) -> bool {
+    matches!(
+        path,
+        "/api/auth/login"
+            | "/api/auth/refresh"
+            | "/api/auth/activate"
+            | "/api/auth/activate/resend"
+            | "/api/auth/password/forgot"
+            | "/api/auth/password/reset"
+            | "/api/auth/oidc/callback"
+            | "/otp/generate"
+            | "/otp/verify"
+    ) || path.ends_with("/facial-verify")
+}
+
+fn client_ip(req: &ServiceRequest) -> String {
+    let trust_proxy = env::var("TRUST_PROXY_HEADERS")
+        .map(|v| v == "true")
+        .unwrap_or(false);
+    let info = req.connection_info();
+    let ip = if trust_proxy {
+        info.realip_remote_addr()
+    } else {
+        info.peer_addr()
+    };
+    ip.unwrap_or("unknown").to_string()
+}
+
+pub async fn limit_sensitive_routes(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    if is_sensitive_route(req.path()) {
+        if let Some(state) = req.app_data::<web::Data<AppState>>() {
+            let key = format!("ip:{}:{}", client_ip(&req), req.path());
+            if let Err(retry_after) = state.rate_limiter.check(&key, IP_LIMIT) {
+                let response = too_many_requests(retry_after);
+                return Ok(req.into_response(response).map_into_right_body());
+            }
+        }
+    }
+
+    next.call(req).await.map(|res| res.map_into_left_body())
+}
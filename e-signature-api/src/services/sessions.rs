@@ -0,0 +1,250 @@
+use crate::services::auth::{self, Claims};
+use crate::services::errors::ServiceError;
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+const REFRESH_TOKEN_TTL_DAYS: i64 = 30;
+
+#[derive(Debug, Serialize)]
+pub struct SessionTokens {
+    pub token: String,
+    pub refresh_token: String,
+    pub refresh_expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, FromRow)]
+struct RefreshTokenRecord {
+    refresh_token_id: i64,
+    user_id: i64,
+    session_id: String,
+    expires_at: DateTime<Utc>,
+    used_at: Option<DateTime<Utc>>,
+    revoked_at: Option<DateTime<Utc>>,
+}
+
+pub fn hash_token(token: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(token.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+fn generate_refresh_token() -> String {
+    let bytes: [u8; 32] = rand::random();
+    general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+}
+
+async fn store_refresh_token(
+    pool: &PgPool,
+    user_id: i64,
+    session_id: &str,
+) -> Result<(String, DateTime<Utc>), sqlx::Error> {
+    let refresh_token = generate_refresh_token();
+    let expires_at = Utc::now() + Duration::days(REFRESH_TOKEN_TTL_DAYS);
+
+    sqlx::query(
+        r#"
+        INSERT INTO refresh_token (user_id, session_id, token_hash, expires_at)
+        VALUES ($1, $2, $3, $4)
+        "#,
+    )
+    .bind(user_id)
+    .bind(session_id)
+    .bind(hash_token(&refresh_token))
+    .bind(expires_at)
+    .execute(pool)
+    .await?;
+
+    Ok((refresh_token, expires_at))
+}
+
+fn session_tokens(
+    user_id: i64,
+    session_id: &str,
+    refresh_token: String,
+    refresh_expires_at: DateTime<Utc>,
+) -> Result<SessionTokens, ServiceError> {
+    let token = auth::create_session_jwt(&user_id.to_string(), Some(session_id))?;
+
+    Ok(SessionTokens {
+        token,
+        refresh_token,
+        refresh_expires_at,
+    })
+}
+
+pub async fn start_session(pool: &PgPool, user_id: i64) -> Result<SessionTokens, ServiceError> {
+    let session_id = Uuid::new_v4().to_string();
+    let (refresh_token, expires_at) = store_refresh_token(pool, user_id, &session_id).await?;
+    session_tokens(user_id, &session_id, refresh_token, expires_at)
+}
+
+/// Troca um refresh token válido por um novo par de tokens. Reapresentar um token
+/// já utilizado é tratado como roubo e derruba a sessão inteira.
+pub async fn rotate(
+    pool: &PgPool,
+    refresh_token: &str,
+) -> Result<Option<SessionTokens>, ServiceError> {
+    let record = sqlx::query_as::<_, RefreshTokenRecord>(
+        r#"
+        SELECT refresh_token_id, user_id, session_id, expires_at, used_at, revoked_at
+        FROM refresh_token
+        WHERE token_hash = $1
+        "#,
+    )
+    .bind(hash_token(refresh_token))
+    .fetch_optional(pool)
+    .await?;
+
+    let record = match record {
+        Some(record) => record,
+        None => return Ok(None),
+    };
+
+    if record.revoked_at.is_some() || record.expires_at < Utc::now() {
+        return Ok(None);
+    }
+
+    if record.used_at.is_some() {
+        revoke_session(pool, &record.session_id).await?;
+        return Ok(None);
+    }
+
+    let claimed = sqlx::query(
+        "UPDATE refresh_token SET used_at = $1 WHERE refresh_token_id = $2 AND used_at IS NULL",
+    )
+    .bind(Utc::now())
+    .bind(record.refresh_token_id)
+    .execute(pool)
+    .await?;
+
+    if claimed.rows_affected() == 0 {
+        revoke_session(pool, &record.session_id).await?;
+        return Ok(None);
+    }
+
+    let (new_refresh_token, expires_at) =
+        store_refresh_token(pool, record.user_id, &record.session_id).await?;
+    session_tokens(
+        record.user_id,
+        &record.session_id,
+        new_refresh_token,
+        expires_at,
+    )
+    .map(Some)
+}
+
+pub async fn revoke_session(pool: &PgPool, session_id: &str) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE refresh_token SET revoked_at = $1 WHERE session_id = $2 AND revoked_at IS NULL",
+    )
+    .bind(Utc::now())
+    .bind(session_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn revoke_access_token(pool: &PgPool, claims: &Claims) -> Result<(), sqlx::Error> {
+    let expires_at = Utc
+        .timestamp_opt(claims.exp as i64, 0)
+        .single()
+        .unwrap_or_else(Utc::now);
+
+    sqlx::query("DELETE FROM revoked_token WHERE expires_at < $1")
+        .bind(Utc::now())
+        .execute(pool)
+        .await?;
+
+    sqlx::query(
+        "INSERT INTO revoked_token (jti, expires_at) VALUES ($1, $2) ON CONFLICT (jti) DO NOTHING",
+    )
+    .bind(&claims.jti)
+    .bind(expires_at)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn logout(pool: &PgPool, claims: &Claims) -> Result<(), sqlx::Error> {
+    if let Some(session_id) = &claims.sid {
+        revoke_session(pool, session_id).await?;
+    }
+    revoke_access_token(pool, claims).await
+}
+
+pub async fn revoke_all_sessions(pool: &PgPool, user_id: i64) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        "UPDATE refresh_token SET revoked_at = $1 WHERE user_id = $2 AND revoked_at IS NULL",
+    )
+    .bind(Utc::now())
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?;
+
+    // Tokens de acesso emitidos até este segundo deixam de valer, exceto os de sessões
+    // abertas depois (ver `is_revoked`).
+    sqlx::query("UPDATE user_account SET sessions_revoked_at = $1 WHERE user_id = $2")
+        .bind(Utc::now())
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await
+}
+
+pub async fn revoke_other_sessions(
+    pool: &PgPool,
+    user_id: i64,
+    keep_session_id: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE refresh_token SET revoked_at = $1
+        WHERE user_id = $2 AND revoked_at IS NULL AND session_id IS DISTINCT FROM $3
+        "#,
+    )
+    .bind(Utc::now())
+    .bind(user_id)
+    .bind(keep_session_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Um token emitido no mesmo segundo de uma revogação geral continua valendo só se a
+/// sessão dele (`sid`) foi aberta depois dela e ainda está ativa.
+pub async fn is_revoked(pool: &PgPool, claims: &Claims) -> Result<bool, sqlx::Error> {
+    let user_id: i64 = claims.sub.parse().unwrap_or(0);
+
+    let revoked: (bool,) = sqlx::query_as(
+        r#"
+        SELECT EXISTS (SELECT 1 FROM revoked_token WHERE jti = $1)
+            OR EXISTS (
+                SELECT 1 FROM user_account u
+                WHERE u.user_id = $2 AND u.sessions_revoked_at >= to_timestamp($3)
+                  AND NOT EXISTS (
+                      SELECT 1 FROM refresh_token r
+                      WHERE r.session_id = $4 AND r.user_id = u.user_id AND r.revoked_at IS NULL
+                  )
+            )
+            OR EXISTS (
+                SELECT 1 FROM refresh_token
+                WHERE session_id = $4 AND revoked_at IS NOT NULL
+            )
+        "#,
+    )
+    .bind(&claims.jti)
+    .bind(user_id)
+    .bind(claims.iat as f64)
+    .bind(&claims.sid)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(revoked.0)
+}
@@ -0,0 +1,299 @@
+use crate::services::errors::ServiceError;
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::jwk::{
+    AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm,
+    OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse,
+};
+use jsonwebtoken::{DecodingKey, EncodingKey};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use sqlx::{FromRow, PgPool};
+use std::env;
+use std::sync::{LazyLock, RwLock};
+use uuid::Uuid;
+
+const ALGORITHM: &str = "EdDSA";
+const DEFAULT_ROTATION_DAYS: i64 = 30;
+// Novas chaves são publicadas no JWKS antes de começarem a assinar, e as antigas
+// continuam válidas por mais um tempo para os tokens já emitidos.
+const PUBLISH_AHEAD_HOURS: i64 = 1;
+const RETIRE_GRACE_HOURS: i64 = 2;
+const ROTATION_CHECK_MINUTES: u64 = 5;
+
+#[derive(FromRow)]
+struct SigningKeyRecord {
+    kid: String,
+    private_key: Vec<u8>,
+    private_key_nonce: Option<Vec<u8>>,
+    public_key: Vec<u8>,
+    activates_at: DateTime<Utc>,
+    retires_at: DateTime<Utc>,
+}
+
+#[derive(Clone)]
+pub struct SigningKey {
+    pub kid: String,
+    pub encoding_key: EncodingKey,
+    pub decoding_key: DecodingKey,
+    pub public_key: Vec<u8>,
+    pub activates_at: DateTime<Utc>,
+    pub retires_at: DateTime<Utc>,
+}
+
+static KEY_RING: LazyLock<RwLock<Vec<SigningKey>>> = LazyLock::new(|| RwLock::new(Vec::new()));
+
+fn rotation_interval() -> Duration {
+    let days = env::var("JWT_KEY_ROTATION_DAYS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_ROTATION_DAYS);
+    Duration::days(days)
+}
+
+/// Chave AES-256 (base64) que cifra as chaves privadas no banco, com o `kid` como dado
+/// associado para que uma chave cifrada não possa ser trocada de linha.
+fn encryption_key() -> Result<LessSafeKey, ServiceError> {
+    let key = env::var("JWT_KEY_ENCRYPTION_KEY")
+        .ok()
+        .and_then(|value| general_purpose::STANDARD.decode(value).ok())
+        .ok_or(ServiceError::Crypto(
+            "JWT_KEY_ENCRYPTION_KEY ausente ou inválida",
+        ))?;
+    UnboundKey::new(&AES_256_GCM, &key)
+        .map(LessSafeKey::new)
+        .map_err(|_| ServiceError::Crypto("JWT_KEY_ENCRYPTION_KEY deve ter 32 bytes"))
+}
+
+fn seal_private_key(
+    key: &LessSafeKey,
+    kid: &str,
+    private_key: &[u8],
+) -> Result<(Vec<u8>, Vec<u8>), ServiceError> {
+    let mut nonce = [0u8; NONCE_LEN];
+    SystemRandom::new()
+        .fill(&mut nonce)
+        .map_err(|_| ServiceError::Crypto("falha ao gerar o nonce"))?;
+
+    let mut ciphertext = private_key.to_vec();
+    key.seal_in_place_append_tag(
+        Nonce::assume_unique_for_key(nonce),
+        Aad::from(kid.as_bytes()),
+        &mut ciphertext,
+    )
+    .map_err(|_| ServiceError::Crypto("falha ao cifrar a chave de assinatura"))?;
+    Ok((ciphertext, nonce.to_vec()))
+}
+
+fn open_private_key(
+    key: &LessSafeKey,
+    kid: &str,
+    ciphertext: &[u8],
+    nonce: &[u8],
+) -> Result<Vec<u8>, ServiceError> {
+    let nonce = Nonce::try_assume_unique_for_key(nonce)
+        .map_err(|_| ServiceError::Crypto("nonce da chave de assinatura inválido"))?;
+    let mut plaintext = ciphertext.to_vec();
+    let len = key
+        .open_in_place(nonce, Aad::from(kid.as_bytes()), &mut plaintext)
+        .map_err(|_| ServiceError::Crypto("falha ao decifrar a chave de assinatura"))?
+        .len();
+    plaintext.truncate(len);
+    Ok(plaintext)
+}
+
+pub fn current_signing_key() -> Option<SigningKey> {
+    let now = Utc::now();
+    KEY_RING
+        .read()
+        .ok()?
+        .iter()
+        .filter(|key| key.activates_at <= now && key.retires_at > now)
+        .max_by_key(|key| key.activates_at)
+        .cloned()
+}
+
+pub fn decoding_key(kid: &str) -> Option<DecodingKey> {
+    let now = Utc::now();
+    KEY_RING
+        .read()
+        .ok()?
+        .iter()
+        .find(|key| key.kid == kid && key.retires_at > now)
+        .map(|key| key.decoding_key.clone())
+}
+
+pub fn jwks() -> JwkSet {
+    let now = Utc::now();
+    let keys = KEY_RING
+        .read()
+        .map(|ring| {
+            ring.iter()
+                .filter(|key| key.retires_at > now)
+                .map(|key| Jwk {
+                    common: CommonParameters {
+                        public_key_use: Some(PublicKeyUse::Signature),
+                        key_algorithm: Some(KeyAlgorithm::EdDSA),
+                        key_id: Some(key.kid.clone()),
+                        ..Default::default()
+                    },
+                    algorithm: AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                        key_type: OctetKeyPairType::OctetKeyPair,
+                        curve: EllipticCurve::Ed25519,
+                        x: general_purpose::URL_SAFE_NO_PAD.encode(&key.public_key),
+                    }),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    JwkSet { keys }
+}
+
+pub async fn reload(pool: &PgPool) -> Result<(), ServiceError> {
+    let key = encryption_key()?;
+    let records = sqlx::query_as::<_, SigningKeyRecord>(
+        r#"
+        SELECT kid, private_key, private_key_nonce, public_key, activates_at, retires_at
+        FROM jwt_signing_key
+        WHERE algorithm = $1 AND retires_at > $2
+        ORDER BY activates_at
+        "#,
+    )
+    .bind(ALGORITHM)
+    .bind(Utc::now())
+    .fetch_all(pool)
+    .await?;
+
+    let mut keys = Vec::with_capacity(records.len());
+    for record in records {
+        let nonce = record.private_key_nonce.ok_or(ServiceError::Crypto(
+            "chave de assinatura gravada sem cifrar",
+        ))?;
+        let private_key = open_private_key(&key, &record.kid, &record.private_key, &nonce)?;
+        keys.push(SigningKey {
+            encoding_key: EncodingKey::from_ed_der(&private_key),
+            decoding_key: DecodingKey::from_ed_der(&record.public_key),
+            kid: record.kid,
+            public_key: record.public_key,
+            activates_at: record.activates_at,
+            retires_at: record.retires_at,
+        });
+    }
+
+    if let Ok(mut ring) = KEY_RING.write() {
+        *ring = keys;
+    }
+    Ok(())
+}
+
+/// Garante que exista uma chave ativa e que a próxima já esteja publicada
+/// quando a atual estiver perto de expirar.
+pub async fn rotate_if_due(pool: &PgPool) -> Result<(), ServiceError> {
+    let key = encryption_key()?;
+    let mut tx = pool.begin().await?;
+
+    // Várias instâncias da API podem rodar este job ao mesmo tempo.
+    let (locked,): (bool,) =
+        sqlx::query_as("SELECT pg_try_advisory_xact_lock(hashtext('jwt_signing_key'))")
+            .fetch_one(&mut *tx)
+            .await?;
+    if !locked {
+        return Ok(());
+    }
+
+    let now = Utc::now();
+    let interval = rotation_interval();
+    let (latest_activation,): (Option<DateTime<Utc>>,) = sqlx::query_as(
+        "SELECT MAX(activates_at) FROM jwt_signing_key WHERE algorithm = $1 AND retires_at > $2",
+    )
+    .bind(ALGORITHM)
+    .bind(now)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let next_activation = match latest_activation {
+        None => Some(now),
+        Some(activation) if activation + interval - Duration::hours(PUBLISH_AHEAD_HOURS) <= now => {
+            Some((activation + interval).max(now))
+        }
+        Some(_) => None,
+    };
+
+    if let Some(activates_at) = next_activation {
+        let rng = SystemRandom::new();
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&rng)
+            .map_err(|_| ServiceError::Crypto("falha ao gerar a chave de assinatura"))?;
+        let key_pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref())
+            .map_err(|_| ServiceError::Crypto("chave de assinatura inválida"))?;
+        let retires_at = activates_at + interval + Duration::hours(RETIRE_GRACE_HOURS);
+        let kid = Uuid::new_v4().to_string();
+        let (private_key, nonce) = seal_private_key(&key, &kid, pkcs8.as_ref())?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO jwt_signing_key
+                (kid, algorithm, private_key, private_key_nonce, public_key, activates_at, retires_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+        )
+        .bind(&kid)
+        .bind(ALGORITHM)
+        .bind(private_key)
+        .bind(nonce)
+        .bind(key_pair.public_key().as_ref())
+        .bind(activates_at)
+        .bind(retires_at)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Cifra as chaves gravadas antes de a cifragem existir.
+async fn seal_legacy_keys(pool: &PgPool) -> Result<(), ServiceError> {
+    let key = encryption_key()?;
+    let mut tx = pool.begin().await?;
+    let legacy: Vec<(String, Vec<u8>)> = sqlx::query_as(
+        "SELECT kid, private_key FROM jwt_signing_key WHERE private_key_nonce IS NULL FOR UPDATE",
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    for (kid, private_key) in legacy {
+        let (ciphertext, nonce) = seal_private_key(&key, &kid, &private_key)?;
+        sqlx::query(
+            "UPDATE jwt_signing_key SET private_key = $1, private_key_nonce = $2 WHERE kid = $3",
+        )
+        .bind(ciphertext)
+        .bind(nonce)
+        .bind(&kid)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
+
+pub async fn initialize(pool: &PgPool) -> Result<(), ServiceError> {
+    seal_legacy_keys(pool).await?;
+    rotate_if_due(pool).await?;
+    reload(pool).await
+}
+
+pub fn spawn_rotation(pool: PgPool) {
+    tokio::spawn(async move {
+        let mut interval =
+            tokio::time::interval(std::time::Duration::from_secs(ROTATION_CHECK_MINUTES * 60));
+        loop {
+            interval.tick().await;
+            if let Err(e) = initialize(&pool).await {
+                eprintln!("Falha ao rotacionar chaves JWT: {:?}", e);
+            }
+        }
+    });
+}
@@ -0,0 +1,163 @@
+use serde::Serializer;
+
+/// Sequências repetidas ("111.111.111-11") passam no módulo 11 mas não são documentos.
+fn is_repeated(values: &[u32]) -> bool {
+    values.iter().all(|value| *value == values[0])
+}
+
+fn cpf_check_digit(digits: &[u32]) -> u32 {
+    let len = digits.len();
+    let sum: u32 = digits
+        .iter()
+        .enumerate()
+        .map(|(i, digit)| digit * (len + 1 - i) as u32)
+        .sum();
+    sum * 10 % 11 % 10
+}
+
+/// CPF só com os 11 dígitos, se os verificadores conferem. Aceita com ou sem pontuação.
+pub fn normalize_cpf(value: &str) -> Option<String> {
+    let cleaned: String = value
+        .chars()
+        .filter(|c| !matches!(c, '.' | '-' | ' '))
+        .collect();
+    let digits: Vec<u32> = cleaned
+        .chars()
+        .map(|c| c.to_digit(10))
+        .collect::<Option<_>>()?;
+    if digits.len() != 11 || is_repeated(&digits) {
+        return None;
+    }
+    if cpf_check_digit(&digits[..9]) != digits[9] || cpf_check_digit(&digits[..10]) != digits[10] {
+        return None;
+    }
+    Some(cleaned)
+}
+
+/// Cada caractere vale seu código ASCII menos 48, o que mantém os dígitos e dá às letras
+/// do CNPJ alfanumérico os valores definidos pela Receita (A = 17, B = 18...).
+fn cnpj_check_digit(values: &[u32]) -> u32 {
+    let sum: u32 = values
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, value)| value * (i as u32 % 8 + 2))
+        .sum();
+    match sum % 11 {
+        0 | 1 => 0,
+        rest => 11 - rest,
+    }
+}
+
+/// CNPJ com os 14 caracteres em maiúsculas, se os verificadores conferem. Aceita o formato
+/// numérico e o alfanumérico (raiz e ordem com letras, verificadores sempre numéricos).
+pub fn normalize_cnpj(value: &str) -> Option<String> {
+    let cleaned: String = value
+        .chars()
+        .filter(|c| !matches!(c, '.' | '/' | '-' | ' '))
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    let chars: Vec<char> = cleaned.chars().collect();
+    if chars.len() != 14
+        || !chars[..12].iter().all(|c| c.is_ascii_alphanumeric())
+        || !chars[12..].iter().all(|c| c.is_ascii_digit())
+    {
+        return None;
+    }
+    let values: Vec<u32> = chars.iter().map(|c| *c as u32 - '0' as u32).collect();
+    if is_repeated(&values) {
+        return None;
+    }
+    if cnpj_check_digit(&values[..12]) != values[12]
+        || cnpj_check_digit(&values[..13]) != values[13]
+    {
+        return None;
+    }
+    Some(cleaned)
+}
+
+/// `12345678909` vira `123.456.789-09`. Valores fora do formato canônico voltam como estão.
+pub fn format_cpf(cpf: &str) -> String {
+    if cpf.len() != 11 || !cpf.is_ascii() {
+        return cpf.to_string();
+    }
+    format!("{}.{}.{}-{}", &cpf[..3], &cpf[3..6], &cpf[6..9], &cpf[9..])
+}
+
+/// Para `#[serde(serialize_with)]`: guardado só com dígitos, exibido formatado.
+pub fn serialize_cpf<S: Serializer>(cpf: &str, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format_cpf(cpf))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cpf_valid() {
+        for (input, expected) in [
+            ("529.982.247-25", "52998224725"),
+            ("52998224725", "52998224725"),
+            (" 111.444.777-35 ", "11144477735"),
+            ("111 444 777 35", "11144477735"),
+        ] {
+            assert_eq!(normalize_cpf(input).as_deref(), Some(expected), "{}", input);
+        }
+    }
+
+    #[test]
+    fn cpf_invalid() {
+        for input in [
+            "529.982.247-24",
+            "529.982.247-15",
+            "111.111.111-11",
+            "000.000.000-00",
+            "5299822472",
+            "529982247250",
+            "529.982.247/25",
+            "52998224a25",
+            "",
+        ] {
+            assert_eq!(normalize_cpf(input), None, "{}", input);
+        }
+    }
+
+    #[test]
+    fn cnpj_valid() {
+        for (input, expected) in [
+            ("11.222.333/0001-81", "11222333000181"),
+            ("11222333000181", "11222333000181"),
+            ("12.ABC.345/01DE-35", "12ABC34501DE35"),
+            ("12.abc.345/01de-35", "12ABC34501DE35"),
+        ] {
+            assert_eq!(
+                normalize_cnpj(input).as_deref(),
+                Some(expected),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn cnpj_invalid() {
+        for input in [
+            "11.222.333/0001-80",
+            "12.ABC.345/01DE-36",
+            "00.000.000/0000-00",
+            "11.111.111/1111-11",
+            "12.ABC.345/01DE-3A",
+            "1122233300018",
+            "11.222.333/0001-8$",
+            "",
+        ] {
+            assert_eq!(normalize_cnpj(input), None, "{}", input);
+        }
+    }
+
+    #[test]
+    fn cpf_format() {
+        assert_eq!(format_cpf("52998224725"), "529.982.247-25");
+        assert_eq!(format_cpf("5299822472"), "5299822472");
+    }
+}
@@ -1 +1,3 @@
 pub mod models;
+#[allow(clippy::module_inception)]
+pub mod telegram;
@@ -0,0 +1,15 @@
+use std::env;
+use std::error::Error;
+use teloxide::prelude::*;
+
+pub async fn send_telegram_message(
+    chat_id: i64,
+    text: &str,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let bot_token = env::var("TELEGRAM_BOT_TOKEN")?;
+    let bot = Bot::new(bot_token);
+
+    let message = bot.send_message(ChatId(chat_id), text).await?;
+    println!("Mensagem de Telegram enviada para o chat {}", chat_id);
+    Ok(message.id.0.to_string())
+}
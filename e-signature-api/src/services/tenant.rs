@@ -0,0 +1,245 @@
+use crate::services::policy::DocumentScope;
+use sqlx::{PgConnection, PgPool, Postgres, Transaction};
+
+pub async fn begin(
+    pool: &PgPool,
+    scope: DocumentScope,
+) -> Result<Transaction<'static, Postgres>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("SET LOCAL ROLE e_signature_app")
+        .execute(&mut *tx)
+        .await?;
+    set_scope(&mut tx, scope).await?;
+
+    Ok(tx)
+}
+
+pub async fn set_scope(conn: &mut PgConnection, scope: DocumentScope) -> Result<(), sqlx::Error> {
+    let name = match scope {
+        DocumentScope::All => "all",
+        DocumentScope::Company(_) => "company",
+        DocumentScope::Signer(_) => "signer",
+        DocumentScope::Nothing => "none",
+    };
+
+    sqlx::query(
+        r#"
+        SELECT set_config('app.scope', $1, true),
+               set_config('app.company_id', $2, true),
+               set_config('app.signer_id', $3, true)
+        "#,
+    )
+    .bind(name)
+    .bind(
+        scope
+            .company_id()
+            .map(|id| id.to_string())
+            .unwrap_or_default(),
+    )
+    .bind(
+        scope
+            .signer_id()
+            .map(|id| id.to_string())
+            .unwrap_or_default(),
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Testes de banco: `#[sqlx::test]` cria um banco temporário a partir de `DATABASE_URL`
+/// e aplica `migrations/`. Sem `DATABASE_URL` eles não rodam; o usuário da conexão
+/// precisa poder criar bancos e assumir a role `e_signature_app`.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::documents::services::{get_all_documents, get_document_by_id};
+    use crate::services::users::models::Role;
+    use crate::services::users::services::get_signer_by_id;
+
+    /// Empresa com um documento e um signatário, gravados pelo dono das tabelas (sem RLS).
+    struct Tenant {
+        company_id: i64,
+        document_id: i64,
+        signer_id: i64,
+    }
+
+    async fn insert_tenant(pool: &PgPool, n: i64) -> Tenant {
+        let (user_id,): (i64,) = sqlx::query_as(
+            "INSERT INTO user_account (email, password_hash, role) VALUES ($1, $2, $3) RETURNING user_id",
+        )
+        .bind(format!("empresa{n}@teste.test"))
+        .bind("x".repeat(60))
+        .bind(Role::Company)
+        .fetch_one(pool)
+        .await
+        .unwrap();
+        let (company_id,): (i64,) = sqlx::query_as(
+            r#"
+            INSERT INTO company (legal_name, tax_id, contact_email, user_id)
+            VALUES ($1, $2, $3, $4)
+            RETURNING company_id
+            "#,
+        )
+        .bind(format!("Empresa {n}"))
+        .bind(format!("{n:012}00"))
+        .bind(format!("empresa{n}@teste.test"))
+        .bind(user_id)
+        .fetch_one(pool)
+        .await
+        .unwrap();
+        let (document_id,): (i64,) = sqlx::query_as(
+            r#"
+            INSERT INTO document (company_id, file_name, file_path, hash_sha256, status_id)
+            VALUES ($1, 'contrato.pdf', 'uploads/contrato.pdf', $2, 1)
+            RETURNING document_id
+            "#,
+        )
+        .bind(company_id)
+        .bind("0".repeat(64))
+        .fetch_one(pool)
+        .await
+        .unwrap();
+        let (signer_id,): (i64,) = sqlx::query_as(
+            r#"
+            INSERT INTO signer (full_name, national_id, phone_number, contact_email)
+            VALUES ($1, $2, '+5511999990000', $3)
+            RETURNING signer_id
+            "#,
+        )
+        .bind(format!("Signatário {n}"))
+        .bind(format!("{n:011}"))
+        .bind(format!("signatario{n}@teste.test"))
+        .fetch_one(pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO document_signer (document_id, signer_id, status_id) VALUES ($1, $2, 1)",
+        )
+        .bind(document_id)
+        .bind(signer_id)
+        .execute(pool)
+        .await
+        .unwrap();
+
+        Tenant {
+            company_id,
+            document_id,
+            signer_id,
+        }
+    }
+
+    async fn visible_ids(conn: &mut PgConnection, sql: &str) -> Vec<i64> {
+        sqlx::query_as::<_, (i64,)>(sql)
+            .fetch_all(conn)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(id,)| id)
+            .collect()
+    }
+
+    #[sqlx::test]
+    async fn scoped_queries_only_return_own_company(pool: PgPool) {
+        let a = insert_tenant(&pool, 1).await;
+        let b = insert_tenant(&pool, 2).await;
+        let scope = DocumentScope::Company(a.company_id);
+
+        let documents = get_all_documents(&pool, scope).await.unwrap();
+        assert_eq!(
+            documents.iter().map(|d| d.document_id).collect::<Vec<_>>(),
+            vec![a.document_id]
+        );
+        assert!(get_document_by_id(&pool, scope, a.document_id)
+            .await
+            .unwrap()
+            .is_some());
+        assert!(get_document_by_id(&pool, scope, b.document_id)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(get_signer_by_id(&pool, scope, a.signer_id)
+            .await
+            .unwrap()
+            .is_some());
+        assert!(get_signer_by_id(&pool, scope, b.signer_id)
+            .await
+            .unwrap()
+            .is_none());
+
+        let signer_scope = DocumentScope::Signer(b.signer_id);
+        let documents = get_all_documents(&pool, signer_scope).await.unwrap();
+        assert_eq!(
+            documents.iter().map(|d| d.document_id).collect::<Vec<_>>(),
+            vec![b.document_id]
+        );
+        assert!(get_signer_by_id(&pool, signer_scope, a.signer_id)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[sqlx::test]
+    async fn row_level_security_hides_other_company(pool: PgPool) {
+        let a = insert_tenant(&pool, 1).await;
+        let b = insert_tenant(&pool, 2).await;
+
+        // Consultas sem filtro nenhum: só as políticas de RLS separam as empresas.
+        let mut tx = begin(&pool, DocumentScope::Company(a.company_id))
+            .await
+            .unwrap();
+        let (role,): (String,) = sqlx::query_as("SELECT current_user::TEXT")
+            .fetch_one(&mut *tx)
+            .await
+            .unwrap();
+        assert_eq!(role, "e_signature_app");
+        assert_eq!(
+            visible_ids(&mut tx, "SELECT document_id FROM document").await,
+            vec![a.document_id]
+        );
+        assert_eq!(
+            visible_ids(&mut tx, "SELECT signer_id FROM signer").await,
+            vec![a.signer_id]
+        );
+        assert_eq!(
+            visible_ids(&mut tx, "SELECT document_id FROM document_signer").await,
+            vec![a.document_id]
+        );
+
+        let updated =
+            sqlx::query("UPDATE document SET file_name = 'alterado.pdf' WHERE document_id = $1")
+                .bind(b.document_id)
+                .execute(&mut *tx)
+                .await
+                .unwrap();
+        assert_eq!(updated.rows_affected(), 0);
+        tx.rollback().await.unwrap();
+
+        let mut tx = begin(&pool, DocumentScope::Company(a.company_id))
+            .await
+            .unwrap();
+        let inserted = sqlx::query(
+            r#"
+            INSERT INTO document (company_id, file_name, file_path, hash_sha256, status_id)
+            VALUES ($1, 'intruso.pdf', 'uploads/intruso.pdf', $2, 1)
+            "#,
+        )
+        .bind(b.company_id)
+        .bind("0".repeat(64))
+        .execute(&mut *tx)
+        .await;
+        assert!(inserted.is_err());
+        tx.rollback().await.unwrap();
+
+        let mut tx = begin(&pool, DocumentScope::Nothing).await.unwrap();
+        assert!(visible_ids(&mut tx, "SELECT document_id FROM document")
+            .await
+            .is_empty());
+        assert!(visible_ids(&mut tx, "SELECT signer_id FROM signer")
+            .await
+            .is_empty());
+        tx.rollback().await.unwrap();
+    }
+}
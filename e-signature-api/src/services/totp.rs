@@ -0,0 +1,247 @@
+use crate::services::errors::ServiceError;
+use crate::services::sessions::hash_token;
+use base32::Alphabet;
+use base64::{engine::general_purpose, Engine as _};
+use chrono::Utc;
+use image::{DynamicImage, ImageFormat, Luma};
+use qrcode::QrCode;
+use serde::Serialize;
+use sqlx::PgPool;
+use std::io::Cursor;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const ISSUER: &str = "e-Signature";
+const STEP_SECONDS: u64 = 30;
+const DIGITS: u32 = 6;
+const RECOVERY_CODE_COUNT: usize = 10;
+
+#[derive(Debug, Serialize)]
+pub struct TotpEnrollment {
+    pub secret: String,
+    pub otpauth_uri: String,
+    pub qr_code_png_base64: String,
+}
+
+fn recovery_hash(user_id: i64, code: &str) -> String {
+    hash_token(&format!("{}:{}", user_id, code.trim().to_uppercase()))
+}
+
+fn otpauth_uri(email: &str, secret: &str) -> String {
+    let mut uri = reqwest::Url::parse("otpauth://totp/").expect("valid otpauth base uri");
+    uri.set_path(&format!("{}:{}", ISSUER, email));
+    uri.query_pairs_mut()
+        .append_pair("secret", secret)
+        .append_pair("issuer", ISSUER)
+        .append_pair("algorithm", "SHA1")
+        .append_pair("digits", &DIGITS.to_string())
+        .append_pair("period", &STEP_SECONDS.to_string());
+    uri.to_string()
+}
+
+fn qr_code_png(data: &str) -> Result<Vec<u8>, ServiceError> {
+    let code = QrCode::new(data.as_bytes()).map_err(|e| ServiceError::QrCode(e.to_string()))?;
+    let image = code.render::<Luma<u8>>().min_dimensions(240, 240).build();
+
+    let mut png = Vec::new();
+    DynamicImage::ImageLuma8(image)
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .map_err(|e| ServiceError::QrCode(e.to_string()))?;
+    Ok(png)
+}
+
+/// Devolve o passo de 30s em que o código confere, aceitando um passo de
+/// diferença para compensar relógios fora de sincronia.
+fn matching_step(secret: &[u8], code: &str) -> Option<i64> {
+    let guess: u32 = code.trim().parse().ok()?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+    let current_step = now / STEP_SECONDS;
+
+    [
+        current_step.saturating_sub(1),
+        current_step,
+        current_step + 1,
+    ]
+    .into_iter()
+    .find(|step| {
+        let time = UNIX_EPOCH + Duration::from_secs(step * STEP_SECONDS);
+        otp::totp_validate(
+            secret,
+            time,
+            Duration::from_secs(STEP_SECONDS),
+            guess,
+            DIGITS,
+        )
+    })
+    .map(|step| step as i64)
+}
+
+pub async fn is_enabled(pool: &PgPool, user_id: i64) -> Result<bool, sqlx::Error> {
+    let record: Option<(bool,)> =
+        sqlx::query_as("SELECT totp_enabled FROM user_account WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_optional(pool)
+            .await?;
+    Ok(record.map(|(enabled,)| enabled).unwrap_or(false))
+}
+
+pub async fn begin_enrollment(
+    pool: &PgPool,
+    user_id: i64,
+    email: &str,
+) -> Result<Option<TotpEnrollment>, ServiceError> {
+    if is_enabled(pool, user_id).await? {
+        return Ok(None);
+    }
+
+    let secret_bytes: [u8; 20] = rand::random();
+    let secret = base32::encode(Alphabet::Rfc4648 { padding: false }, &secret_bytes);
+    let uri = otpauth_uri(email, &secret);
+    let png = qr_code_png(&uri)?;
+
+    sqlx::query(
+        "UPDATE user_account SET totp_secret = $1, totp_enabled = FALSE, totp_last_step = NULL WHERE user_id = $2",
+    )
+    .bind(&secret_bytes[..])
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+
+    Ok(Some(TotpEnrollment {
+        secret,
+        otpauth_uri: uri,
+        qr_code_png_base64: general_purpose::STANDARD.encode(png),
+    }))
+}
+
+/// Ativa o segundo fator se o código conferir com o segredo pendente e devolve
+/// os códigos de recuperação em texto puro (só são mostrados esta vez).
+pub async fn confirm_enrollment(
+    pool: &PgPool,
+    user_id: i64,
+    code: &str,
+) -> Result<Option<Vec<String>>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let record: Option<(Option<Vec<u8>>, bool)> = sqlx::query_as(
+        "SELECT totp_secret, totp_enabled FROM user_account WHERE user_id = $1 FOR UPDATE",
+    )
+    .bind(user_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let secret = match record {
+        Some((Some(secret), false)) => secret,
+        _ => return Ok(None),
+    };
+    let step = match matching_step(&secret, code) {
+        Some(step) => step,
+        None => return Ok(None),
+    };
+
+    sqlx::query(
+        "UPDATE user_account SET totp_enabled = TRUE, totp_last_step = $1, updated_at = $2 WHERE user_id = $3",
+    )
+    .bind(step)
+    .bind(Utc::now())
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query("DELETE FROM totp_recovery_code WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    let mut recovery_codes = Vec::with_capacity(RECOVERY_CODE_COUNT);
+    for _ in 0..RECOVERY_CODE_COUNT {
+        let bytes: [u8; 5] = rand::random();
+        let code = base32::encode(Alphabet::Rfc4648 { padding: false }, &bytes);
+
+        sqlx::query("INSERT INTO totp_recovery_code (user_id, code_hash) VALUES ($1, $2)")
+            .bind(user_id)
+            .bind(recovery_hash(user_id, &code))
+            .execute(&mut *tx)
+            .await?;
+        recovery_codes.push(code);
+    }
+
+    tx.commit().await?;
+
+    Ok(Some(recovery_codes))
+}
+
+pub async fn verify_code(pool: &PgPool, user_id: i64, code: &str) -> Result<bool, sqlx::Error> {
+    let record: Option<(Option<Vec<u8>>,)> =
+        sqlx::query_as("SELECT totp_secret FROM user_account WHERE user_id = $1 AND totp_enabled")
+            .bind(user_id)
+            .fetch_optional(pool)
+            .await?;
+
+    let secret = match record {
+        Some((Some(secret),)) => secret,
+        _ => return Ok(false),
+    };
+    let step = match matching_step(&secret, code) {
+        Some(step) => step,
+        None => return Ok(false),
+    };
+
+    // Um código já aceito não pode ser reutilizado dentro da mesma janela.
+    let updated = sqlx::query(
+        r#"
+        UPDATE user_account SET totp_last_step = $1
+        WHERE user_id = $2 AND (totp_last_step IS NULL OR totp_last_step < $1)
+        "#,
+    )
+    .bind(step)
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+
+    Ok(updated.rows_affected() == 1)
+}
+
+pub async fn use_recovery_code(
+    pool: &PgPool,
+    user_id: i64,
+    code: &str,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+        UPDATE totp_recovery_code SET used_at = $1
+        WHERE user_id = $2 AND code_hash = $3 AND used_at IS NULL
+        "#,
+    )
+    .bind(Utc::now())
+    .bind(user_id)
+    .bind(recovery_hash(user_id, code))
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+pub async fn reset(pool: &PgPool, user_id: i64) -> Result<u64, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let result = sqlx::query(
+        r#"
+        UPDATE user_account
+        SET totp_secret = NULL, totp_enabled = FALSE, totp_last_step = NULL, updated_at = $1
+        WHERE user_id = $2 AND deleted_at IS NULL
+        "#,
+    )
+    .bind(Utc::now())
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query("DELETE FROM totp_recovery_code WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(result.rows_affected())
+}
@@ -0,0 +1,82 @@
+use base64::{engine::general_purpose, Engine as _};
+use reqwest::Client;
+use ring::hmac;
+use std::collections::BTreeMap;
+use std::env;
+use std::error::Error;
+use subtle::ConstantTimeEq;
+
+type SendError = Box<dyn Error + Send + Sync>;
+
+fn required_env(name: &str) -> Result<String, SendError> {
+    env::var(name).map_err(|_| format!("{} não está definido", name).into())
+}
+
+/// Envia uma mensagem pela API de Messages da Twilio. `from` e `to` já vêm no
+/// formato do canal (ex.: "whatsapp:+55..." para WhatsApp, "+55..." para SMS).
+pub async fn send_message(from: &str, to: &str, body: &str) -> Result<String, SendError> {
+    let account_sid = required_env("TWILIO_ACCOUNT_SID")?;
+    let auth_token = required_env("TWILIO_AUTH_TOKEN")?;
+
+    let url = format!(
+        "https://api.twilio.com/2010-04-01/Accounts/{}/Messages.json",
+        account_sid
+    );
+
+    let status_callback = status_callback_url();
+    let mut params = vec![("To", to), ("From", from), ("Body", body)];
+    if let Some(callback) = status_callback.as_deref() {
+        params.push(("StatusCallback", callback));
+    }
+
+    let response = Client::new()
+        .post(&url)
+        .basic_auth(account_sid, Some(auth_token))
+        .form(&params)
+        .send()
+        .await?;
+
+    if response.status().is_success() {
+        // Sem o SID não há como casar os callbacks de status com a mensagem.
+        let message: serde_json::Value = response.json().await?;
+        match message["sid"].as_str().filter(|sid| !sid.is_empty()) {
+            Some(sid) => Ok(sid.to_string()),
+            None => Err("Resposta da Twilio sem o SID da mensagem".into()),
+        }
+    } else {
+        let error_text = response.text().await?;
+        Err(format!("Falha no envio pela Twilio: {}", error_text).into())
+    }
+}
+
+pub async fn send_sms_message(to_number: &str, body: &str) -> Result<String, SendError> {
+    let from_number = required_env("TWILIO_SMS_FROM")?;
+    let sid = send_message(&from_number, to_number, body).await?;
+    println!("SMS enviado para {}", to_number);
+    Ok(sid)
+}
+
+/// URL pública do webhook de status (TWILIO_STATUS_CALLBACK_URL). Sem ela a Twilio
+/// não avisa sobre a entrega e as mensagens ficam no status do envio.
+pub fn status_callback_url() -> Option<String> {
+    env::var("TWILIO_STATUS_CALLBACK_URL").ok()
+}
+
+/// Confere o cabeçalho X-Twilio-Signature: HMAC-SHA1 com o auth token sobre a URL
+/// chamada seguida dos parâmetros do formulário ordenados por nome.
+pub fn verify_signature(url: &str, params: &BTreeMap<String, String>, signature: &str) -> bool {
+    let auth_token = match env::var("TWILIO_AUTH_TOKEN") {
+        Ok(token) => token,
+        Err(_) => return false,
+    };
+
+    let mut payload = url.to_string();
+    for (name, value) in params {
+        payload.push_str(name);
+        payload.push_str(value);
+    }
+
+    let key = hmac::Key::new(hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY, auth_token.as_bytes());
+    let expected = general_purpose::STANDARD.encode(hmac::sign(&key, payload.as_bytes()));
+    expected.as_bytes().ct_eq(signature.as_bytes()).into()
+}
@@ -52,7 +52,9 @@ pub struct User {
     pub created_at: DateTime<Utc>,
     pub updated_at: Option<DateTime<Utc>>,
     #[serde(skip)]
+    #[allow(dead_code)]
     pub deleted_at: Option<DateTime<Utc>>,
+    pub is_active: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -64,7 +66,6 @@ pub struct CreateUser {
     pub tax_id: Option<String>,
     pub full_name: Option<String>,
     pub phone_number: Option<String>,
-    pub is_verified: Option<i32>,
     pub national_id: Option<String>,
 }
 
@@ -74,17 +75,19 @@ pub struct UpdateUser {
     pub role: Option<Role>,
 }
 
-#[derive(Serialize, FromRow, Debug)]
-pub struct Signer {
-    pub signer_id: i64,
-    pub full_name: String,
-    pub national_id: String,
-    pub phone_number: String,
-    pub contact_email: String,
-    pub public_key: Option<String>,
-    pub created_at: DateTime<Utc>,
-    pub updated_at: Option<DateTime<Utc>>,
-    pub deleted_at: Option<DateTime<Utc>>,
-    pub user_id: Option<i64>,
-    pub photo_id_url: Option<String>,
+#[derive(Debug, FromRow)]
+pub struct UserContact {
+    pub user_id: i64,
+    pub email: String,
+    pub phone_number: Option<String>,
+    pub telegram_chat_id: Option<i64>,
+    pub notification_channels: Option<Vec<String>>,
+    pub locale: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FaceEnrollment {
+    pub user_id: i64,
+    pub model_version: String,
+    pub enrolled_at: DateTime<Utc>,
 }
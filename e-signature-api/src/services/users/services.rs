@@ -1,43 +1,68 @@
+use crate::services::documents::evidence::{self, EvidenceKind};
 use crate::services::documents::models::Signer;
-use crate::services::users::models::{CreateUser, Role, UpdateUser, User};
-use base64::{engine::general_purpose, Engine as _};
+use crate::services::errors::{self, AppError, ServiceError};
+use crate::services::face::{self, Embedding, FaceError, FaceMatch};
+use crate::services::face_attempts;
+use crate::services::phones;
+use crate::services::policy::DocumentScope;
+use crate::services::tax_ids;
+use crate::services::tenant;
+use crate::services::users::models::{
+    CreateUser, FaceEnrollment, Role, UpdateUser, User, UserContact,
+};
 use bcrypt::{hash, DEFAULT_COST};
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use serde_json::json;
-use sqlx::PgPool;
-use std::fs;
-use std::io::Write;
-use std::process::Command;
-use std::process::Stdio;
+use sqlx::{FromRow, PgPool};
 
-pub async fn create_user(pool: &PgPool, new_user: CreateUser) -> Result<User, sqlx::Error> {
-    let mut tx = pool.begin().await?;
+fn required<T>(value: Option<T>, field: &'static str) -> Result<T, AppError> {
+    value.ok_or_else(|| AppError::unprocessable("request.field_required").with_var("field", field))
+}
+
+pub async fn create_user(pool: &PgPool, new_user: CreateUser) -> Result<User, AppError> {
+    let company = match new_user.role {
+        Role::Company => {
+            let legal_name = required(new_user.legal_name, "legal_name")?;
+            let tax_id = tax_ids::normalize_cnpj(&required(new_user.tax_id, "tax_id")?)
+                .ok_or_else(|| AppError::unprocessable("company.invalid_tax_id"))?;
+            Some((legal_name, tax_id))
+        }
+        _ => None,
+    };
+    let signer = match new_user.role {
+        Role::Signer => {
+            let full_name = required(new_user.full_name, "full_name")?;
+            let phone_number = phones::normalize(&required(new_user.phone_number, "phone_number")?)
+                .ok_or_else(|| AppError::unprocessable("phone.invalid"))?;
+            let national_id =
+                tax_ids::normalize_cpf(&required(new_user.national_id, "national_id")?)
+                    .ok_or_else(|| AppError::unprocessable("signer.invalid_national_id"))?;
+            Some((full_name, phone_number, national_id))
+        }
+        _ => None,
+    };
 
     let password_hash = hash(&new_user.password, DEFAULT_COST)
-        .map_err(|_| sqlx::Error::Protocol("Failed to hash password".into()))?;
+        .map_err(|e| AppError::internal("user.create_failed", e))?;
+
+    let mut tx = pool.begin().await?;
 
     let user = sqlx::query_as!(
         User,
         r#"
         INSERT INTO user_account (email, password_hash, role)
         VALUES ($1, $2, $3)
-        RETURNING user_id, email, password_hash, role as "role: _", created_at as "created_at!", updated_at, deleted_at
+        RETURNING user_id, email, password_hash, role as "role: _", created_at as "created_at!", updated_at, deleted_at, is_active
         "#,
         new_user.email,
         password_hash,
         new_user.role as i32
     )
     .fetch_one(&mut *tx)
-    .await?;
-
-    if new_user.role == Role::Company {
-        let legal_name = new_user
-            .legal_name
-            .ok_or_else(|| sqlx::Error::Protocol("Missing 'legal_name' for company user".into()))?;
-        let tax_id = new_user.tax_id.ok_or_else(|| {
-            sqlx::Error::Protocol("Missing 'tax_id' (CNPJ) for company user".into())
-        })?;
+    .await
+    .map_err(errors::conflict_on_unique("user.email_in_use"))?;
 
+    if let Some((legal_name, tax_id)) = company {
         sqlx::query!(
             r#"
             INSERT INTO company (legal_name, tax_id, contact_email, user_id)
@@ -52,17 +77,7 @@ pub async fn create_user(pool: &PgPool, new_user: CreateUser) -> Result<User, sq
         .await?;
     }
 
-    if new_user.role == Role::Signer {
-        let full_name = new_user
-            .full_name
-            .ok_or_else(|| sqlx::Error::Protocol("Missing 'full_name' for Signer user".into()))?;
-        let phone_number = new_user.phone_number.ok_or_else(|| {
-            sqlx::Error::Protocol("Missing 'phone_number' for signer user".into())
-        })?;
-        let national_id = new_user.national_id.ok_or_else(|| {
-            sqlx::Error::Protocol("Missing 'national_id' (CPF) for signer user".into())
-        })?;
-
+    if let Some((full_name, phone_number, national_id)) = signer {
         sqlx::query!(
             r#"
             INSERT INTO signer (full_name, phone_number, contact_email, user_id, national_id)
@@ -75,7 +90,8 @@ pub async fn create_user(pool: &PgPool, new_user: CreateUser) -> Result<User, sq
             national_id
         )
         .execute(&mut *tx)
-        .await?;
+        .await
+        .map_err(errors::conflict_on_unique("signer.national_id_in_use"))?;
     }
 
     tx.commit().await?;
@@ -87,7 +103,7 @@ pub async fn get_all_users(pool: &PgPool) -> Result<Vec<User>, sqlx::Error> {
     let users = sqlx::query_as!(
         User,
         r#"
-        SELECT user_id, email, password_hash, role as "role: _", created_at as "created_at!", updated_at, deleted_at
+        SELECT user_id, email, password_hash, role as "role: _", created_at as "created_at!", updated_at, deleted_at, is_active
         FROM user_account
         WHERE deleted_at IS NULL
         ORDER BY user_id
@@ -102,7 +118,7 @@ pub async fn get_user_by_id(pool: &PgPool, user_id: i64) -> Result<Option<User>,
     let user = sqlx::query_as!(
         User,
         r#"
-        SELECT user_id, email, password_hash, role as "role: _", created_at as "created_at!", updated_at, deleted_at
+        SELECT user_id, email, password_hash, role as "role: _", created_at as "created_at!", updated_at, deleted_at, is_active
         FROM user_account
         WHERE user_id = $1 AND deleted_at IS NULL
         "#,
@@ -113,25 +129,129 @@ pub async fn get_user_by_id(pool: &PgPool, user_id: i64) -> Result<Option<User>,
     Ok(user)
 }
 
+pub async fn get_user_by_email(pool: &PgPool, email: &str) -> Result<Option<User>, sqlx::Error> {
+    let user = sqlx::query_as!(
+        User,
+        r#"
+        SELECT user_id, email, password_hash, role as "role: _", created_at as "created_at!", updated_at, deleted_at, is_active
+        FROM user_account
+        WHERE email = $1 AND deleted_at IS NULL
+        "#,
+        email
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(user)
+}
+
 pub async fn get_signer_by_id(
     pool: &PgPool,
+    scope: DocumentScope,
     signer_id: i64,
 ) -> Result<Option<Signer>, sqlx::Error> {
+    if scope == DocumentScope::Nothing {
+        return Ok(None);
+    }
+
+    let mut tx = tenant::begin(pool, scope).await?;
     let signer = sqlx::query_as::<_, Signer>(
         r#"
     SELECT photo_id_url, user_id, signer_id, full_name, national_id, phone_number,
            public_key, contact_email, created_at, updated_at, deleted_at
-    FROM signer
+    FROM signer s
     WHERE signer_id = $1 AND deleted_at IS NULL
+      AND ($2::BIGINT IS NULL OR EXISTS (
+            SELECT 1
+            FROM document_signer ds
+            INNER JOIN document d ON d.document_id = ds.document_id
+            WHERE ds.signer_id = s.signer_id AND d.company_id = $2
+          ))
+      AND ($3::BIGINT IS NULL OR signer_id = $3)
     "#,
     )
     .bind(signer_id)
-    .fetch_optional(pool)
+    .bind(scope.company_id())
+    .bind(scope.signer_id())
+    .fetch_optional(&mut *tx)
     .await?;
+    tx.commit().await?;
 
     Ok(signer)
 }
 
+pub async fn get_user_contact(
+    pool: &PgPool,
+    email: &str,
+) -> Result<Option<UserContact>, sqlx::Error> {
+    sqlx::query_as::<_, UserContact>(
+        r#"
+        SELECT u.user_id,
+               u.email,
+               (SELECT s.phone_number FROM signer s
+                WHERE (s.user_id = u.user_id OR s.contact_email = u.email) AND s.deleted_at IS NULL
+                ORDER BY s.user_id NULLS LAST
+                LIMIT 1) AS phone_number,
+               (SELECT t.chat_id FROM telegram_links t
+                WHERE t.email = u.email AND t.confirmed
+                ORDER BY t.confirmed_at DESC NULLS LAST
+                LIMIT 1) AS telegram_chat_id,
+               u.notification_channels,
+               u.locale
+        FROM user_account u
+        WHERE u.email = $1 AND u.deleted_at IS NULL
+        "#,
+    )
+    .bind(email)
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn update_notification_channels(
+    pool: &PgPool,
+    user_id: i64,
+    channels: &[String],
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE user_account SET notification_channels = $1, updated_at = $2 WHERE user_id = $3",
+    )
+    .bind(channels)
+    .bind(Utc::now())
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn update_locale(
+    pool: &PgPool,
+    user_id: i64,
+    locale: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE user_account SET locale = $1, updated_at = $2 WHERE user_id = $3")
+        .bind(locale)
+        .bind(Utc::now())
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn update_password(
+    pool: &PgPool,
+    user_id: i64,
+    new_password: &str,
+) -> Result<(), ServiceError> {
+    let password_hash = hash(new_password, DEFAULT_COST)?;
+
+    sqlx::query("UPDATE user_account SET password_hash = $1, updated_at = $2 WHERE user_id = $3")
+        .bind(password_hash)
+        .bind(Utc::now())
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
 pub async fn update_user(
     pool: &PgPool,
     user_id: i64,
@@ -152,7 +272,7 @@ pub async fn update_user(
         UPDATE user_account
         SET email = $1, role = $2, updated_at = $3
         WHERE user_id = $4
-        RETURNING user_id, email, password_hash, role as "role: _", created_at as "created_at!", updated_at, deleted_at
+        RETURNING user_id, email, password_hash, role as "role: _", created_at as "created_at!", updated_at, deleted_at, is_active
         "#,
         email,
         role as i32,
@@ -178,86 +298,323 @@ pub async fn delete_user(pool: &PgPool, user_id: i64) -> Result<u64, sqlx::Error
     Ok(result.rows_affected())
 }
 
-pub async fn verify_signer_face(
+async fn read_reference_photo(path: &str) -> Result<Vec<u8>, FaceError> {
+    tokio::fs::read(path)
+        .await
+        .map_err(|e| FaceError::InvalidImage(format!("foto de referência: {}", e)))
+}
+
+/// Grava o embedding. Sem `replace`, só grava se o usuário não tiver cadastro válido
+/// para o modelo atual.
+async fn store_face_embedding(
     pool: &PgPool,
-    national_id: &str,
-    live_image_base64: &str,
-) -> Result<Option<bool>, sqlx::Error> {
-    // 1. Busca o signatário
-    let signer_record = sqlx::query!(
+    user_id: i64,
+    embedding: &[f32],
+    model_version: &str,
+    replace: bool,
+) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+    let enrolled_at: Option<(DateTime<Utc>,)> = sqlx::query_as(
         r#"
-        SELECT photo_id_url
-        FROM signer
-        WHERE national_id = $1 AND deleted_at IS NULL
+        UPDATE user_account
+        SET face_embedding = $1, face_embedding_model = $2, face_enrolled_at = $3, updated_at = $3
+        WHERE user_id = $4 AND deleted_at IS NULL
+          AND ($5 OR face_embedding IS NULL OR face_embedding_model IS DISTINCT FROM $2)
+        RETURNING face_enrolled_at
         "#,
-        national_id
     )
+    .bind(face::embedding_to_bytes(embedding))
+    .bind(model_version)
+    .bind(Utc::now())
+    .bind(user_id)
+    .bind(replace)
     .fetch_optional(pool)
     .await?;
 
-    let record = match signer_record {
-        Some(r) => r,
-        None => return Ok(None),
+    Ok(enrolled_at.map(|(enrolled_at,)| enrolled_at))
+}
+
+#[derive(FromRow)]
+struct UserFaceRecord {
+    photo_id_url: Option<String>,
+    face_embedding: Option<Vec<u8>>,
+    face_embedding_model: Option<String>,
+}
+
+/// Cadastra o rosto do usuário a partir da imagem enviada (selfie ou foto do documento)
+/// ou, sem imagem, da foto do documento do signatário vinculado a ele. Com
+/// `require_match`, a imagem enviada passa pela prova de vida e precisa conferir com a
+/// referência atual (cadastro ou foto do documento), para que ninguém troque o rosto
+/// cadastrado por outro.
+pub async fn enroll_face(
+    pool: &PgPool,
+    user_id: i64,
+    image_base64: Option<&str>,
+    require_match: bool,
+) -> Result<Option<FaceEnrollment>, FaceError> {
+    let record = sqlx::query_as::<_, UserFaceRecord>(
+        r#"
+        SELECT (
+            SELECT s.photo_id_url FROM signer s
+            WHERE s.user_id = u.user_id AND s.deleted_at IS NULL AND s.photo_id_url IS NOT NULL
+            ORDER BY s.signer_id DESC
+            LIMIT 1
+        ) AS photo_id_url, u.face_embedding, u.face_embedding_model
+        FROM user_account u
+        WHERE u.user_id = $1 AND u.deleted_at IS NULL
+        "#,
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    let Some(record) = record else {
+        return Ok(None);
     };
 
-    let reference_photo_path = match record.photo_id_url {
-        Some(path) => path,
+    let image = match image_base64 {
+        Some(image_base64) => {
+            let image = face::decode_base64_image(image_base64)?;
+            if require_match {
+                let reference = current_reference(
+                    record.face_embedding.as_deref(),
+                    record.face_embedding_model.as_deref(),
+                    record.photo_id_url.as_deref(),
+                )
+                .await?;
+                match reference {
+                    Some(reference) => {
+                        let face_match = face::verify(reference, image.clone()).await?;
+                        if !face_match.matched {
+                            return Err(FaceError::ReferenceMismatch(face_match));
+                        }
+                    }
+                    None => {
+                        face::verify_liveness(image.clone()).await?;
+                    }
+                }
+            }
+            image
+        }
         None => {
-            return Err(sqlx::Error::Protocol(
-                "Signatário sem foto de referência.".into(),
-            ))
+            read_reference_photo(&record.photo_id_url.ok_or(FaceError::NoReferencePhoto)?).await?
         }
     };
+    enroll_face_image(pool, user_id, image, true).await
+}
 
-    // 2. Lê a imagem de referência e converte para base64
-    let reference_image_bytes = fs::read(&reference_photo_path)
-        .map_err(|e| sqlx::Error::Protocol(format!("Erro ao ler a foto de referência: {}", e)))?;
-    let reference_image_base64 = general_purpose::STANDARD.encode(&reference_image_bytes);
-
-    // 3. Chama script Python
-    let mut child = Command::new("python")
-        .arg("./scripts/compare_faces.py")
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .spawn()
-        .map_err(|e| sqlx::Error::Protocol(format!("Erro ao iniciar Python: {}", e)))?;
-
-    // 4. Envia JSON para o Python via stdin
-    let input_json = json!({
-        "ref_image_base64": reference_image_base64,
-        "live_image_base64": live_image_base64
-    });
-
-    let stdin = child.stdin.as_mut().ok_or_else(|| {
-        sqlx::Error::Protocol("Não foi possível abrir stdin do processo Python.".into())
-    })?;
-    stdin
-        .write_all(input_json.to_string().as_bytes())
-        .map_err(|e| sqlx::Error::Protocol(format!("Erro ao escrever stdin: {}", e)))?;
-
-    // 5. Recebe resultado do Python
-    let output = child
-        .wait_with_output()
-        .map_err(|e| sqlx::Error::Protocol(format!("Erro ao executar Python: {}", e)))?;
-
-    if !output.status.success() {
-        return Err(sqlx::Error::Protocol(format!(
-            "Python retornou erro: {}",
-            String::from_utf8_lossy(&output.stderr)
-        )));
+/// Rosto de referência do usuário: o cadastro, se for do modelo carregado, ou a foto do
+/// documento. `None` se não há nenhum dos dois.
+async fn current_reference(
+    face_embedding: Option<&[u8]>,
+    face_embedding_model: Option<&str>,
+    photo_id_url: Option<&str>,
+) -> Result<Option<Embedding>, FaceError> {
+    let model_version = face::model_version().ok_or(FaceError::NotLoaded)?;
+    if let (Some(bytes), Some(model)) = (face_embedding, face_embedding_model) {
+        if model == model_version {
+            if let Some(embedding) = face::embedding_from_bytes(bytes) {
+                return Ok(Some(embedding));
+            }
+        }
+    }
+    match photo_id_url {
+        Some(path) => Ok(Some(
+            face::compute_embedding(read_reference_photo(path).await?).await?,
+        )),
+        None => Ok(None),
     }
+}
 
-    let output_json: serde_json::Value = serde_json::from_slice(&output.stdout)
-        .map_err(|e| sqlx::Error::Protocol(format!("Erro ao parsear JSON do Python: {}", e)))?;
+/// Cadastra o rosto do usuário a partir de uma imagem já decodificada, como o retrato
+/// recortado do documento de identidade. Sem `replace`, um cadastro válido existente é
+/// mantido e a função devolve `None`.
+pub async fn enroll_face_image(
+    pool: &PgPool,
+    user_id: i64,
+    image: Vec<u8>,
+    replace: bool,
+) -> Result<Option<FaceEnrollment>, FaceError> {
+    let model_version = face::model_version().ok_or(FaceError::NotLoaded)?;
+    let embedding = face::compute_embedding(image).await?;
+
+    let enrolled_at =
+        store_face_embedding(pool, user_id, &embedding, model_version, replace).await?;
+    Ok(enrolled_at.map(|enrolled_at| FaceEnrollment {
+        user_id,
+        model_version: model_version.to_string(),
+        enrolled_at,
+    }))
+}
 
-    let match_result = output_json
-        .get("match")
-        .and_then(|v| v.as_bool())
-        .unwrap_or(false);
+pub async fn delete_face_enrollment(pool: &PgPool, user_id: i64) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+        UPDATE user_account
+        SET face_embedding = NULL, face_embedding_model = NULL, face_enrolled_at = NULL,
+            updated_at = $1
+        WHERE user_id = $2 AND deleted_at IS NULL
+        "#,
+    )
+    .bind(Utc::now())
+    .bind(user_id)
+    .execute(pool)
+    .await?;
 
-    println!("Base64 reference length: {}", reference_image_base64.len());
-    println!("Base64 live image length: {}", live_image_base64.len());
-    println!("Resultado da comparação: {}", match_result);
+    Ok(result.rows_affected())
+}
+
+#[derive(FromRow)]
+struct SignerFaceRecord {
+    signer_id: i64,
+    user_id: Option<i64>,
+    photo_id_url: Option<String>,
+    face_embedding: Option<Vec<u8>>,
+    face_embedding_model: Option<String>,
+}
+
+/// E-mail de contato do signatário de um documento, a quem as provas de OTP da
+/// assinatura são emitidas.
+pub async fn get_document_signer_email(
+    pool: &PgPool,
+    national_id: &str,
+    document_id: i64,
+) -> Result<Option<String>, sqlx::Error> {
+    let Some(national_id) = tax_ids::normalize_cpf(national_id) else {
+        return Ok(None);
+    };
+    let email: Option<(String,)> = sqlx::query_as(
+        r#"
+        SELECT s.contact_email
+        FROM signer s
+        JOIN document_signer ds ON ds.signer_id = s.signer_id
+        WHERE s.national_id = $1 AND s.deleted_at IS NULL AND ds.document_id = $2
+        "#,
+    )
+    .bind(national_id)
+    .bind(document_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(email.map(|(email,)| email))
+}
+
+/// Compara a foto ao vivo com o rosto cadastrado do signatário de `document_id`,
+/// registrando a tentativa no histórico e respeitando o limite de falhas por documento.
+/// O resultado da prova de vida e da comparação entra nas evidências da assinatura.
+pub async fn verify_signer_face(
+    pool: &PgPool,
+    national_id: &str,
+    document_id: i64,
+    live_image_base64: &str,
+) -> Result<Option<FaceMatch>, FaceError> {
+    let Some(national_id) = tax_ids::normalize_cpf(national_id) else {
+        return Ok(None);
+    };
+    let signer_record = sqlx::query_as::<_, SignerFaceRecord>(
+        r#"
+        SELECT s.signer_id, s.user_id, s.photo_id_url, u.face_embedding, u.face_embedding_model
+        FROM signer s
+        LEFT JOIN user_account u ON u.user_id = s.user_id AND u.deleted_at IS NULL
+        WHERE s.national_id = $1 AND s.deleted_at IS NULL
+          AND EXISTS (
+              SELECT 1 FROM document_signer ds
+              WHERE ds.signer_id = s.signer_id AND ds.document_id = $2
+          )
+        "#,
+    )
+    .bind(national_id)
+    .bind(document_id)
+    .fetch_optional(pool)
+    .await?;
+
+    let Some(record) = signer_record else {
+        return Ok(None);
+    };
+    let signer_id = record.signer_id;
+
+    // Tentativas simultâneas do mesmo signatário passam uma de cada vez pela checagem do
+    // limite e pelo registro; sem isso todas passariam antes de a primeira falha contar.
+    let mut tx = pool.begin().await?;
+    sqlx::query("SELECT pg_advisory_xact_lock($1)")
+        .bind(signer_id)
+        .execute(&mut *tx)
+        .await?;
+
+    if let Some(until) = face_attempts::blocked_until(&mut tx, signer_id, Some(document_id)).await?
+    {
+        let blocked = Err(FaceError::TooManyAttempts(until));
+        face_attempts::record(&mut tx, signer_id, Some(document_id), &blocked, None).await?;
+        tx.commit().await?;
+        return blocked.map(Some);
+    }
+
+    let (result, frame) = match face::decode_base64_image(live_image_base64) {
+        Ok(live_image) => (
+            match_enrolled_face(pool, record, live_image.clone()).await,
+            Some(live_image),
+        ),
+        Err(e) => (Err(e), None),
+    };
+    face_attempts::record(
+        &mut tx,
+        signer_id,
+        Some(document_id),
+        &result,
+        frame.as_deref(),
+    )
+    .await?;
+    tx.commit().await?;
+
+    // Falhas técnicas (imagem inválida, sem rosto...) não são evidência.
+    let details = match &result {
+        Ok(face_match) => Some(json!({
+            "outcome": if face_match.matched { "matched" } else { "not_matched" },
+            "result": face_match,
+        })),
+        Err(FaceError::SpoofDetected(liveness)) => Some(json!({
+            "outcome": "spoof_detected",
+            "result": { "model_version": face::model_version(), "liveness": liveness },
+        })),
+        Err(_) => None,
+    };
+    if let Some(details) = details {
+        evidence::record(
+            pool,
+            document_id,
+            signer_id,
+            EvidenceKind::FaceVerification,
+            details,
+        )
+        .await?;
+    }
+
+    result.map(Some)
+}
+
+/// Compara com o cadastro do usuário do signatário. Sem cadastro válido para o modelo
+/// atual, o cadastro é feito uma vez a partir da foto do documento.
+async fn match_enrolled_face(
+    pool: &PgPool,
+    record: SignerFaceRecord,
+    live_image: Vec<u8>,
+) -> Result<FaceMatch, FaceError> {
+    let model_version = face::model_version().ok_or(FaceError::NotLoaded)?;
+
+    let enrolled = match (record.face_embedding, record.face_embedding_model) {
+        (Some(bytes), Some(model)) if model == model_version => face::embedding_from_bytes(&bytes),
+        _ => None,
+    };
+    let reference = match enrolled {
+        Some(embedding) => embedding,
+        None => {
+            let photo_id_url = record.photo_id_url.ok_or(FaceError::NotEnrolled)?;
+            let embedding =
+                face::compute_embedding(read_reference_photo(&photo_id_url).await?).await?;
+            if let Some(user_id) = record.user_id {
+                store_face_embedding(pool, user_id, &embedding, model_version, true).await?;
+            }
+            embedding
+        }
+    };
 
-    Ok(Some(match_result))
+    face::verify(reference, live_image).await
 }